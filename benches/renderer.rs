@@ -0,0 +1,104 @@
+//! Compare the batched TileMapRenderer against the previous sprite based rendering
+//! (one sprite and one draw call per tile).
+//!
+//! Run with `cargo bench --bench renderer`
+
+use retroland::tilemap::{TileMap, TileMapRenderer, Tileset};
+use sfml::graphics::{Color, RenderTarget, RenderTexture, Sprite, Texture, Transformable, View};
+use sfml::system::SfBox;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+const SCREEN_SIZE: (u32, u32) = (1920, 1080);
+const MAP_SIZE: (u32, u32) = (100, 100);
+const FRAMES: u32 = 200;
+
+fn main() {
+    let mut target = RenderTexture::new(SCREEN_SIZE.0, SCREEN_SIZE.1, false)
+        .expect("unable to create render texture");
+
+    let mut textures = BTreeMap::new();
+    for i in 1..=50 {
+        textures.insert(i, Texture::new(16, 16).unwrap());
+    }
+    let tileset = Tileset::from_textures(&textures, (16, 16)).expect("unable to create tileset");
+
+    let mut tile_map = TileMap::new(MAP_SIZE, 2, 1);
+    for y in 0..MAP_SIZE.1 {
+        for x in 0..MAP_SIZE.0 {
+            tile_map.set_tile((x, y), 0, (x + y) % 50 + 1).unwrap();
+            tile_map.set_tile((x, y), 1, (x * y) % 50 + 1).unwrap();
+        }
+    }
+
+    let sprites = build_sprites(&tile_map, &textures, 16.0);
+    let sprites_time = measure(&mut target, |target| {
+        for sprite in &sprites {
+            target.draw(sprite);
+        }
+    });
+
+    let renderer = TileMapRenderer::new(
+        &tile_map,
+        SCREEN_SIZE,
+        (120, 68),
+        View::new((960.0, 540.0).into(), (1920.0, 1080.0).into()),
+        &tileset,
+        false,
+    );
+    let renderer_time = measure(&mut target, |target| target.draw(&renderer));
+
+    println!(
+        "{}x{} map, {} layers, {} frames",
+        MAP_SIZE.0,
+        MAP_SIZE.1,
+        tile_map.layer_count(),
+        FRAMES
+    );
+    report("sprites", sprites_time);
+    report("batched", renderer_time);
+}
+
+/// Build one sprite per tile per layer, the way the renderer used to do
+fn build_sprites<'s>(
+    tile_map: &TileMap,
+    textures: &'s BTreeMap<u32, SfBox<Texture>>,
+    tile_size: f32,
+) -> Vec<Sprite<'s>> {
+    let size = tile_map.size();
+    let mut sprites = Vec::new();
+
+    for layer in 0..tile_map.layer_count() {
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let tile_id = tile_map.get_tile((x, y), layer).unwrap();
+                let mut sprite = Sprite::with_texture(textures.get(&tile_id).unwrap());
+                sprite.set_scale((tile_size / 16.0, tile_size / 16.0));
+                sprite.set_position((x as f32 * tile_size, y as f32 * tile_size));
+                sprites.push(sprite);
+            }
+        }
+    }
+
+    sprites
+}
+
+/// Render FRAMES frames using given draw function and returns the total elapsed time
+fn measure<F: Fn(&mut RenderTexture)>(target: &mut RenderTexture, draw: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        target.clear(Color::BLACK);
+        draw(target);
+        target.display();
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{:>8}: {:>8.2?} total, {:>8.2?} per frame",
+        name,
+        elapsed,
+        elapsed / FRAMES
+    );
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::recent::recent_files_path;
+
+/// The time (in seconds) between an edit and the autosave
+pub const AUTOSAVE_INTERVAL: f32 = 60.0;
+
+/// Autosave keeps track of the edits not autosaved yet
+/// the tile map is autosaved once the interval elapsed since the first of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Autosave {
+    interval: f32,
+    /// the time (in seconds) elapsed since the first edit not autosaved, None if there is none
+    elapsed: Option<f32>,
+}
+
+impl Autosave {
+    /// Create a new autosave using given interval (in seconds)
+    pub fn new(interval: f32) -> Self {
+        Autosave {
+            interval,
+            elapsed: None,
+        }
+    }
+
+    /// Record an edit of the tile map
+    pub fn mark_changed(&mut self) {
+        self.elapsed.get_or_insert(0.0);
+    }
+
+    /// Forget the edits (i.e once the tile map is saved)
+    pub fn clear(&mut self) {
+        self.elapsed = None;
+    }
+
+    /// Advance the autosave by given delta time (in seconds)
+    /// returns true if the tile map should be autosaved now
+    pub fn update(&mut self, dt: f32) -> bool {
+        match &mut self.elapsed {
+            Some(elapsed) => {
+                *elapsed += dt;
+                if *elapsed >= self.interval {
+                    self.elapsed = None;
+                    return true;
+                }
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+/// Retrieve the sidecar file the given tile map file is autosaved to
+/// the untitled tile maps are autosaved in the user home directory (or the current directory)
+pub fn autosave_path(current_file: Option<&Path>) -> PathBuf {
+    match current_file {
+        Some(path) => {
+            let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+            file_name.push(".autosave");
+            path.with_file_name(file_name)
+        }
+        None => recent_files_path()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .unwrap_or_default()
+            .join(".retroland-editor-untitled.autosave"),
+    }
+}
+
+/// Determinate if the autosave of given tile map file should be recovered:
+/// it exists and is more recent than the tile map file
+pub fn has_recovery(current_file: Option<&Path>) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    match modified(&autosave_path(current_file)) {
+        Some(autosaved) => match current_file.and_then(modified) {
+            Some(saved) => autosaved > saved,
+            None => true,
+        },
+        None => false,
+    }
+}
+
+/// Remove the autosave of given tile map file, if any
+pub fn discard_autosave(current_file: Option<&Path>) -> io::Result<()> {
+    match fs::remove_file(autosave_path(current_file)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    /// Write given content to given file, modified at given time
+    fn write_at(path: &Path, content: &str, modified: SystemTime) {
+        fs::write(path, content).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_autosave_update() {
+        let mut autosave = Autosave::new(10.0);
+        assert!(!autosave.update(20.0));
+
+        autosave.mark_changed();
+        assert!(!autosave.update(6.0));
+        // Further edits don't delay the autosave
+        autosave.mark_changed();
+        assert!(autosave.update(6.0));
+        assert!(!autosave.update(20.0));
+
+        autosave.mark_changed();
+        autosave.clear();
+        assert!(!autosave.update(20.0));
+    }
+
+    #[test]
+    fn test_autosave_path() {
+        assert_eq!(
+            autosave_path(Some(Path::new("maps/village.bin"))),
+            PathBuf::from("maps/village.bin.autosave")
+        );
+        assert!(autosave_path(None).ends_with(".retroland-editor-untitled.autosave"));
+    }
+
+    #[test]
+    fn test_has_recovery() {
+        let dir = temp_dir("recovery");
+        let path = dir.path().join("map.bin");
+        let saved = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        write_at(&path, "map", saved);
+        assert!(!has_recovery(Some(&path)));
+
+        // The autosave as old as the tile map file is ignored
+        write_at(&autosave_path(Some(&path)), "autosave", saved);
+        assert!(!has_recovery(Some(&path)));
+
+        // The autosave is more recent than the tile map file
+        let autosaved = saved + Duration::from_secs(1);
+        write_at(&autosave_path(Some(&path)), "autosave", autosaved);
+        assert!(has_recovery(Some(&path)));
+
+        assert!(discard_autosave(Some(&path)).is_ok());
+        assert!(!has_recovery(Some(&path)));
+        assert!(discard_autosave(Some(&path)).is_ok());
+    }
+}
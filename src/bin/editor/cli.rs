@@ -0,0 +1,259 @@
+use sfml::system::Vector2u;
+use std::fmt;
+use std::path::PathBuf;
+
+/// The editor usage, displayed by --help and on invalid arguments
+pub const USAGE: &str = "Usage: editor [OPTIONS] [MAP_FILE]
+
+Edit the tile map saved in MAP_FILE (Ctrl+S), a new tile map is created if the file doesn't exist
+
+Options:
+    --new WIDTHxHEIGHT   create a new tile map of given size (in tiles), even if MAP_FILE exists
+                         (at most 16777216 tiles, i.e 4096x4096)
+    --layers N           the number of layers of a new tile map (default: 2)
+    --fill ID            the tile filling the first layer of a new tile map (default: 3)
+    --assets DIR         the directory the tileset sprites are loaded from (default: assets)
+    --tileset MANIFEST   the tileset manifest, one '<id> <file> <left> <top> <width> <height>
+                         [<footprint width> <footprint height>] [<property>...]' line per tile,
+                         the properties set the layer, walkable, light & light blocking tiles
+                         and the animations (animation=<tile>:<seconds>,...[,pingpong][,per_cell])
+                         (default: the built-in village tileset)
+    -h, --help           display this help";
+
+/// The maximum number of tiles (width * height) of a new tile map
+const MAX_MAP_TILES: u64 = 4096 * 4096;
+
+/// The expected --new value, see MAX_MAP_TILES
+const MAP_SIZE_FORMAT: &str = "WIDTHxHEIGHT of at most 16777216 tiles";
+
+/// Command is the action requested on the command line
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Run the editor using given options
+    Edit(Options),
+    /// Display the usage
+    Help,
+}
+
+/// Options are the editor settings given on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// the tile map file, loaded if it exists and used to save the tile map
+    pub map_file: Option<PathBuf>,
+    /// create a new tile map even if the map file exists
+    pub new_map: bool,
+    /// the size (in tiles) of a new tile map
+    pub map_size: Vector2u,
+    /// the number of layers of a new tile map
+    pub layers: u32,
+    /// the tile filling the first layer of a new tile map
+    pub fill: u32,
+    /// the directory the tileset sprites are loaded from
+    pub assets_dir: PathBuf,
+    /// the tileset manifest, None to use the built-in one
+    pub tileset: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            map_file: None,
+            new_map: false,
+            map_size: Vector2u::new(30, 20),
+            layers: 2,
+            fill: 3,
+            assets_dir: PathBuf::from("assets"),
+            tileset: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    /// The option expects a value
+    MissingValue(String),
+    /// The option value is malformed, with the expected format
+    InvalidValue(String, String, &'static str),
+    /// The option doesn't exist
+    UnknownOption(String),
+    /// Only one tile map file may be given
+    UnexpectedArgument(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::MissingValue(option) => write!(f, "missing value for {}", option),
+            CliError::InvalidValue(option, value, expected) => {
+                write!(
+                    f,
+                    "invalid value '{}' for {}, expected {}",
+                    value, option, expected
+                )
+            }
+            CliError::UnknownOption(option) => write!(f, "unknown option {}", option),
+            CliError::UnexpectedArgument(argument) => {
+                write!(f, "unexpected argument '{}'", argument)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Parse given command line arguments (without the program name)
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, CliError> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .ok_or_else(|| CliError::MissingValue(option.to_string()))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--new" => {
+                let size = value(&arg)?;
+                options.map_size = parse_size(&size)
+                    .ok_or_else(|| CliError::InvalidValue(arg.clone(), size, MAP_SIZE_FORMAT))?;
+                options.new_map = true;
+            }
+            "--layers" => {
+                let layers = value(&arg)?;
+                options.layers = layers
+                    .parse()
+                    .ok()
+                    .filter(|layers| *layers > 0)
+                    .ok_or_else(|| {
+                        CliError::InvalidValue(arg.clone(), layers, "a positive number")
+                    })?;
+            }
+            "--fill" => {
+                let fill = value(&arg)?;
+                options.fill = fill
+                    .parse()
+                    .map_err(|_| CliError::InvalidValue(arg.clone(), fill, "a tile id"))?;
+            }
+            "--assets" => options.assets_dir = PathBuf::from(value(&arg)?),
+            "--tileset" => options.tileset = Some(PathBuf::from(value(&arg)?)),
+            _ if arg.starts_with('-') => return Err(CliError::UnknownOption(arg)),
+            _ if options.map_file.is_some() => return Err(CliError::UnexpectedArgument(arg)),
+            _ => options.map_file = Some(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(Command::Edit(options))
+}
+
+/// Parse given WIDTHxHEIGHT size, None if it is malformed, empty or too large
+fn parse_size(size: &str) -> Option<Vector2u> {
+    let (width, height) = size.split_once('x')?;
+    let size = Vector2u::new(width.parse().ok()?, height.parse().ok()?);
+    let tiles = size.x as u64 * size.y as u64;
+    if tiles == 0 || tiles > MAX_MAP_TILES {
+        return None;
+    }
+
+    Some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse(&[]), Ok(Command::Edit(Options::default())));
+        assert_eq!(parse(&["map.bin", "--help"]), Ok(Command::Help));
+
+        assert_eq!(
+            parse(&[
+                "--new",
+                "40x25",
+                "map.bin",
+                "--layers",
+                "3",
+                "--fill",
+                "1",
+                "--assets",
+                "res",
+                "--tileset",
+                "village.txt",
+            ]),
+            Ok(Command::Edit(Options {
+                map_file: Some(PathBuf::from("map.bin")),
+                new_map: true,
+                map_size: Vector2u::new(40, 25),
+                layers: 3,
+                fill: 1,
+                assets_dir: PathBuf::from("res"),
+                tileset: Some(PathBuf::from("village.txt")),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert_eq!(
+            parse(&["--layers"]),
+            Err(CliError::MissingValue("--layers".to_string()))
+        );
+        assert_eq!(
+            parse(&["--layers", "0"]),
+            Err(CliError::InvalidValue(
+                "--layers".to_string(),
+                "0".to_string(),
+                "a positive number"
+            ))
+        );
+        assert_eq!(
+            parse(&["--new", "40"]),
+            Err(CliError::InvalidValue(
+                "--new".to_string(),
+                "40".to_string(),
+                MAP_SIZE_FORMAT
+            ))
+        );
+        assert_eq!(
+            parse(&["--new", "100000x100000"]),
+            Err(CliError::InvalidValue(
+                "--new".to_string(),
+                "100000x100000".to_string(),
+                MAP_SIZE_FORMAT
+            ))
+        );
+        assert_eq!(
+            parse(&["--fill", "grass"]),
+            Err(CliError::InvalidValue(
+                "--fill".to_string(),
+                "grass".to_string(),
+                "a tile id"
+            ))
+        );
+        assert_eq!(
+            parse(&["--zoom", "2"]),
+            Err(CliError::UnknownOption("--zoom".to_string()))
+        );
+        assert_eq!(
+            parse(&["a.bin", "b.bin"]),
+            Err(CliError::UnexpectedArgument("b.bin".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("30x20"), Some(Vector2u::new(30, 20)));
+        assert_eq!(parse_size("30x0"), None);
+        assert_eq!(parse_size("30"), None);
+        assert_eq!(parse_size("ax20"), None);
+        assert_eq!(parse_size("4096x4096"), Some(Vector2u::new(4096, 4096)));
+        assert_eq!(parse_size("4096x4097"), None);
+        assert_eq!(parse_size("4294967295x4294967295"), None);
+    }
+}
@@ -0,0 +1,175 @@
+use sfml::graphics::{Color, Drawable, FloatRect, RenderStates, RenderTarget};
+use sfml::system::{Vector2f, Vector2u};
+use sfml::window::{Event, Key};
+
+use retroland::font::{append_text, text_size};
+use retroland::geometry::{append_rect, Quads};
+use retroland::tilemap::{LayerBlendMode, RenderBackend, SfmlBackend};
+
+/// The size (in screen pixels) of a font pixel
+const PIXEL_SIZE: f32 = 3.0;
+
+/// The space (in screen pixels) around the confirmation content
+const PADDING: f32 = 20.0;
+
+/// The keys reminder displayed below the message
+const HINT: &str = "Enter: continue, Escape: cancel";
+
+/// ConfirmationStatus is the state of a confirmation after an event
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ConfirmationStatus {
+    /// The user has not answered yet
+    Pending,
+    /// The user accepted (Enter or Y)
+    Confirmed,
+    /// The user refused (Escape or N)
+    Cancelled,
+}
+
+/// Confirmation is a question displayed on top of the editor
+/// (i.e before discarding the unsaved changes)
+pub struct Confirmation {
+    message: String,
+    screen_size: Vector2u,
+    /// the background & text, as a single batch of untextured quads
+    vertices: Quads,
+}
+
+impl Confirmation {
+    /// Create a new confirmation displaying given message ('\n' starts a new line)
+    /// in the center of a screen of given size
+    pub fn new<T: Into<Vector2u>>(message: &str, screen_size: T) -> Self {
+        let mut confirmation = Confirmation {
+            message: message.to_string(),
+            screen_size: screen_size.into(),
+            vertices: Quads::new(),
+        };
+        confirmation.vertices = confirmation.build();
+
+        confirmation
+    }
+
+    /// Retrieve the answer given by given event, if any
+    pub fn handle_event(&self, event: &Event) -> ConfirmationStatus {
+        match *event {
+            Event::KeyPressed {
+                code: Key::Return | Key::Y,
+                ..
+            } => ConfirmationStatus::Confirmed,
+            Event::KeyPressed {
+                code: Key::Escape | Key::N,
+                ..
+            } => ConfirmationStatus::Cancelled,
+            _ => ConfirmationStatus::Pending,
+        }
+    }
+
+    /// Center the confirmation on a screen of given size (i.e when the window is resized)
+    pub fn resize<T: Into<Vector2u>>(&mut self, screen_size: T) {
+        self.screen_size = screen_size.into();
+        self.vertices = self.build();
+    }
+
+    /// Build the confirmation geometry
+    fn build(&self) -> Quads {
+        let mut vertices = Quads::new();
+        let screen = Vector2f::new(self.screen_size.x as f32, self.screen_size.y as f32);
+
+        // Dim the editor behind the confirmation
+        append_rect(
+            &mut vertices,
+            FloatRect::new(0.0, 0.0, screen.x, screen.y),
+            Color::rgba(0, 0, 0, 160),
+        );
+
+        let text = format!("{}\n\n{}", self.message, HINT);
+        let size = text_size(&text, PIXEL_SIZE) + Vector2f::new(PADDING * 2.0, PADDING * 2.0);
+        let (left, top) = ((screen.x - size.x) / 2.0, (screen.y - size.y) / 2.0);
+        append_rect(
+            &mut vertices,
+            FloatRect::new(left, top, size.x, size.y),
+            Color::rgba(44, 62, 80, 240),
+        );
+        append_text(
+            &mut vertices,
+            &text,
+            Vector2f::new(left + PADDING, top + PADDING),
+            PIXEL_SIZE,
+            Color::WHITE,
+        );
+
+        vertices
+    }
+}
+
+impl Drawable for Confirmation {
+    fn draw<'a: 'shader, 'texture, 'shader, 'shader_texture>(
+        &'a self,
+        target: &mut dyn RenderTarget,
+        states: RenderStates<'texture, 'shader, 'shader_texture>,
+    ) {
+        SfmlBackend::untextured(target, states).draw_quads(
+            &self.vertices,
+            false,
+            LayerBlendMode::Alpha,
+            Vector2f::default(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: Key) -> Event {
+        Event::KeyPressed {
+            code,
+            alt: false,
+            ctrl: false,
+            shift: false,
+            system: false,
+        }
+    }
+
+    #[test]
+    fn test_confirmation_handle_event() {
+        let confirmation = Confirmation::new("Discard the changes?", (800, 600));
+
+        for (event, status) in [
+            (key(Key::Return), ConfirmationStatus::Confirmed),
+            (key(Key::Y), ConfirmationStatus::Confirmed),
+            (key(Key::Escape), ConfirmationStatus::Cancelled),
+            (key(Key::N), ConfirmationStatus::Cancelled),
+            (key(Key::A), ConfirmationStatus::Pending),
+            (
+                Event::TextEntered { unicode: 'y' },
+                ConfirmationStatus::Pending,
+            ),
+        ] {
+            assert_eq!(confirmation.handle_event(&event), status);
+        }
+    }
+
+    #[test]
+    fn test_confirmation_resize() {
+        let mut confirmation = Confirmation::new("Discard the changes?", (800, 600));
+        assert_eq!(
+            confirmation.vertices[2].position,
+            Vector2f::new(800.0, 600.0)
+        );
+
+        confirmation.resize((400, 300));
+        assert_eq!(
+            confirmation.vertices[2].position,
+            Vector2f::new(400.0, 300.0)
+        );
+
+        // The message box is centered
+        let (top_left, bottom_right) = (
+            confirmation.vertices[4].position,
+            confirmation.vertices[6].position,
+        );
+        assert_eq!(top_left.x + bottom_right.x, 400.0);
+        assert_eq!(top_left.y + bottom_right.y, 300.0);
+    }
+}
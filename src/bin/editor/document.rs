@@ -0,0 +1,119 @@
+use std::path::{Path, PathBuf};
+
+use crate::autosave::{autosave_path, discard_autosave, Autosave, AUTOSAVE_INTERVAL};
+
+/// Document tracks the file of the edited tile map and its unsaved changes
+pub struct Document {
+    /// the tile map file, None if the tile map has never been saved
+    path: Option<PathBuf>,
+    /// true if the tile map has unsaved changes
+    dirty: bool,
+    autosave: Autosave,
+}
+
+impl Document {
+    /// Create a new document, without unsaved changes, saved to given file
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Document {
+            path,
+            dirty: false,
+            autosave: Autosave::new(AUTOSAVE_INTERVAL),
+        }
+    }
+
+    /// Returns the tile map file, None if the tile map has never been saved
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns true if the tile map has unsaved changes
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Record an edit of the tile map
+    pub fn mark_changed(&mut self) {
+        self.dirty = true;
+        self.autosave.mark_changed();
+    }
+
+    /// Switch to given file, without unsaved changes (i.e once saved, opened or created)
+    /// the autosave of the unsaved changes is discarded
+    pub fn reset(&mut self, path: Option<PathBuf>) {
+        if let Err(e) = discard_autosave(self.path()) {
+            eprintln!("warning: unable to remove the autosave: {}", e);
+        }
+        self.path = path;
+        self.dirty = false;
+        self.autosave.clear();
+    }
+
+    /// Advance the autosave by given delta time (in seconds)
+    /// returns the file the tile map should be autosaved to, if it is time to
+    pub fn update_autosave(&mut self, dt: f32) -> Option<PathBuf> {
+        if self.autosave.update(dt) {
+            Some(autosave_path(self.path()))
+        } else {
+            None
+        }
+    }
+
+    /// Compute the window title: the edited file, followed by '*' if it has unsaved changes
+    pub fn title(&self) -> String {
+        format!(
+            "Retroland Editor - {}{}",
+            self.path()
+                .map_or("untitled".into(), |path| path.to_string_lossy()),
+            if self.dirty { " *" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn test_document_mark_changed() {
+        let mut document = Document::new(Some(PathBuf::from("village.bin")));
+        assert!(!document.is_dirty());
+        assert_eq!(document.title(), "Retroland Editor - village.bin");
+
+        document.mark_changed();
+        assert!(document.is_dirty());
+        assert_eq!(document.title(), "Retroland Editor - village.bin *");
+    }
+
+    #[test]
+    fn test_document_update_autosave() {
+        let mut document = Document::new(Some(PathBuf::from("village.bin")));
+        assert_eq!(document.update_autosave(AUTOSAVE_INTERVAL), None);
+
+        document.mark_changed();
+        assert_eq!(
+            document.update_autosave(AUTOSAVE_INTERVAL),
+            Some(PathBuf::from("village.bin.autosave"))
+        );
+        assert_eq!(document.update_autosave(AUTOSAVE_INTERVAL), None);
+    }
+
+    #[test]
+    fn test_document_reset() {
+        let dir = temp_dir("document");
+        let path = dir.path().join("map.bin");
+        fs::write(autosave_path(Some(&path)), "autosave").unwrap();
+
+        let mut document = Document::new(Some(path.clone()));
+        document.mark_changed();
+        document.reset(None);
+
+        assert_eq!(document.path(), None);
+        assert!(!document.is_dirty());
+        assert_eq!(document.title(), "Retroland Editor - untitled");
+        assert_eq!(document.update_autosave(AUTOSAVE_INTERVAL), None);
+        // The autosave of the previous file is discarded
+        assert!(!autosave_path(Some(&path)).exists());
+    }
+}
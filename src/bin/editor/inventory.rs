@@ -15,27 +15,49 @@ impl<'s> Inventory<'s> {
         screen_size: T,
         textures: &'s BTreeMap<u32, SfBox<Texture>>,
     ) -> Self {
+        let mut background = RectangleShape::new();
+        background.set_fill_color(Color::rgba(44, 62, 80, 240));
+
+        let mut items = Vec::new();
+        let mut items_id = BTreeMap::new();
+        for (i, (id, texture)) in textures.iter().enumerate() {
+            let mut item = RectangleShape::new();
+            item.set_texture(texture, true);
+            items.push(item);
+
+            items_id.insert(i as u32, *id);
+        }
+
+        let mut inventory = Inventory {
+            background,
+            items,
+            items_id,
+        };
+        inventory.resize(screen_size);
+
+        inventory
+    }
+
+    /// Layout the inventory on a screen of given size (i.e when the window is resized)
+    pub fn resize<T: Into<Vector2u>>(&mut self, screen_size: T) {
         let screen_size = screen_size.into();
 
         let bg_border = 50.0; // TODO make 50 something calculated?
-        let mut background = RectangleShape::new();
+        let background = &mut self.background;
         background.set_position((bg_border, bg_border));
         background.set_size(Vector2f::new(
             screen_size.x as f32 - bg_border * 2.0,
             screen_size.y as f32 - bg_border * 2.0,
         ));
-        background.set_fill_color(Color::rgba(44, 62, 80, 240));
 
         // Determinate item size by expecting a certain number of items per inventory row
         let item_per_row = 15;
         let item_size = background.size().x / item_per_row as f32;
         let item_border = item_size / 4.0;
-        let mut items = Vec::new();
         let mut x = 0;
         let mut y = 0;
 
-        let mut items_id = BTreeMap::new();
-        for (i, (id, texture)) in textures.iter().enumerate() {
+        for item in &mut self.items {
             // Determinate if x position will overlaps background and therefore
             // need to do a new line
             if x as f32 * (item_size + item_border) + item_border + item_size
@@ -45,25 +67,14 @@ impl<'s> Inventory<'s> {
                 y += 1;
             }
 
-            let mut item = RectangleShape::new();
             item.set_position(Vector2f::new(
                 background.position().x + (x as f32 * (item_size + item_border) + item_border),
                 background.position().y + (y as f32 * (item_size + item_border) + item_border),
             ));
             item.set_size((item_size, item_size));
-            item.set_texture(texture, true);
-            items.push(item);
-
-            items_id.insert(i as u32, *id);
 
             x += 1;
         }
-
-        Inventory {
-            background,
-            items,
-            items_id,
-        }
     }
 
     /// Get the item located at given position
@@ -112,7 +123,7 @@ mod tests {
         );
         assert_eq!(inventory.items.len(), 50);
 
-        let size = inventory.items.get(0).unwrap().size();
+        let size = inventory.items.first().unwrap().size();
         assert_eq!(size.x as u32, 121);
         assert_eq!(size.y as u32, 121);
         assert_eq!(*inventory.items_id.get(&0).unwrap(), 1);
@@ -145,6 +156,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_inventory_resize() {
+        let textures = load_textures();
+
+        let mut inventory = Inventory::new((1920, 1080), &textures);
+        inventory.resize((960, 540));
+
+        assert_eq!(
+            inventory.background.size(),
+            Vector2f::new(960.0 - 100.0, 540.0 - 100.0)
+        );
+        let size = inventory.items.first().unwrap().size();
+        assert_eq!(size.x as u32, 57);
+        assert_eq!(inventory.get_item_id(Vector2f::new(60.0, 60.0)), None);
+        assert_eq!(inventory.get_item_id(Vector2f::new(80.0, 80.0)).unwrap(), 1);
+        assert_eq!(
+            inventory.get_item_id(Vector2f::new(146.0, 98.0)).unwrap(),
+            2
+        );
+    }
+
     fn load_textures() -> BTreeMap<u32, SfBox<Texture>> {
         let mut textures = BTreeMap::new();
 
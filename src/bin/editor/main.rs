@@ -1,212 +1,605 @@
+mod autosave;
+mod cli;
+mod confirmation;
+mod document;
 mod inventory;
+mod manifest;
+mod prompt;
+mod recent;
+#[cfg(test)]
+mod test_utils;
 
-use sfml::graphics::{Color, IntRect, RenderTarget, RenderWindow, Texture};
-use sfml::system::{Clock, SfBox, Vector2f};
+use sfml::graphics::{Color, FloatRect, RenderTarget, RenderWindow, Texture, View};
+use sfml::system::{Clock, SfBox, Vector2f, Vector2u};
 use sfml::window::mouse::Button;
 use sfml::window::{Event, Key, Style, VideoMode};
 
+use crate::autosave::{autosave_path, discard_autosave, has_recovery};
+use crate::cli::{Command, Options};
+use crate::confirmation::{Confirmation, ConfirmationStatus};
+use crate::document::Document;
 use crate::inventory::Inventory;
-use retroland::tilemap::{TileMap, TileMapRenderer};
+use crate::manifest::{parse_manifest, read_manifest, TileEntry, DEFAULT_MANIFEST};
+use crate::prompt::{Prompt, PromptStatus};
+use crate::recent::{recent_files_path, RecentFiles};
+use retroland::tilemap::{
+    export_to_file, Camera, ExportOptions, GridSettings, Lighting, MapObject, Minimap, TileMap,
+    TileMapRenderer, Tileset, FLIP_HORIZONTAL, FLIP_VERTICAL, TILE_ID_MASK,
+};
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::env;
 use std::error::Error;
+use std::fmt::Display;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process;
 
+/// Load the sprite of each tileset manifest entry from given assets directory
 fn load_textures<P: AsRef<Path>>(
     assets_dir: P,
+    entries: &[TileEntry],
 ) -> Result<BTreeMap<u32, SfBox<Texture>>, Box<dyn Error>> {
     let mut textures = BTreeMap::new();
 
-    let mut idx = 1;
-    for x in 0..5 {
-        textures.insert(
-            idx,
-            Texture::from_file_with_rect(
-                &format!("{}/grass.png", assets_dir.as_ref().display()),
-                &IntRect::new(x * 16, 0, 16, 16),
-            )
-            .ok_or_else(|| "unable to load texture".to_string())?,
-        );
-        idx += 1;
+    for entry in entries {
+        let path = assets_dir.as_ref().join(&entry.file);
+        let texture = Texture::from_file_with_rect(&path.to_string_lossy(), &entry.rect)
+            .ok_or_else(|| format!("unable to load tile {} from {}", entry.id, path.display()))?;
+        textures.insert(entry.id, texture);
     }
 
-    // Start of layer 1 blocks
-    idx = 50;
-    for y in 2..4 {
-        for x in 0..3 {
-            textures.insert(
-                idx,
-                Texture::from_file_with_rect(
-                    &format!("{}/houses.png", assets_dir.as_ref().display()),
-                    &IntRect::new(x * 16, y * 16, 16, 16),
-                )
-                .ok_or_else(|| "unable to load texture".to_string())?,
-            );
-            idx += 1;
-        }
-    }
-    for x in 0..3 {
-        textures.insert(
-            idx,
-            Texture::from_file_with_rect(
-                &format!("{}/markets.png", assets_dir.as_ref().display()),
-                &IntRect::new(x * 16, 2 * 16, 16, 16),
-            )
-            .ok_or_else(|| "unable to load texture".to_string())?,
-        );
-        idx += 1;
+    Ok(textures)
+}
+
+/// Retrieve the footprint of given object, None if the tile is not an object
+fn get_object_footprint(entries: &[TileEntry], tile_id: u32) -> Option<(u32, u32)> {
+    entries
+        .iter()
+        .find(|entry| entry.id == tile_id & TILE_ID_MASK)
+        .and_then(|entry| entry.footprint)
+}
+
+/// Retrieve the tile map layer given tile is placed on, as declared in the tileset manifest
+fn get_tile_layer(entries: &[TileEntry], tile_id: u32) -> u32 {
+    entries
+        .iter()
+        .find(|entry| entry.id == tile_id & TILE_ID_MASK)
+        .map_or(0, |entry| entry.layer)
+}
+
+/// Load the tile map to edit: the map file if it exists (unless a new map is requested),
+/// otherwise a new tile map
+fn load_tile_map(options: &Options) -> Result<TileMap, Box<dyn Error>> {
+    match &options.map_file {
+        Some(path) if !options.new_map && path.exists() => open_tile_map(path),
+        _ => Ok(new_tile_map(options)),
     }
-    for y in 0..4 {
-        textures.insert(
-            idx,
-            Texture::from_file_with_rect(
-                &format!("{}/resources.png", assets_dir.as_ref().display()),
-                &IntRect::new(0, y * 16, 16, 16),
-            )
-            .ok_or_else(|| "unable to load texture".to_string())?,
-        );
-        idx += 1;
+}
+
+/// Create a new tile map using the command line settings
+fn new_tile_map(options: &Options) -> TileMap {
+    TileMap::new(options.map_size, options.layers, options.fill)
+}
+
+/// Read the tile map saved in given file
+fn open_tile_map(path: &Path) -> Result<TileMap, Box<dyn Error>> {
+    let file = File::open(path)
+        .map_err(|e| format!("unable to open tile map {}: {}", path.display(), e))?;
+    let tile_map = TileMap::try_from(file)
+        .map_err(|e| format!("unable to read tile map {}: {:?}", path.display(), e))?;
+    Ok(tile_map)
+}
+
+/// Save given tile map to given file
+fn save_tile_map(tile_map: &TileMap, path: &Path) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)
+        .map_err(|e| format!("unable to create tile map {}: {}", path.display(), e))?;
+    tile_map
+        .write(&file)
+        .map_err(|e| format!("unable to write tile map {}: {:?}", path.display(), e))?;
+    Ok(())
+}
+
+/// The file operations asking for a path
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum FileAction {
+    Open,
+    SaveAs,
+}
+
+/// Create the prompt asking the path of given file operation
+/// the current file is pre-filled and the recent files are suggested
+fn file_prompt(
+    action: FileAction,
+    current_file: Option<&Path>,
+    recent: &RecentFiles,
+    screen_size: Vector2u,
+) -> Prompt {
+    let title = match action {
+        FileAction::Open => "Open (up / down: recent files, escape: cancel)",
+        FileAction::SaveAs => "Save as (up / down: recent files, escape: cancel)",
+    };
+    let text = current_file.map_or("".into(), |path| path.to_string_lossy());
+    let suggestions = recent
+        .paths()
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+
+    Prompt::new(title, &text, suggestions, screen_size)
+}
+
+/// Add given file on top of the recent files, and persist them
+fn remember_file(recent: &mut RecentFiles, path: &Path) {
+    recent.add(path);
+    if let Some(recent_path) = recent_files_path() {
+        if let Err(e) = recent.save(&recent_path) {
+            eprintln!("warning: unable to save the recent files: {}", e);
+        }
     }
-    for x in 0..2 {
-        for y in 0..2 {
-            textures.insert(
-                idx,
-                Texture::from_file_with_rect(
-                    &format!("{}/towers.png", assets_dir.as_ref().display()),
-                    &IntRect::new(x * 16, y * 16 + 16, 16, 16),
-                )
-                .ok_or_else(|| "unable to load texture".to_string())?,
-            );
-            idx += 1;
+}
+
+/// The editor actions needing a confirmation from the user
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum EditorAction {
+    /// Close the editor, discarding the unsaved changes
+    Quit,
+    /// Create a new tile map, discarding the unsaved changes
+    New,
+    /// Open a tile map, discarding the unsaved changes
+    Open,
+    /// Replace the tile map by its autosave (i.e after a crash)
+    Recover,
+}
+
+/// Retrieve the question asked before given action
+fn get_confirmation_message(action: EditorAction) -> &'static str {
+    match action {
+        EditorAction::Quit => "The tile map has unsaved changes.\nQuit anyway?",
+        EditorAction::New | EditorAction::Open => {
+            "The tile map has unsaved changes.\nDiscard them?"
         }
+        EditorAction::Recover => "The tile map has autosaved changes.\nRecover them?",
     }
-    for x in 0..4 {
-        textures.insert(
-            idx,
-            Texture::from_file_with_rect(
-                &format!("{}/wheatfields.png", assets_dir.as_ref().display()),
-                &IntRect::new(x * 16, 0, 16, 16),
-            )
-            .ok_or_else(|| "unable to load texture".to_string())?,
-        );
-        idx += 1;
+}
+
+/// Display given tile map (i.e once opened or created), the camera is centered on it
+fn show_tile_map(
+    tile_map: &TileMap,
+    screen_size: Vector2u,
+    viewport_size: Vector2u,
+    renderer: &mut TileMapRenderer,
+    minimap: &mut Minimap,
+    camera: &mut Camera,
+) {
+    if let Err(e) = renderer.update(tile_map, screen_size, viewport_size) {
+        eprintln!("unable to display the tile map: {:?}", e);
     }
-    for x in 1..4 {
-        textures.insert(
-            idx,
-            Texture::from_file_with_rect(
-                &format!("{}/trees.png", assets_dir.as_ref().display()),
-                &IntRect::new(x * 16, 0, 16, 16),
-            )
-            .ok_or_else(|| "unable to load texture".to_string())?,
-        );
-        idx += 1;
+    renderer.set_selection(vec![]);
+    if minimap.update(tile_map).is_none() {
+        eprintln!("unable to display the tile map overview");
     }
 
-    Ok(textures)
+    let bounds = renderer.world_bounds();
+    camera.set_bounds(Some(bounds));
+    camera.set_position((
+        bounds.left + bounds.width / 2.0,
+        bounds.top + bounds.height / 2.0,
+    ));
+}
+
+/// Display given error and exit
+fn exit_with_error(error: &dyn Display) -> ! {
+    eprintln!("error: {}", error);
+    process::exit(1);
 }
 
-// TODO something better
-fn get_tile_layer(tile_id: u32) -> u32 {
-    if tile_id < 50 {
-        0
-    } else {
-        1
+/// The zoom factor applied on each zoom in / out action
+const ZOOM_FACTOR: f32 = 1.1;
+
+/// The time (in seconds) the camera takes to catch up with the movements
+const CAMERA_SMOOTHING: f32 = 0.08;
+
+/// The space (in tiles) the camera may show around the tile map
+const CAMERA_MARGINS: f32 = 2.0;
+
+/// Display a major grid line (and its coordinate) every GRID_MAJOR_INTERVAL tiles
+const GRID_MAJOR_INTERVAL: u32 = 5;
+
+/// The tile property highlighted by the debug overlay (declared in the tileset manifest)
+const WALKABLE_PROPERTY: &str = "walkable";
+
+/// Build the lighting previewed in the editor from the lights declared in the tileset manifest
+/// the tiles having the blocks_light property block them
+fn manifest_lighting(entries: &[TileEntry]) -> Lighting {
+    let mut lighting = Lighting::default();
+    for entry in entries {
+        if let Some(light) = entry.light {
+            lighting.lights.insert(entry.id, light);
+        }
     }
+    lighting
+}
+
+/// The minimap size, relative to the window height
+const MINIMAP_SIZE: f32 = 0.25;
+
+/// The space (in pixels) between the minimap and the window borders
+const MINIMAP_MARGIN: f32 = 10.0;
+
+/// Compute the screen area of the minimap (top right corner) on a screen of given size
+fn get_minimap_area(screen_size: Vector2u) -> FloatRect {
+    let minimap_size = screen_size.y as f32 * MINIMAP_SIZE;
+    FloatRect::new(
+        screen_size.x as f32 - minimap_size - MINIMAP_MARGIN,
+        MINIMAP_MARGIN,
+        minimap_size,
+        minimap_size,
+    )
+}
+
+/// The opacity of a dimmed layer
+const DIMMED_LAYER_OPACITY: f32 = 0.3;
+
+/// Retrieve the layer controlled by given key (F1 => layer 0, F2 => layer 1, ...)
+fn get_layer_key(code: Key) -> Option<u32> {
+    let keys = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+    ];
+    keys.iter().position(|k| *k == code).map(|v| v as u32)
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let save_file = args.get(1);
-    let map_width = args.get(2).map(|v| v.parse().unwrap()).unwrap_or(30);
-    let map_height = args.get(3).map(|v| v.parse().unwrap()).unwrap_or(20);
+    let options = match cli::parse_args(env::args().skip(1)) {
+        Ok(Command::Edit(options)) => options,
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    let mut tile_map = load_tile_map(&options).unwrap_or_else(|e| exit_with_error(&e));
+    let mut document = Document::new(options.map_file.clone());
 
+    // Remember the opened files
+    let mut recent = recent_files_path()
+        .map(RecentFiles::load)
+        .unwrap_or_default();
+    if let Some(path) = document.path().filter(|path| path.exists()) {
+        remember_file(&mut recent, path);
+    }
+
+    // Load textures
+    let entries = match &options.tileset {
+        Some(path) => read_manifest(path),
+        None => parse_manifest(DEFAULT_MANIFEST),
+    }
+    .unwrap_or_else(|e| exit_with_error(&e));
+    let textures =
+        load_textures(&options.assets_dir, &entries).unwrap_or_else(|e| exit_with_error(&e));
+    let mut tileset = Tileset::from_textures(&textures, (16, 16))
+        .unwrap_or_else(|| exit_with_error(&"unable to create tileset"));
+    for entry in &entries {
+        // The object sprite has been packed in the atlas, it only needs to be re-anchored
+        if let (Some(footprint), Some(rect)) = (entry.footprint, tileset.tile_rect(entry.id)) {
+            tileset.add_object(entry.id, rect, footprint);
+        }
+        for property in &entry.properties {
+            tileset.set_tile_property(entry.id, property, true);
+        }
+        if let Some(animation) = &entry.animation {
+            tileset.add_animation(entry.id, animation.clone());
+        }
+    }
+    let lighting = manifest_lighting(&entries);
+
+    let mut title = document.title();
     let mut window = RenderWindow::new(
         VideoMode::desktop_mode(),
-        "Retroland Editor",
+        &title,
         Style::DEFAULT,
         &Default::default(),
     );
     window.set_vertical_sync_enabled(true);
 
-    let mut tile_map = TileMap::new((map_width, map_height), 2, 3);
-    // Try to load tile map from file
-    if let Some(save_file) = save_file {
-        let file = File::open(save_file);
-        if let Ok(file) = file {
-            tile_map = TileMap::try_from(file).unwrap();
-        }
-    }
-
-    // Load textures
-    let textures = load_textures("assets").expect("unable to load textures");
-
     // Create inventory
     let mut show_inventory = false;
-    let inventory = Inventory::new(window.size(), &textures);
+    let mut inventory = Inventory::new(window.size(), &textures);
 
     // Create tile map
-    let mut viewport_size = (15, 15).into();
+    let viewport_size = (15, 15).into();
     let mut renderer = TileMapRenderer::new(
         &tile_map,
         window.size(),
         viewport_size,
         window.default_view().to_owned(),
-        &textures,
+        &tileset,
         true,
     );
+    renderer.set_grid_settings(GridSettings {
+        color: Color::rgba(0, 0, 0, 96),
+        major_interval: GRID_MAJOR_INTERVAL,
+        show_labels: true,
+        ..Default::default()
+    });
+    if !renderer.unknown_tiles().is_empty() {
+        eprintln!(
+            "warning: the tile map contains unknown tiles: {:?}",
+            renderer.unknown_tiles()
+        );
+    }
+
+    // Keep the camera around the tile map
+    let mut camera = Camera::new(renderer.center());
+    camera.set_smoothing(CAMERA_SMOOTHING);
+    camera.set_bounds(Some(renderer.world_bounds()));
+    camera.set_margins(renderer.tile_size() * CAMERA_MARGINS);
+
+    // Display an overview of the tile map in the top right corner
+    let mut show_minimap = true;
+    let mut minimap = Minimap::new(&tile_map, &tileset, get_minimap_area(window.size()))
+        .unwrap_or_else(|| exit_with_error(&"unable to create minimap"));
 
-    let mut tile_id = 2 as u32;
+    let mut tile_id = 2_u32;
     let mut delta_clock = Clock::default();
     let mut offset = Vector2f::default();
+    let mut selection_start = None;
+    let mut prompt: Option<(FileAction, Prompt)> = None;
+
+    // Offer to recover the changes autosaved before the editor was closed
+    let mut confirmation: Option<(EditorAction, Confirmation)> = None;
+    if !options.new_map && has_recovery(document.path()) {
+        let action = EditorAction::Recover;
+        let question = Confirmation::new(get_confirmation_message(action), window.size());
+        confirmation = Some((action, question));
+    }
+
     while window.is_open() {
         let delta_time = delta_clock.restart();
         let move_factor = 2000.0 * delta_time.as_seconds();
+        let mut requested = None;
+        let mut approved = None;
 
         while let Some(event) = window.poll_event() {
             if let Event::Closed = event {
-                window.close();
+                requested = Some(EditorAction::Quit);
             }
 
-            if let Event::KeyPressed { code, ctrl, .. } = event {
+            // Relayout everything to the new window size instead of stretching the display
+            if let Event::Resized { width, height } = event {
+                let screen_size = Vector2u::new(width, height);
+                let view = View::from_rect(&FloatRect::new(0.0, 0.0, width as f32, height as f32));
+                window.set_view(&view);
+
+                renderer.resize(screen_size, viewport_size, view);
+                camera.set_position(renderer.center());
+                camera.set_bounds(Some(renderer.world_bounds()));
+                camera.set_margins(renderer.tile_size() * CAMERA_MARGINS);
+                inventory.resize(screen_size);
+                minimap.set_area(get_minimap_area(screen_size));
+                if let Some((_, file_prompt)) = &mut prompt {
+                    file_prompt.resize(screen_size);
+                }
+                if let Some((_, question)) = &mut confirmation {
+                    question.resize(screen_size);
+                }
+            }
+
+            // The confirmation captures the keyboard & mouse while displayed
+            if let Some((action, question)) = &confirmation {
+                let action = *action;
+                match question.handle_event(&event) {
+                    ConfirmationStatus::Pending => {}
+                    ConfirmationStatus::Confirmed => {
+                        confirmation = None;
+                        approved = Some(action);
+                    }
+                    ConfirmationStatus::Cancelled => {
+                        confirmation = None;
+                        if action == EditorAction::Recover {
+                            if let Err(e) = discard_autosave(document.path()) {
+                                eprintln!("warning: unable to remove the autosave: {}", e);
+                            }
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // The prompt captures the keyboard & mouse while displayed
+            if let Some((action, file_prompt)) = &mut prompt {
+                let action = *action;
+                match file_prompt.handle_event(&event) {
+                    PromptStatus::Pending => {}
+                    PromptStatus::Cancelled => prompt = None,
+                    PromptStatus::Submitted(path) => {
+                        prompt = None;
+                        let path = PathBuf::from(path);
+                        let result = match action {
+                            FileAction::Open => open_tile_map(&path).map(|opened| {
+                                tile_map = opened;
+                                show_tile_map(
+                                    &tile_map,
+                                    window.size(),
+                                    viewport_size,
+                                    &mut renderer,
+                                    &mut minimap,
+                                    &mut camera,
+                                );
+                            }),
+                            FileAction::SaveAs => save_tile_map(&tile_map, &path),
+                        };
+
+                        match result {
+                            Ok(_) => {
+                                remember_file(&mut recent, &path);
+                                document.reset(Some(path));
+                            }
+                            Err(e) => eprintln!("error: {}", e),
+                        }
+                    }
+                }
+                continue;
+            }
+
+            if let Event::KeyPressed {
+                code, ctrl, shift, ..
+            } = event
+            {
                 match code {
                     // Zoom control
                     Key::Add => {
-                        // prevent having a 0 viewport size (will cause crash)
-                        if viewport_size.x != 1 {
-                            viewport_size = (viewport_size.x - 1, viewport_size.y - 1).into();
-                        }
+                        let mouse_pos =
+                            window.map_pixel_to_coords_current_view(window.mouse_position());
+                        camera.zoom_at(ZOOM_FACTOR, mouse_pos, &mut renderer);
                     }
                     Key::Subtract => {
-                        viewport_size = (viewport_size.x + 1, viewport_size.y + 1).into();
+                        let mouse_pos =
+                            window.map_pixel_to_coords_current_view(window.mouse_position());
+                        camera.zoom_at(1.0 / ZOOM_FACTOR, mouse_pos, &mut renderer);
                     }
-                    // Other controls
-                    Key::S => {
-                        if ctrl {
-                            if let Some(save_file) = save_file {
-                                let file = File::create(save_file).unwrap();
-                                tile_map.write(&file).unwrap();
+                    // File controls
+                    Key::S if ctrl => {
+                        match document.path().map(Path::to_path_buf) {
+                            Some(path) if !shift => match save_tile_map(&tile_map, &path) {
+                                Ok(_) => document.reset(Some(path)),
+                                Err(e) => eprintln!("error: {}", e),
+                            },
+                            _ => {
+                                let action = FileAction::SaveAs;
+                                let file_prompt =
+                                    file_prompt(action, document.path(), &recent, window.size());
+                                prompt = Some((action, file_prompt));
+                                offset = Vector2f::default();
                             }
-                            continue; // no further processing
                         }
+                        continue; // no further processing
+                    }
+                    Key::O if ctrl => {
+                        requested = Some(EditorAction::Open);
+                        continue; // no further processing
+                    }
+                    Key::N if ctrl => {
+                        requested = Some(EditorAction::New);
+                        continue; // no further processing
+                    }
+                    Key::P if ctrl => {
+                        // Export the whole tile map next to the save file
+                        let path = format!(
+                            "{}.png",
+                            document
+                                .path()
+                                .map_or("map".into(), |p| p.to_string_lossy())
+                        );
+                        match export_to_file(&tile_map, &tileset, &ExportOptions::default(), &path)
+                        {
+                            Ok(_) => println!("tile map exported to {}", path),
+                            Err(e) => eprintln!("unable to export tile map: {:?}", e),
+                        }
+                        continue; // no further processing
+                    }
+                    // Flip the selected tile
+                    Key::H => {
+                        tile_id ^= FLIP_HORIZONTAL;
+                    }
+                    Key::V => {
+                        tile_id ^= FLIP_VERTICAL;
                     }
                     Key::E => {
                         show_inventory = !show_inventory;
                     }
+                    Key::N => {
+                        let preview = match renderer.lighting() {
+                            Some(_) => None,
+                            None => Some(lighting.clone()),
+                        };
+                        renderer.set_lighting(preview);
+                    }
+                    Key::M => {
+                        show_minimap = !show_minimap;
+                    }
                     Key::X => {
                         renderer.set_show_grid(!renderer.show_grid());
                     }
+                    Key::Escape => {
+                        renderer.set_selection(vec![]);
+                    }
+                    // Debug overlays
+                    Key::I | Key::L | Key::C | Key::W => {
+                        let mut settings = renderer.debug_settings().clone();
+                        match code {
+                            Key::I => settings.show_tile_ids = !settings.show_tile_ids,
+                            Key::L => settings.show_layers = !settings.show_layers,
+                            Key::C => settings.show_chunks = !settings.show_chunks,
+                            _ => {
+                                settings.property = match settings.property {
+                                    Some(_) => None,
+                                    None => Some(WALKABLE_PROPERTY.to_string()),
+                                }
+                            }
+                        }
+                        renderer.set_debug_settings(settings);
+                    }
                     _ => {}
                 }
 
-                // Re create the renderer with updated details
-                if code == Key::Add || code == Key::Subtract {
-                    renderer.update(&tile_map, window.size(), viewport_size);
+                // Layer display controls: F<n> toggle the layer visibility, Shift+F<n> dim it
+                if let Some(layer) = get_layer_key(code) {
+                    if let Some(settings) = tile_map.layer_settings(layer) {
+                        let mut settings = *settings;
+                        if shift {
+                            settings.opacity = if settings.opacity < 1.0 {
+                                1.0
+                            } else {
+                                DIMMED_LAYER_OPACITY
+                            };
+                        } else {
+                            settings.visible = !settings.visible;
+                        }
+
+                        if let Err(e) = tile_map.set_layer_settings(layer, settings) {
+                            eprintln!("unable to update the layer {}: {:?}", layer, e);
+                        }
+                    }
                 }
             }
 
+            // Zoom to cursor using the mouse wheel
+            if let Event::MouseWheelScrolled { delta, x, y, .. } = event {
+                let mouse_pos = window.map_pixel_to_coords_current_view((x, y).into());
+                camera.zoom_at(ZOOM_FACTOR.powf(delta), mouse_pos, &mut renderer);
+            }
+
+            // Select a rectangle of cells by dragging with the right button
+            if let Event::MouseButtonPressed {
+                button: Button::Right,
+                x,
+                y,
+            } = event
+            {
+                let world_pos = window.map_pixel_to_coords_current_view((x, y).into());
+                selection_start = renderer.get_tile_position(world_pos);
+            }
+            if let Event::MouseButtonReleased {
+                button: Button::Right,
+                ..
+            } = event
+            {
+                selection_start = None;
+            }
+
             // Manage click event
             if Button::Left.is_pressed() {
                 let world_pos = window.map_pixel_to_coords_current_view(window.mouse_position());
@@ -216,15 +609,37 @@ fn main() {
                         tile_id = item_id;
                         show_inventory = false; // hide inventory if an item has been selected
                     }
+                } else if show_minimap && minimap.contains(world_pos) {
+                    // Jump the view to the clicked location
+                    if let Some(position) = minimap.world_position(world_pos) {
+                        camera.set_position(position);
+                    }
                 } else if let Some(map_position) = renderer.get_tile_position(world_pos) {
-                    let layer = get_tile_layer(tile_id);
-                    tile_map.set_tile(map_position, layer, tile_id).unwrap();
-                    renderer.set_tile(map_position, layer, tile_id);
-
-                    if layer == 0 {
-                        // If layer is 0 then reset all tile layers on the position
-                        tile_map.set_tile(map_position, 1, 0).unwrap();
-                        renderer.set_tile(map_position, 1, 0);
+                    let layer = get_tile_layer(&entries, tile_id);
+                    if let Some(footprint) = get_object_footprint(&entries, tile_id) {
+                        // Objects replace the objects they overlap, and the tile map
+                        // is left unchanged if they don't fit in it
+                        let object = MapObject::new(tile_id, map_position, layer, footprint);
+                        if let Err(e) = tile_map.place_object(object) {
+                            eprintln!("unable to place the object {}: {:?}", tile_id, e);
+                        }
+                    } else {
+                        let mut result = tile_map.set_tile(map_position, layer, tile_id);
+
+                        // The object covering the position is erased as a whole
+                        tile_map.remove_object_at(map_position, layer);
+
+                        if layer == 0 {
+                            // If layer is 0 then reset all tile layers on the position
+                            for upper in 1..tile_map.layer_count() {
+                                result =
+                                    result.and_then(|_| tile_map.set_tile(map_position, upper, 0));
+                                tile_map.remove_object_at(map_position, upper);
+                            }
+                        }
+                        if let Err(e) = result {
+                            eprintln!("unable to set the tile {}: {:?}", tile_id, e);
+                        }
                     }
                 }
             }
@@ -247,13 +662,122 @@ fn main() {
                 offset.x = 0.0;
             }
         }
-        renderer.move_(offset);
+
+        // Ask for a confirmation before discarding the unsaved changes
+        if let Some(action) = requested {
+            if document.is_dirty() {
+                let question = Confirmation::new(get_confirmation_message(action), window.size());
+                confirmation = Some((action, question));
+                offset = Vector2f::default();
+            } else {
+                approved = Some(action);
+            }
+        }
+        match approved {
+            Some(EditorAction::Quit) => {
+                // The unsaved changes are discarded, along with their autosave
+                if document.is_dirty() {
+                    document.reset(None);
+                }
+                window.close();
+            }
+            Some(EditorAction::New) => {
+                tile_map = new_tile_map(&options);
+                show_tile_map(
+                    &tile_map,
+                    window.size(),
+                    viewport_size,
+                    &mut renderer,
+                    &mut minimap,
+                    &mut camera,
+                );
+                document.reset(None);
+            }
+            Some(EditorAction::Open) => {
+                let action = FileAction::Open;
+                let file_prompt = file_prompt(action, document.path(), &recent, window.size());
+                prompt = Some((action, file_prompt));
+                offset = Vector2f::default();
+            }
+            Some(EditorAction::Recover) => match open_tile_map(&autosave_path(document.path())) {
+                Ok(recovered) => {
+                    tile_map = recovered;
+                    show_tile_map(
+                        &tile_map,
+                        window.size(),
+                        viewport_size,
+                        &mut renderer,
+                        &mut minimap,
+                        &mut camera,
+                    );
+                    document.mark_changed();
+                }
+                Err(e) => eprintln!("error: {}", e),
+            },
+            None => {}
+        }
+
+        // Display the tile map changes made this frame
+        let changes = tile_map.take_changes();
+        if !changes.is_empty() {
+            document.mark_changed();
+        }
+
+        // Autosave the unsaved changes, so they can be recovered if the editor is not closed properly
+        if let Some(path) = document.update_autosave(delta_time.as_seconds()) {
+            if let Err(e) = save_tile_map(&tile_map, &path) {
+                eprintln!("warning: unable to autosave the tile map: {}", e);
+            }
+        }
+        if let Err(e) = renderer.apply_changes(&tile_map, &changes) {
+            eprintln!("unable to display the tile map changes: {:?}", e);
+        }
+        minimap.apply_changes(&tile_map, &changes);
+
+        // Keep the same on screen speed whatever the zoom level is
+        camera.move_(offset / camera.zoom_level());
+        camera.update(delta_time.as_seconds(), &mut renderer);
+        renderer.advance(delta_time.as_seconds());
+        minimap.update_view(&renderer);
+
+        // Highlight the cell under the cursor and preview the selected tile on it
+        let mouse_pos = window.map_pixel_to_coords_current_view(window.mouse_position());
+        let hover = if show_inventory
+            || prompt.is_some()
+            || confirmation.is_some()
+            || (show_minimap && minimap.contains(mouse_pos))
+        {
+            None
+        } else {
+            renderer.get_tile_position(mouse_pos)
+        };
+        renderer.set_hover(hover);
+        renderer.set_ghost(hover, &[((0, 0).into(), tile_id)]);
+        if let (Some(start), Some(end)) = (selection_start, hover) {
+            renderer.set_selection_rect(start, end);
+        }
 
         window.clear(Color::BLACK);
         window.draw(&renderer);
+        if show_minimap {
+            window.draw(&minimap);
+        }
         if show_inventory {
             window.draw(&inventory);
         }
+        if let Some((_, file_prompt)) = &prompt {
+            window.draw(file_prompt);
+        }
+        if let Some((_, question)) = &confirmation {
+            window.draw(question);
+        }
+
+        // Display the edited file and whether it has unsaved changes
+        let new_title = document.title();
+        if new_title != title {
+            window.set_title(&new_title);
+            title = new_title;
+        }
         window.display();
     }
 }
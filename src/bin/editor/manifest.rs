@@ -0,0 +1,382 @@
+use retroland::tilemap::{Animation, AnimationFrame, AnimationMode, AnimationPhase, LightSource};
+use sfml::graphics::{Color, IntRect};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The tileset used when no manifest is given
+pub const DEFAULT_MANIFEST: &str = include_str!("default_tileset.txt");
+
+/// TileEntry is a tileset manifest line: a tile, its sprite, its footprint and its properties
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileEntry {
+    pub id: u32,
+    /// the sprite file, relative to the assets directory
+    pub file: String,
+    /// the sprite area in the file
+    pub rect: IntRect,
+    /// the footprint (in tiles) of the multi-cell objects, None for the plain tiles
+    pub footprint: Option<(u32, u32)>,
+    /// the tile map layer the tile is placed on
+    pub layer: u32,
+    /// the tileset properties of the tile (i.e blocks_light)
+    pub properties: Vec<String>,
+    /// the light emitted by the tile, if any
+    pub light: Option<LightSource>,
+    /// the animation displayed by the tile, if any
+    pub animation: Option<Animation>,
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    /// The manifest file (path) cannot be read
+    ReadError(String, io::Error),
+    /// The manifest line (starting at 1) is malformed
+    InvalidLine(usize, String),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::ReadError(path, e) => {
+                write!(f, "unable to read tileset manifest {}: {}", path, e)
+            }
+            ManifestError::InvalidLine(line, content) => write!(
+                f,
+                "invalid tileset manifest line {}: '{}' (expected: <id> <file> <left> <top> <width> <height> [<footprint width> <footprint height>] [<property> | layer=<layer> | light=<r>,<g>,<b>,<radius>,<intensity> | animation=<tile>:<seconds>,...[,pingpong][,per_cell]]...)",
+                line, content
+            ),
+        }
+    }
+}
+
+impl Error for ManifestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ManifestError::ReadError(_, e) => Some(e),
+            ManifestError::InvalidLine(..) => None,
+        }
+    }
+}
+
+/// Parse the tileset manifest at given path
+pub fn read_manifest<P: AsRef<Path>>(path: P) -> Result<Vec<TileEntry>, ManifestError> {
+    let content = fs::read_to_string(&path)
+        .map_err(|e| ManifestError::ReadError(path.as_ref().display().to_string(), e))?;
+    parse_manifest(&content)
+}
+
+/// Parse given tileset manifest, one tile per line
+/// the blank lines and the lines starting with '#' are ignored
+/// the animation frames must be tiles declared in the manifest
+pub fn parse_manifest(content: &str) -> Result<Vec<TileEntry>, ManifestError> {
+    let mut lines = Vec::new();
+    let mut entries = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry =
+            parse_entry(line).ok_or_else(|| ManifestError::InvalidLine(i + 1, line.to_string()))?;
+        lines.push((i + 1, line));
+        entries.push(entry);
+    }
+
+    let ids: BTreeSet<u32> = entries.iter().map(|entry| entry.id).collect();
+    for ((line, content), entry) in lines.into_iter().zip(&entries) {
+        let unknown_frame = entry.animation.iter().any(|animation| {
+            animation
+                .frames
+                .iter()
+                .any(|frame| !ids.contains(&frame.tile_id))
+        });
+        if unknown_frame {
+            return Err(ManifestError::InvalidLine(line, content.to_string()));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Parse given manifest line, None if it is malformed
+fn parse_entry(line: &str) -> Option<TileEntry> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 6 {
+        return None;
+    }
+
+    let rect = IntRect::new(
+        fields[2].parse().ok()?,
+        fields[3].parse().ok()?,
+        fields[4].parse().ok()?,
+        fields[5].parse().ok()?,
+    );
+    if rect.width <= 0 || rect.height <= 0 {
+        return None;
+    }
+    // The footprint is made of the two numbers following the sprite area
+    let footprint = match fields.get(6..8) {
+        Some([width, height]) if width.parse::<u32>().is_ok() => {
+            Some((width.parse().ok()?, height.parse().ok()?))
+        }
+        _ => None,
+    };
+    if matches!(footprint, Some((width, height)) if width == 0 || height == 0) {
+        return None;
+    }
+
+    let mut properties = Vec::new();
+    let mut layer = 0;
+    let mut light = None;
+    let mut animation = None;
+    let first_property = if footprint.is_some() { 8 } else { 6 };
+    for field in &fields[first_property..] {
+        match field.split_once('=') {
+            Some(("layer", value)) => layer = value.parse().ok()?,
+            Some(("light", value)) => light = Some(parse_light(value)?),
+            Some(("animation", value)) => animation = Some(parse_animation(value)?),
+            Some(_) => return None,
+            // The property names are words, i.e not a misplaced footprint
+            None if !field.starts_with(char::is_alphabetic) => return None,
+            None => properties.push(field.to_string()),
+        }
+    }
+
+    Some(TileEntry {
+        id: fields[0].parse().ok()?,
+        file: fields[1].to_string(),
+        rect,
+        footprint,
+        layer,
+        properties,
+        light,
+        animation,
+    })
+}
+
+/// Parse given light definition (<r>,<g>,<b>,<radius>,<intensity>), None if it is malformed
+fn parse_light(value: &str) -> Option<LightSource> {
+    let fields: Vec<&str> = value.split(',').collect();
+    match fields[..] {
+        [r, g, b, radius, intensity] => Some(LightSource {
+            color: Color::rgb(r.parse().ok()?, g.parse().ok()?, b.parse().ok()?),
+            radius: radius.parse().ok()?,
+            intensity: intensity.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Parse given animation definition (<tile>:<seconds>,...[,pingpong][,per_cell]),
+/// None if it is malformed or has no frames
+fn parse_animation(value: &str) -> Option<Animation> {
+    let mut animation = Animation {
+        frames: Vec::new(),
+        mode: AnimationMode::Loop,
+        phase: AnimationPhase::Synchronized,
+    };
+
+    for field in value.split(',') {
+        match field {
+            "pingpong" => animation.mode = AnimationMode::PingPong,
+            "per_cell" => animation.phase = AnimationPhase::PerCell,
+            _ => {
+                let (tile_id, duration) = field.split_once(':')?;
+                let duration: f32 = duration.parse().ok()?;
+                if !duration.is_finite() || duration <= 0.0 {
+                    return None;
+                }
+                animation.frames.push(AnimationFrame {
+                    tile_id: tile_id.parse().ok()?,
+                    duration,
+                });
+            }
+        }
+    }
+
+    if animation.frames.is_empty() {
+        return None;
+    }
+    Some(animation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let entries = parse_manifest(
+            "# comment\n\n1 grass.png 0 0 16 16 walkable\n  100 houses.png 0 32 48 32 3 1  \n\
+             50 houses.png 0 32 16 16 layer=1 blocks_light\n\
+             102 towers.png 0 16 32 32 2 1 light=255,200,120,6,1 blocks_light layer=1\n",
+        )
+        .unwrap();
+        let torch = LightSource {
+            color: Color::rgb(255, 200, 120),
+            radius: 6.0,
+            intensity: 1.0,
+        };
+
+        assert_eq!(
+            entries,
+            vec![
+                TileEntry {
+                    id: 1,
+                    file: "grass.png".to_string(),
+                    rect: IntRect::new(0, 0, 16, 16),
+                    footprint: None,
+                    layer: 0,
+                    properties: vec!["walkable".to_string()],
+                    light: None,
+                    animation: None,
+                },
+                TileEntry {
+                    id: 100,
+                    file: "houses.png".to_string(),
+                    rect: IntRect::new(0, 32, 48, 32),
+                    footprint: Some((3, 1)),
+                    layer: 0,
+                    properties: vec![],
+                    light: None,
+                    animation: None,
+                },
+                TileEntry {
+                    id: 50,
+                    file: "houses.png".to_string(),
+                    rect: IntRect::new(0, 32, 16, 16),
+                    footprint: None,
+                    layer: 1,
+                    properties: vec!["blocks_light".to_string()],
+                    light: None,
+                    animation: None,
+                },
+                TileEntry {
+                    id: 102,
+                    file: "towers.png".to_string(),
+                    rect: IntRect::new(0, 16, 32, 32),
+                    footprint: Some((2, 1)),
+                    layer: 1,
+                    properties: vec!["blocks_light".to_string()],
+                    light: Some(torch),
+                    animation: None,
+                },
+            ]
+        );
+
+        assert!(matches!(
+            parse_manifest("1 grass.png 0 0 16 16\n2 grass.png 0 0 16\n"),
+            Err(ManifestError::InvalidLine(2, line)) if line == "2 grass.png 0 0 16"
+        ));
+        assert!(parse_manifest("a grass.png 0 0 16 16").is_err());
+        assert!(parse_manifest("1 grass.png 0 0 16 16 3").is_err());
+        assert!(parse_manifest("1 grass.png 0 0 16 16 light=255,0,0").is_err());
+        assert!(parse_manifest("1 grass.png 0 0 16 16 color=red").is_err());
+        assert!(parse_manifest("1 grass.png 0 0 16 16 layer=top").is_err());
+    }
+
+    #[test]
+    fn test_parse_manifest_invalid_size() {
+        // The sprite area and the footprint must not be empty
+        assert!(matches!(
+            parse_manifest("1 grass.png 0 0 0 16"),
+            Err(ManifestError::InvalidLine(1, _))
+        ));
+        assert!(matches!(
+            parse_manifest("1 grass.png 0 0 16 -16"),
+            Err(ManifestError::InvalidLine(1, _))
+        ));
+        assert!(matches!(
+            parse_manifest("1 grass.png 0 0 16 16\n2 tower.png 0 0 16 32 0 2\n"),
+            Err(ManifestError::InvalidLine(2, line)) if line == "2 tower.png 0 0 16 32 0 2"
+        ));
+        assert!(matches!(
+            parse_manifest("1 grass.png 0 0 16 16\n2 tower.png 0 0 16 32 1 0\n"),
+            Err(ManifestError::InvalidLine(2, _))
+        ));
+    }
+
+    #[test]
+    fn test_parse_manifest_animation() {
+        let entries = parse_manifest(
+            "1 water.png 0 0 16 16 animation=1:0.5,2:0.25\n\
+             2 water.png 16 0 16 16 animation=2:1,1:1,pingpong,per_cell\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            entries[0].animation,
+            Some(Animation {
+                frames: vec![
+                    AnimationFrame {
+                        tile_id: 1,
+                        duration: 0.5
+                    },
+                    AnimationFrame {
+                        tile_id: 2,
+                        duration: 0.25
+                    },
+                ],
+                mode: AnimationMode::Loop,
+                phase: AnimationPhase::Synchronized,
+            })
+        );
+        let animation = entries[1].animation.as_ref().unwrap();
+        assert_eq!(animation.frames.len(), 2);
+        assert_eq!(animation.mode, AnimationMode::PingPong);
+        assert_eq!(animation.phase, AnimationPhase::PerCell);
+
+        assert!(parse_manifest("1 water.png 0 0 16 16 animation=").is_err());
+        assert!(parse_manifest("1 water.png 0 0 16 16 animation=pingpong").is_err());
+        assert!(parse_manifest("1 water.png 0 0 16 16 animation=1:0").is_err());
+        assert!(parse_manifest("1 water.png 0 0 16 16 animation=1").is_err());
+        assert!(parse_manifest("1 water.png 0 0 16 16 animation=1:1,reverse").is_err());
+        // The frames must be declared in the manifest
+        assert!(matches!(
+            parse_manifest("\n1 water.png 0 0 16 16 animation=1:1,3:1"),
+            Err(ManifestError::InvalidLine(2, _))
+        ));
+    }
+
+    #[test]
+    fn test_default_manifest() {
+        let entries = parse_manifest(DEFAULT_MANIFEST).unwrap();
+
+        assert_eq!(entries.len(), 32);
+        assert_eq!(entries.iter().filter(|e| e.footprint.is_some()).count(), 3);
+        assert_eq!(entries.iter().filter(|e| e.light.is_some()).count(), 2);
+        assert_eq!(entries.iter().filter(|e| e.animation.is_some()).count(), 1);
+        assert_eq!(
+            entries
+                .iter()
+                .filter(|e| e.properties.contains(&"blocks_light".to_string()))
+                .count(),
+            6
+        );
+        assert_eq!(
+            entries
+                .iter()
+                .filter(|e| e.properties.contains(&"walkable".to_string()))
+                .count(),
+            5
+        );
+        assert_eq!(entries.iter().filter(|e| e.layer == 1).count(), 27);
+    }
+
+    #[test]
+    fn test_read_manifest() {
+        let error = read_manifest("missing.txt").unwrap_err();
+        assert!(matches!(
+            &error,
+            ManifestError::ReadError(path, e)
+                if path == "missing.txt" && e.kind() == io::ErrorKind::NotFound
+        ));
+        assert!(error.source().is_some());
+    }
+}
@@ -0,0 +1,272 @@
+use sfml::graphics::{Color, Drawable, FloatRect, RenderStates, RenderTarget};
+use sfml::system::{Vector2f, Vector2u};
+use sfml::window::{Event, Key};
+
+use retroland::font::{append_text, text_size};
+use retroland::geometry::{append_rect, Quads};
+use retroland::tilemap::{LayerBlendMode, RenderBackend, SfmlBackend};
+
+/// The size (in screen pixels) of a font pixel
+const PIXEL_SIZE: f32 = 3.0;
+
+/// The space (in screen pixels) around the prompt content
+const PADDING: f32 = 20.0;
+
+/// The prompt width, relative to the screen width
+const WIDTH: f32 = 0.6;
+
+/// PromptStatus is the state of a prompt after an event
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptStatus {
+    /// The text is being typed
+    Pending,
+    /// The text has been submitted (Enter)
+    Submitted(String),
+    /// The prompt has been closed (Escape)
+    Cancelled,
+}
+
+/// Prompt is a text entry displayed on top of the editor (i.e to type a file path)
+/// the suggestions (i.e the recent files) can be picked using the up / down keys
+pub struct Prompt {
+    title: String,
+    text: String,
+    suggestions: Vec<String>,
+    /// the picked suggestion, if any
+    selected: Option<usize>,
+    screen_size: Vector2u,
+    /// the background, title, text & suggestions, as a single batch of untextured quads
+    vertices: Quads,
+}
+
+impl Prompt {
+    /// Create a new prompt with given title, initial text and suggestions
+    /// displayed in the center of a screen of given size
+    pub fn new<T: Into<Vector2u>>(
+        title: &str,
+        text: &str,
+        suggestions: Vec<String>,
+        screen_size: T,
+    ) -> Self {
+        let mut prompt = Prompt {
+            title: title.to_string(),
+            text: text.to_string(),
+            suggestions,
+            selected: None,
+            screen_size: screen_size.into(),
+            vertices: Quads::new(),
+        };
+        prompt.vertices = prompt.build();
+
+        prompt
+    }
+
+    /// Update the prompt using given event
+    /// the typed characters are appended to the text, Backspace erase the last one,
+    /// Enter submit the text and Escape cancel the prompt
+    pub fn handle_event(&mut self, event: &Event) -> PromptStatus {
+        match *event {
+            Event::TextEntered { unicode } if !unicode.is_control() => {
+                self.text.push(unicode);
+                self.selected = None;
+            }
+            Event::KeyPressed { code, .. } => match code {
+                Key::Return if !self.text.is_empty() => {
+                    return PromptStatus::Submitted(self.text.clone())
+                }
+                Key::Escape => return PromptStatus::Cancelled,
+                Key::BackSpace => {
+                    self.text.pop();
+                    self.selected = None;
+                }
+                Key::Up | Key::Down if !self.suggestions.is_empty() => {
+                    let count = self.suggestions.len();
+                    let selected = match (self.selected, code) {
+                        (None, Key::Up) => count - 1,
+                        (None, _) => 0,
+                        (Some(i), Key::Up) => (i + count - 1) % count,
+                        (Some(i), _) => (i + 1) % count,
+                    };
+                    self.selected = Some(selected);
+                    self.text = self.suggestions[selected].clone();
+                }
+                _ => return PromptStatus::Pending,
+            },
+            _ => return PromptStatus::Pending,
+        }
+
+        self.vertices = self.build();
+        PromptStatus::Pending
+    }
+
+    /// Center the prompt on a screen of given size (i.e when the window is resized)
+    pub fn resize<T: Into<Vector2u>>(&mut self, screen_size: T) {
+        self.screen_size = screen_size.into();
+        self.vertices = self.build();
+    }
+
+    /// Build the prompt geometry
+    fn build(&self) -> Quads {
+        let mut vertices = Quads::new();
+        let screen = Vector2f::new(self.screen_size.x as f32, self.screen_size.y as f32);
+        let line_height = text_size("", PIXEL_SIZE).y * 2.0;
+
+        // Dim the editor behind the prompt
+        append_rect(
+            &mut vertices,
+            FloatRect::new(0.0, 0.0, screen.x, screen.y),
+            Color::rgba(0, 0, 0, 160),
+        );
+
+        let width = screen.x * WIDTH;
+        let height = PADDING * 2.0 + line_height * (2 + self.suggestions.len()) as f32;
+        let (left, top) = ((screen.x - width) / 2.0, (screen.y - height) / 2.0);
+        append_rect(
+            &mut vertices,
+            FloatRect::new(left, top, width, height),
+            Color::rgba(44, 62, 80, 240),
+        );
+
+        let left = left + PADDING;
+        let mut top = top + PADDING;
+        append_text(
+            &mut vertices,
+            &self.title,
+            Vector2f::new(left, top),
+            PIXEL_SIZE,
+            Color::rgb(180, 190, 200),
+        );
+        top += line_height;
+
+        // Keep the end of the text (and the cursor) visible
+        let column_width = text_size("__", PIXEL_SIZE).x - text_size("_", PIXEL_SIZE).x;
+        let max_columns = ((width - PADDING * 2.0) / column_width) as usize;
+        let text = format!("{}_", self.text);
+        let skipped = text.chars().count().saturating_sub(max_columns);
+        let text: String = text.chars().skip(skipped).collect();
+        append_text(
+            &mut vertices,
+            &text,
+            Vector2f::new(left, top),
+            PIXEL_SIZE,
+            Color::WHITE,
+        );
+        top += line_height;
+
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            let color = if self.selected == Some(i) {
+                Color::YELLOW
+            } else {
+                Color::rgb(180, 190, 200)
+            };
+            append_text(
+                &mut vertices,
+                suggestion,
+                Vector2f::new(left, top),
+                PIXEL_SIZE,
+                color,
+            );
+            top += line_height;
+        }
+
+        vertices
+    }
+}
+
+impl Drawable for Prompt {
+    fn draw<'a: 'shader, 'texture, 'shader, 'shader_texture>(
+        &'a self,
+        target: &mut dyn RenderTarget,
+        states: RenderStates<'texture, 'shader, 'shader_texture>,
+    ) {
+        SfmlBackend::untextured(target, states).draw_quads(
+            &self.vertices,
+            false,
+            LayerBlendMode::Alpha,
+            Vector2f::default(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: Key) -> Event {
+        Event::KeyPressed {
+            code,
+            alt: false,
+            ctrl: false,
+            shift: false,
+            system: false,
+        }
+    }
+
+    fn type_text(prompt: &mut Prompt, text: &str) {
+        for unicode in text.chars() {
+            assert_eq!(
+                prompt.handle_event(&Event::TextEntered { unicode }),
+                PromptStatus::Pending
+            );
+        }
+    }
+
+    #[test]
+    fn test_prompt_handle_event() {
+        let mut prompt = Prompt::new("Open", "maps/", vec![], (800, 600));
+
+        type_text(&mut prompt, "villagf\u{8}");
+        assert_eq!(prompt.text, "maps/villagf");
+        prompt.handle_event(&key(Key::BackSpace));
+        type_text(&mut prompt, "e.bin");
+        assert_eq!(prompt.text, "maps/village.bin");
+
+        assert_eq!(
+            prompt.handle_event(&key(Key::Return)),
+            PromptStatus::Submitted("maps/village.bin".to_string())
+        );
+        assert_eq!(
+            prompt.handle_event(&key(Key::Escape)),
+            PromptStatus::Cancelled
+        );
+
+        // An empty text cannot be submitted
+        let mut prompt = Prompt::new("Save as", "", vec![], (800, 600));
+        assert_eq!(
+            prompt.handle_event(&key(Key::Return)),
+            PromptStatus::Pending
+        );
+    }
+
+    #[test]
+    fn test_prompt_suggestions() {
+        let suggestions = vec!["a.bin".to_string(), "b.bin".to_string()];
+        let mut prompt = Prompt::new("Open", "", suggestions, (800, 600));
+
+        prompt.handle_event(&key(Key::Down));
+        assert_eq!(prompt.text, "a.bin");
+        prompt.handle_event(&key(Key::Down));
+        assert_eq!(prompt.text, "b.bin");
+        prompt.handle_event(&key(Key::Down));
+        assert_eq!(prompt.text, "a.bin");
+        prompt.handle_event(&key(Key::Up));
+        assert_eq!(prompt.text, "b.bin");
+
+        // The picked suggestion can be edited
+        prompt.handle_event(&key(Key::BackSpace));
+        assert_eq!(prompt.text, "b.bi");
+        assert_eq!(prompt.selected, None);
+    }
+
+    #[test]
+    fn test_prompt_resize() {
+        let mut prompt = Prompt::new("Open", "", vec![], (800, 600));
+        assert_eq!(prompt.vertices[0].position, Vector2f::new(0.0, 0.0));
+        assert_eq!(prompt.vertices[2].position, Vector2f::new(800.0, 600.0));
+
+        prompt.resize((400, 300));
+        assert_eq!(prompt.vertices[2].position, Vector2f::new(400.0, 300.0));
+        // The prompt box is centered
+        assert!((prompt.vertices[4].position.x - 80.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,101 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The number of recent files remembered
+pub const RECENT_FILES_CAPACITY: usize = 8;
+
+/// RecentFiles is the list of the last opened / saved tile maps, most recent first
+/// it is persisted as one path per line
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    /// Load the recent files from given file
+    /// an empty list is returned if the file cannot be read
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        RecentFiles {
+            paths: content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .take(RECENT_FILES_CAPACITY)
+                .map(PathBuf::from)
+                .collect(),
+        }
+    }
+
+    /// Save the recent files to given file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let lines: Vec<String> = self
+            .paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        fs::write(path, lines.join("\n"))
+    }
+
+    /// Move given file on top of the list, the oldest file is forgotten if the list is full
+    pub fn add<P: Into<PathBuf>>(&mut self, path: P) {
+        let path = path.into();
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(RECENT_FILES_CAPACITY);
+    }
+
+    /// Retrieve the recent files, most recent first
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+/// Retrieve the file the recent files are persisted in (in the user home directory)
+/// None if the home directory is unknown
+pub fn recent_files_path() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".retroland-editor-recent"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    #[test]
+    fn test_recent_files_add() {
+        let mut recent = RecentFiles::default();
+        recent.add("a.bin");
+        recent.add("b.bin");
+        recent.add("a.bin");
+
+        assert_eq!(
+            recent.paths(),
+            &[PathBuf::from("a.bin"), PathBuf::from("b.bin")]
+        );
+
+        for i in 0..RECENT_FILES_CAPACITY {
+            recent.add(format!("{}.bin", i));
+        }
+        assert_eq!(recent.paths().len(), RECENT_FILES_CAPACITY);
+        assert_eq!(recent.paths()[0], PathBuf::from("7.bin"));
+    }
+
+    #[test]
+    fn test_recent_files_save_load() {
+        let dir = temp_dir("recent-files");
+        let path = dir.path().join("recent");
+        let mut recent = RecentFiles::default();
+        recent.add("maps/village.bin");
+        recent.add("castle.bin");
+
+        assert!(recent.save(&path).is_ok());
+        assert_eq!(RecentFiles::load(&path), recent);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(RecentFiles::load(&path), RecentFiles::default());
+    }
+}
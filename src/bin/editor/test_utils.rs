@@ -0,0 +1,41 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of temporary directories created by the running tests
+static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// TempDir is a temporary directory, deleted along its content once dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Retrieve the directory path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // The directory may already be gone, there is nothing left to clean in this case
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Create an empty temporary directory, unique to the calling test
+/// the tests run in parallel (and possibly in several processes), so they never share a file
+/// the directory is deleted once the returned guard is dropped (even if the test fails)
+pub fn temp_dir(name: &str) -> TempDir {
+    let path = env::temp_dir().join(format!(
+        "retroland-test-{}-{}-{}",
+        name,
+        process::id(),
+        TEMP_DIRS.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&path).unwrap();
+    TempDir { path }
+}
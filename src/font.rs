@@ -0,0 +1,228 @@
+//! Tiny embedded 5x7 bitmap font, rendered as untextured quads
+//! this allows to display text (labels, debug information, ...) without loading a font file
+
+use sfml::graphics::Color;
+use sfml::system::Vector2f;
+
+use crate::geometry::{Quads, Vertex};
+
+/// The width of a glyph (in font pixels)
+pub const GLYPH_WIDTH: u32 = 5;
+/// The height of a glyph (in font pixels)
+pub const GLYPH_HEIGHT: u32 = 7;
+/// The space between two glyphs (in font pixels)
+const GLYPH_SPACING: u32 = 1;
+/// The space between two lines (in font pixels)
+const LINE_SPACING: u32 = 2;
+
+/// The printable ASCII glyphs (from ' ' to '~'), one byte per column, the lowest bit is the top row
+const GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // '#'
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '''
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // ')'
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // '*'
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // '0'
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // '@'
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // 'A'
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // 'D'
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // 'G'
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // 'H'
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // 'J'
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // 'M'
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // 'N'
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // 'O'
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // 'Q'
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // 'T'
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // 'U'
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // 'V'
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\'
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // 'f'
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // 'g'
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // 'j'
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // 'l'
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // 'p'
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // 'q'
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // 't'
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // 'u'
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // 'v'
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // 'y'
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x08, 0x04, 0x08, 0x10, 0x08], // '~'
+];
+
+/// Compute the size of given text, using given font pixel size
+pub fn text_size(text: &str, pixel_size: f32) -> Vector2f {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let columns = lines
+        .iter()
+        .map(|line| line.chars().count() as u32)
+        .max()
+        .unwrap_or(0);
+
+    let width = (columns * (GLYPH_WIDTH + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING);
+    let height = lines.len() as u32 * (GLYPH_HEIGHT + LINE_SPACING) - LINE_SPACING;
+    Vector2f::new(width as f32 * pixel_size, height as f32 * pixel_size)
+}
+
+/// Append the quads of given text to given vertex array (which should use the Quads primitive)
+/// the text top left corner is placed on given position, '\n' starts a new line
+/// the characters not supported by the font are displayed as '?'
+pub fn append_text(
+    vertices: &mut Quads,
+    text: &str,
+    position: Vector2f,
+    pixel_size: f32,
+    color: Color,
+) {
+    for (row, line) in text.split('\n').enumerate() {
+        let top = position.y + (row as u32 * (GLYPH_HEIGHT + LINE_SPACING)) as f32 * pixel_size;
+
+        for (column, c) in line.chars().enumerate() {
+            let left =
+                position.x + (column as u32 * (GLYPH_WIDTH + GLYPH_SPACING)) as f32 * pixel_size;
+
+            for (x, bits) in glyph(c).iter().enumerate() {
+                for y in 0..GLYPH_HEIGHT {
+                    if bits & (1 << y) == 0 {
+                        continue;
+                    }
+
+                    let pixel_left = left + x as f32 * pixel_size;
+                    let pixel_top = top + y as f32 * pixel_size;
+                    for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                        vertices.append(&Vertex::with_pos_color(
+                            (pixel_left + dx * pixel_size, pixel_top + dy * pixel_size),
+                            color,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Retrieve the glyph of given character
+fn glyph(c: char) -> &'static [u8; 5] {
+    match c {
+        ' '..='~' => &GLYPHS[c as usize - ' ' as usize],
+        _ => &GLYPHS['?' as usize - ' ' as usize],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::graphics::Color;
+    use sfml::system::Vector2f;
+
+    use crate::font::{append_text, glyph, text_size};
+    use crate::geometry::Quads;
+
+    #[test]
+    fn test_text_size() {
+        assert_eq!(text_size("", 1.0), Vector2f::new(0.0, 7.0));
+        assert_eq!(text_size("1", 1.0), Vector2f::new(5.0, 7.0));
+        assert_eq!(text_size("12", 2.0), Vector2f::new(22.0, 14.0));
+        assert_eq!(text_size("123\n1", 1.0), Vector2f::new(17.0, 16.0));
+    }
+
+    #[test]
+    fn test_append_text() {
+        let mut vertices = Quads::new();
+
+        // '1' has 10 pixels
+        append_text(
+            &mut vertices,
+            "1",
+            Vector2f::new(10.0, 20.0),
+            2.0,
+            Color::RED,
+        );
+        assert_eq!(vertices.vertex_count(), 40);
+
+        // The first pixel is the second column, second row
+        assert_eq!(vertices[0].position, Vector2f::new(12.0, 22.0));
+        assert_eq!(vertices[2].position, Vector2f::new(14.0, 24.0));
+        assert_eq!(vertices[0].color, Color::RED);
+
+        // Spaces are not drawn
+        append_text(
+            &mut vertices,
+            " \n ",
+            Vector2f::new(0.0, 0.0),
+            1.0,
+            Color::RED,
+        );
+        assert_eq!(vertices.vertex_count(), 40);
+    }
+
+    #[test]
+    fn test_glyph() {
+        assert_eq!(glyph('A'), &[0x7E, 0x11, 0x11, 0x11, 0x7E]);
+        assert_eq!(glyph('~'), &[0x08, 0x04, 0x08, 0x10, 0x08]);
+        assert_eq!(glyph('é'), glyph('?'));
+    }
+}
@@ -0,0 +1,208 @@
+//! Backend agnostic geometry: batches of quads built by the renderers
+//! the render backends convert them to their own representation when drawing
+
+use sfml::graphics::{Color, FloatRect};
+use sfml::system::Vector2f;
+use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The last revision given to a batch of quads, every change gets a new one
+static REVISION: AtomicU64 = AtomicU64::new(0);
+
+/// Create a new revision, never given before
+fn next_revision() -> u64 {
+    REVISION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Vertex is a corner of a quad
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Vertex {
+    /// the position (in world units)
+    pub position: Vector2f,
+    /// the color, multiplied with the texture if the quad is textured
+    pub color: Color,
+    /// the texture coordinates (in atlas pixels)
+    pub tex_coords: Vector2f,
+}
+
+impl Default for Vertex {
+    fn default() -> Self {
+        Vertex {
+            position: Vector2f::default(),
+            color: Color::WHITE,
+            tex_coords: Vector2f::default(),
+        }
+    }
+}
+
+impl Vertex {
+    /// Create a new vertex at given position, with given color and texture coordinates
+    pub fn new<P: Into<Vector2f>>(position: P, color: Color, tex_coords: Vector2f) -> Self {
+        Vertex {
+            position: position.into(),
+            color,
+            tex_coords,
+        }
+    }
+
+    /// Create a new untextured vertex at given position, with given color
+    pub fn with_pos_color<P: Into<Vector2f>>(position: P, color: Color) -> Self {
+        Vertex::new(position, color, Vector2f::default())
+    }
+}
+
+/// Quads is a batch of quads drawn at once, made of 4 vertices per quad
+/// (top left, top right, bottom right, bottom left)
+///
+/// each change gives the batch a new revision, so the geometry built from it
+/// by the render backends can be kept until it is modified
+#[derive(Debug, Clone, Default)]
+pub struct Quads {
+    vertices: Vec<Vertex>,
+    /// the revision of the vertices, 0 for a batch that has never been modified (i.e empty)
+    revision: u64,
+}
+
+impl Quads {
+    /// Create an empty batch of quads
+    pub fn new() -> Self {
+        Quads::default()
+    }
+
+    /// Create a batch of given number of vertices, using the default vertex
+    pub fn with_vertex_count(vertex_count: usize) -> Self {
+        Quads {
+            vertices: vec![Vertex::default(); vertex_count],
+            revision: next_revision(),
+        }
+    }
+
+    /// Retrieve the revision of the vertices, it changes every time they are modified
+    /// two batches sharing a revision have the same vertices (i.e a clone)
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Retrieve the number of vertices
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Retrieve the vertices
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    /// Append given vertex
+    pub fn append(&mut self, vertex: &Vertex) {
+        self.vertices.push(*vertex);
+        self.revision = next_revision();
+    }
+
+    /// Resize the batch to given number of vertices, the new ones use the default vertex
+    pub fn resize(&mut self, vertex_count: usize) {
+        self.vertices.resize(vertex_count, Vertex::default());
+        self.revision = next_revision();
+    }
+
+    /// Remove every vertex
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.revision = next_revision();
+    }
+}
+
+impl PartialEq for Quads {
+    fn eq(&self, other: &Self) -> bool {
+        self.vertices == other.vertices
+    }
+}
+
+impl Index<usize> for Quads {
+    type Output = Vertex;
+
+    fn index(&self, index: usize) -> &Vertex {
+        &self.vertices[index]
+    }
+}
+
+impl IndexMut<usize> for Quads {
+    fn index_mut(&mut self, index: usize) -> &mut Vertex {
+        self.revision = next_revision();
+        &mut self.vertices[index]
+    }
+}
+
+/// Append the untextured quad of given rectangle to given batch
+pub fn append_rect(quads: &mut Quads, rect: FloatRect, color: Color) {
+    let (right, bottom) = (rect.left + rect.width, rect.top + rect.height);
+    for position in [
+        (rect.left, rect.top),
+        (right, rect.top),
+        (right, bottom),
+        (rect.left, bottom),
+    ] {
+        quads.append(&Vertex::with_pos_color(position, color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::graphics::{Color, FloatRect};
+    use sfml::system::Vector2f;
+
+    use crate::geometry::{append_rect, Quads, Vertex};
+
+    #[test]
+    fn test_quads() {
+        let mut quads = Quads::with_vertex_count(4);
+        assert_eq!(quads.vertex_count(), 4);
+        assert_eq!(quads[3], Vertex::default());
+
+        quads[1].color = Color::RED;
+        quads.append(&Vertex::with_pos_color((1.0, 2.0), Color::GREEN));
+        assert_eq!(quads.vertex_count(), 5);
+        assert_eq!(quads.vertices()[1].color, Color::RED);
+        assert_eq!(quads[4].position, Vector2f::new(1.0, 2.0));
+
+        quads.resize(8);
+        assert_eq!(quads.vertex_count(), 8);
+        quads.clear();
+        assert_eq!(quads.vertex_count(), 0);
+    }
+
+    #[test]
+    fn test_quads_revision() {
+        let mut quads = Quads::new();
+        assert_eq!(quads.revision(), 0);
+
+        quads.append(&Vertex::default());
+        let revision = quads.revision();
+        assert_ne!(revision, 0);
+
+        // The clones share the revision until they are modified
+        let mut clone = quads.clone();
+        assert_eq!(clone.revision(), revision);
+        clone[0].color = Color::RED;
+        assert_ne!(clone.revision(), revision);
+        assert_eq!(quads.revision(), revision);
+
+        // The revisions are never given twice
+        let other = Quads::with_vertex_count(4);
+        assert_ne!(other.revision(), revision);
+        assert_ne!(other.revision(), clone.revision());
+        quads.clear();
+        assert_ne!(quads.revision(), revision);
+    }
+
+    #[test]
+    fn test_append_rect() {
+        let mut quads = Quads::new();
+        append_rect(&mut quads, FloatRect::new(1.0, 2.0, 3.0, 4.0), Color::RED);
+
+        assert_eq!(quads.vertex_count(), 4);
+        assert_eq!(quads[0].position, Vector2f::new(1.0, 2.0));
+        assert_eq!(quads[2].position, Vector2f::new(4.0, 6.0));
+        assert_eq!(quads[3].color, Color::RED);
+    }
+}
@@ -1 +1,3 @@
+pub mod font;
+pub mod geometry;
 pub mod tilemap;
@@ -0,0 +1,168 @@
+use sfml::system::Vector2u;
+
+/// AnimationMode define how the frames of an animation are sequenced
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AnimationMode {
+    /// Play the frames in order then restart from the first one
+    Loop,
+    /// Play the frames in order then in reverse order
+    PingPong,
+}
+
+/// AnimationPhase define how the animation of the different cells are synchronized
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AnimationPhase {
+    /// Every cell display the same frame at the same time
+    Synchronized,
+    /// Each cell has its own time offset, computed from its position
+    PerCell,
+}
+
+/// AnimationFrame is a single frame of an animation
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnimationFrame {
+    /// the tile displayed during the frame
+    pub tile_id: u32,
+    /// the frame duration (in seconds)
+    pub duration: f32,
+}
+
+/// Animation is a sequence of tiles displayed one after another
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub frames: Vec<AnimationFrame>,
+    pub mode: AnimationMode,
+    pub phase: AnimationPhase,
+}
+
+impl Animation {
+    /// Compute the total duration of the animation (in seconds)
+    pub fn duration(&self) -> f32 {
+        self.sequence().map(|i| self.frames[i].duration).sum()
+    }
+
+    /// Retrieve the tile displayed at given time (in seconds) by the cell at given position
+    /// this will return None if the animation has no frames
+    pub fn tile_at(&self, time: f32, position: Vector2u) -> Option<u32> {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return self.frames.first().map(|frame| frame.tile_id);
+        }
+
+        let offset = match self.phase {
+            AnimationPhase::Synchronized => 0.0,
+            AnimationPhase::PerCell => {
+                // Cheap spatial hash to spread the cells across the animation
+                let hash = position
+                    .x
+                    .wrapping_mul(73_856_093)
+                    .wrapping_add(position.y.wrapping_mul(19_349_663));
+                (hash % 1000) as f32 / 1000.0 * duration
+            }
+        };
+
+        let mut time = (time + offset) % duration;
+        for i in self.sequence() {
+            let frame = &self.frames[i];
+            if time < frame.duration {
+                return Some(frame.tile_id);
+            }
+            time -= frame.duration;
+        }
+
+        self.frames.last().map(|frame| frame.tile_id)
+    }
+
+    /// Returns the index of the frames, in the order they are displayed
+    fn sequence(&self) -> impl Iterator<Item = usize> {
+        let count = self.frames.len();
+        let backward = match self.mode {
+            AnimationMode::Loop => 0..0,
+            AnimationMode::PingPong => 1..count.saturating_sub(1),
+        };
+
+        (0..count).chain(backward.rev())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(mode: AnimationMode, phase: AnimationPhase) -> Animation {
+        Animation {
+            frames: vec![
+                AnimationFrame {
+                    tile_id: 1,
+                    duration: 0.5,
+                },
+                AnimationFrame {
+                    tile_id: 2,
+                    duration: 0.25,
+                },
+                AnimationFrame {
+                    tile_id: 3,
+                    duration: 1.0,
+                },
+            ],
+            mode,
+            phase,
+        }
+    }
+
+    #[test]
+    fn test_animation_duration() {
+        let animation_loop = animation(AnimationMode::Loop, AnimationPhase::Synchronized);
+        assert_eq!(animation_loop.duration(), 1.75);
+
+        let ping_pong = animation(AnimationMode::PingPong, AnimationPhase::Synchronized);
+        assert_eq!(ping_pong.duration(), 2.0);
+    }
+
+    #[test]
+    fn test_animation_tile_at_loop() {
+        let animation = animation(AnimationMode::Loop, AnimationPhase::Synchronized);
+        let position = Vector2u::new(3, 4);
+
+        assert_eq!(animation.tile_at(0.0, position), Some(1));
+        assert_eq!(animation.tile_at(0.6, position), Some(2));
+        assert_eq!(animation.tile_at(1.0, position), Some(3));
+        assert_eq!(animation.tile_at(1.8, position), Some(1));
+    }
+
+    #[test]
+    fn test_animation_tile_at_ping_pong() {
+        let animation = animation(AnimationMode::PingPong, AnimationPhase::Synchronized);
+        let position = Vector2u::new(3, 4);
+
+        assert_eq!(animation.tile_at(0.0, position), Some(1));
+        assert_eq!(animation.tile_at(1.0, position), Some(3));
+        assert_eq!(animation.tile_at(1.8, position), Some(2));
+        assert_eq!(animation.tile_at(2.1, position), Some(1));
+    }
+
+    #[test]
+    fn test_animation_tile_at_per_cell() {
+        let animation = animation(AnimationMode::Loop, AnimationPhase::PerCell);
+
+        // Cell (0, 0) has no offset
+        assert_eq!(animation.tile_at(0.0, (0, 0).into()), Some(1));
+
+        // Other cells are not synchronized
+        let tiles: Vec<Option<u32>> = (0..10)
+            .map(|x| animation.tile_at(0.0, (x, 0).into()))
+            .collect();
+        assert!(tiles.iter().any(|tile| *tile != Some(1)));
+    }
+
+    #[test]
+    fn test_animation_tile_at_empty() {
+        let animation = Animation {
+            frames: vec![],
+            mode: AnimationMode::Loop,
+            phase: AnimationPhase::Synchronized,
+        };
+
+        assert_eq!(animation.tile_at(1.0, (0, 0).into()), None);
+    }
+}
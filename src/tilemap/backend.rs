@@ -0,0 +1,468 @@
+use sfml::graphics::{
+    BlendMode, Color, Image, PrimitiveType, RenderStates, RenderTarget, Texture, View,
+};
+use sfml::system::{Vector2f, Vector2u};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+use crate::geometry::{Quads, Vertex};
+use crate::tilemap::raster::blend;
+use crate::tilemap::{LayerBlendMode, PixelBuffer};
+
+/// RenderBackend is the drawing surface used by the tile map renderer
+/// the renderer builds the geometry (batches of quads in world units) and the backend draws it
+pub trait RenderBackend {
+    /// The geometry built by the backend, ready to be drawn
+    type Geometry;
+
+    /// Set the area of the world displayed by the backend
+    fn set_view(&mut self, center: Vector2f, size: Vector2f, rotation: f32);
+
+    /// Build the geometry of given batch of quads (i.e a layer chunk)
+    /// the textured quads sample the tileset atlas, the others only use the vertices color
+    fn build_layer_geometry(&mut self, quads: &Quads, textured: bool) -> Self::Geometry;
+
+    /// Draw given geometry, translated by given offset (in world units)
+    fn draw(&mut self, geometry: &Self::Geometry, blend_mode: LayerBlendMode, offset: Vector2f);
+
+    /// Build and draw given batch of quads, translated by given offset (in world units)
+    fn draw_quads(
+        &mut self,
+        quads: &Quads,
+        textured: bool,
+        blend_mode: LayerBlendMode,
+        offset: Vector2f,
+    ) {
+        let geometry = self.build_layer_geometry(quads, textured);
+        self.draw(&geometry, blend_mode, offset);
+    }
+}
+
+/// GeometryCache keeps the geometry built by a backend for the batches of quads identified by a key
+/// the geometry of a batch is only built again once the batch has been modified
+pub struct GeometryCache<K, G> {
+    /// the geometry of each batch, along the batch revision it was built from
+    entries: BTreeMap<K, (u64, G)>,
+}
+
+impl<K: Ord, G> GeometryCache<K, G> {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        GeometryCache {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Retrieve the geometry of given batch of quads, see RenderBackend::build_layer_geometry
+    /// the geometry is built using given backend if the batch has changed since the last call using given key
+    pub fn geometry<B: RenderBackend<Geometry = G> + ?Sized>(
+        &mut self,
+        backend: &mut B,
+        key: K,
+        quads: &Quads,
+        textured: bool,
+    ) -> &G {
+        let revision = quads.revision();
+        match self.entries.entry(key) {
+            Entry::Occupied(entry) => {
+                let entry = entry.into_mut();
+                if entry.0 != revision {
+                    *entry = (revision, backend.build_layer_geometry(quads, textured));
+                }
+                &entry.1
+            }
+            Entry::Vacant(entry) => {
+                &entry
+                    .insert((revision, backend.build_layer_geometry(quads, textured)))
+                    .1
+            }
+        }
+    }
+
+    /// Remove every geometry from the cache
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl<K: Ord, G> Default for GeometryCache<K, G> {
+    fn default() -> Self {
+        GeometryCache::new()
+    }
+}
+
+/// SfmlGeometry is a batch of quads converted to SFML vertices
+pub struct SfmlGeometry {
+    vertices: Vec<sfml::graphics::Vertex>,
+    textured: bool,
+}
+
+/// SfmlBackend draws on an SFML render target (i.e a window), using the GPU
+pub struct SfmlBackend<'a, 'texture, 'shader, 'shader_texture> {
+    target: &'a mut dyn RenderTarget,
+    states: RenderStates<'texture, 'shader, 'shader_texture>,
+    texture: Option<&'texture Texture>,
+}
+
+impl<'a, 'texture, 'shader, 'shader_texture> SfmlBackend<'a, 'texture, 'shader, 'shader_texture> {
+    /// Create a new backend drawing on given target
+    /// the given states are applied to every draw, and the textured quads use given texture
+    pub fn new(
+        target: &'a mut dyn RenderTarget,
+        states: RenderStates<'texture, 'shader, 'shader_texture>,
+        texture: &'texture Texture,
+    ) -> Self {
+        SfmlBackend {
+            target,
+            states,
+            texture: Some(texture),
+        }
+    }
+
+    /// Create a new backend drawing on given target, without texture
+    /// the given states are applied to every draw, and every quad only uses the vertices color
+    pub fn untextured(
+        target: &'a mut dyn RenderTarget,
+        states: RenderStates<'texture, 'shader, 'shader_texture>,
+    ) -> Self {
+        SfmlBackend {
+            target,
+            states,
+            texture: None,
+        }
+    }
+}
+
+impl<'a, 'texture, 'shader, 'shader_texture> RenderBackend
+    for SfmlBackend<'a, 'texture, 'shader, 'shader_texture>
+{
+    type Geometry = SfmlGeometry;
+
+    fn set_view(&mut self, center: Vector2f, size: Vector2f, rotation: f32) {
+        let mut view = View::new(center, size);
+        view.set_rotation(rotation);
+        self.target.set_view(&view);
+    }
+
+    fn build_layer_geometry(&mut self, quads: &Quads, textured: bool) -> SfmlGeometry {
+        let vertices = quads
+            .vertices()
+            .iter()
+            .map(|vertex| {
+                sfml::graphics::Vertex::new(vertex.position, vertex.color, vertex.tex_coords)
+            })
+            .collect();
+        SfmlGeometry { vertices, textured }
+    }
+
+    fn draw(&mut self, geometry: &SfmlGeometry, blend_mode: LayerBlendMode, offset: Vector2f) {
+        if geometry.vertices.is_empty() {
+            return;
+        }
+
+        let mut states = self.states;
+        states.blend_mode = match blend_mode {
+            LayerBlendMode::Alpha => BlendMode::ALPHA,
+            LayerBlendMode::Add => BlendMode::ADD,
+            LayerBlendMode::Multiply => BlendMode::MULTIPLY,
+            LayerBlendMode::None => BlendMode::NONE,
+        };
+        states.transform.translate(offset.x, offset.y);
+        if geometry.textured {
+            states.texture = self.texture;
+        }
+        self.target
+            .draw_primitives(&geometry.vertices, PrimitiveType::Quads, states);
+    }
+}
+
+/// ImageGeometry is a batch of quads rasterized by the image backend
+pub struct ImageGeometry {
+    quads: Quads,
+    textured: bool,
+}
+
+/// ImageBackend draws into a pixel buffer, without using the GPU
+/// the quads are rasterized as two triangles, using nearest neighbour sampling of the atlas
+pub struct ImageBackend<'i> {
+    buffer: PixelBuffer,
+    atlas: &'i Image,
+    view_center: Vector2f,
+    view_size: Vector2f,
+    view_rotation: f32,
+}
+
+impl<'i> ImageBackend<'i> {
+    /// Create a new backend drawing into a buffer of given size, filled with given color
+    /// the textured quads use given atlas (i.e the tileset image)
+    pub fn new<T: Into<Vector2u>>(size: T, background: Color, atlas: &'i Image) -> Self {
+        let buffer = PixelBuffer::new(size, background);
+        let view_size = Vector2f::new(buffer.size().x as f32, buffer.size().y as f32);
+        ImageBackend {
+            buffer,
+            atlas,
+            view_center: view_size / 2.0,
+            view_size,
+            view_rotation: 0.0,
+        }
+    }
+
+    /// Retrieve the rendered pixels
+    pub fn buffer(&self) -> &PixelBuffer {
+        &self.buffer
+    }
+
+    /// Convert the backend into its rendered pixels
+    pub fn into_buffer(self) -> PixelBuffer {
+        self.buffer
+    }
+
+    /// Convert given world position into a buffer position (in pixels)
+    fn to_pixel(&self, position: Vector2f) -> Vector2f {
+        let (sin, cos) = (-self.view_rotation).to_radians().sin_cos();
+        let relative = position - self.view_center;
+        let rotated = Vector2f::new(
+            relative.x * cos - relative.y * sin,
+            relative.x * sin + relative.y * cos,
+        );
+
+        let size = self.buffer.size();
+        Vector2f::new(
+            (rotated.x / self.view_size.x + 0.5) * size.x as f32,
+            (rotated.y / self.view_size.y + 0.5) * size.y as f32,
+        )
+    }
+
+    /// Rasterize the triangle made of given vertices (positions in pixels)
+    /// the pixels on the edge opposite to the last vertex are skipped if the edge is shared
+    fn draw_triangle(
+        &mut self,
+        vertices: [(Vector2f, &Vertex); 3],
+        textured: bool,
+        blend_mode: LayerBlendMode,
+        shared_edge: bool,
+    ) {
+        let [(a, _), (b, _), (c, _)] = vertices;
+        let area = edge(a, b, c);
+        if area == 0.0 {
+            return;
+        }
+
+        let size = self.buffer.size();
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_x = (a.x.max(b.x).max(c.x).ceil().max(0.0) as u32).min(size.x);
+        let max_y = (a.y.max(b.y).max(c.y).ceil().max(0.0) as u32).min(size.y);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = Vector2f::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [
+                    edge(b, c, p) / area,
+                    edge(c, a, p) / area,
+                    edge(a, b, p) / area,
+                ];
+                if weights.iter().any(|w| *w < 0.0) || (shared_edge && weights[2] == 0.0) {
+                    continue;
+                }
+
+                let mut channels = [0.0; 4];
+                let mut tex_coords = Vector2f::default();
+                for ((_, vertex), weight) in vertices.iter().zip(weights) {
+                    let color = vertex.color;
+                    for (channel, value) in channels
+                        .iter_mut()
+                        .zip([color.r, color.g, color.b, color.a])
+                    {
+                        *channel += value as f32 * weight;
+                    }
+                    tex_coords += vertex.tex_coords * weight;
+                }
+                let [r, g, b, a] = channels.map(|value| value.clamp(0.0, 255.0).round() as u8);
+                let mut color = Color::rgba(r, g, b, a);
+
+                if textured {
+                    let atlas_size = self.atlas.size();
+                    if atlas_size.x == 0 || atlas_size.y == 0 {
+                        continue;
+                    }
+                    let u = (tex_coords.x.floor().max(0.0) as u32).min(atlas_size.x - 1);
+                    let v = (tex_coords.y.floor().max(0.0) as u32).min(atlas_size.y - 1);
+                    color *= self.atlas.pixel_at(u, v);
+                }
+
+                if let Some(destination) = self.buffer.pixel(x, y) {
+                    self.buffer
+                        .set_pixel(x, y, blend(color, destination, blend_mode));
+                }
+            }
+        }
+    }
+}
+
+impl<'i> RenderBackend for ImageBackend<'i> {
+    type Geometry = ImageGeometry;
+
+    fn set_view(&mut self, center: Vector2f, size: Vector2f, rotation: f32) {
+        self.view_center = center;
+        self.view_size = size;
+        self.view_rotation = rotation;
+    }
+
+    fn build_layer_geometry(&mut self, quads: &Quads, textured: bool) -> ImageGeometry {
+        ImageGeometry {
+            quads: quads.clone(),
+            textured,
+        }
+    }
+
+    fn draw(&mut self, geometry: &ImageGeometry, blend_mode: LayerBlendMode, offset: Vector2f) {
+        let (quads, textured) = (&geometry.quads, geometry.textured);
+        for quad in 0..quads.vertex_count() / 4 {
+            let vertex = |i: usize| &quads[quad * 4 + i];
+            let corner = |i: usize| (self.to_pixel(vertex(i).position + offset), vertex(i));
+            let corners = [corner(0), corner(1), corner(2), corner(3)];
+
+            // The diagonal is shared by both triangles, only the first one draws it
+            self.draw_triangle(
+                [corners[0], corners[1], corners[2]],
+                textured,
+                blend_mode,
+                false,
+            );
+            self.draw_triangle(
+                [corners[2], corners[0], corners[3]],
+                textured,
+                blend_mode,
+                true,
+            );
+        }
+    }
+}
+
+/// Compute the edge function of given point relative to the edge going from a to b
+/// i.e twice the signed area of the triangle (a, b, p)
+fn edge(a: Vector2f, b: Vector2f, p: Vector2f) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::graphics::{Color, Image, View};
+    use sfml::system::Vector2f;
+
+    use crate::geometry::{Quads, Vertex};
+    use crate::tilemap::test_utils::{pixel_tileset, CountingBackend};
+    use crate::tilemap::{
+        rasterize, ExportOptions, GeometryCache, ImageBackend, LayerBlendMode, RenderBackend,
+        TileMap, TileMapRenderer,
+    };
+
+    fn quad(left: f32, top: f32, size: f32, color: Color) -> Quads {
+        let mut quads = Quads::new();
+        for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            quads.append(&Vertex::new(
+                (left + x * size, top + y * size),
+                color,
+                Vector2f::new(x * 2.0, y * 2.0),
+            ));
+        }
+        quads
+    }
+
+    #[test]
+    fn test_image_backend_draw_quads() {
+        let atlas = Image::new(1, 1);
+        let mut backend = ImageBackend::new((4, 4), Color::BLACK, &atlas);
+
+        let quads = quad(0.0, 0.0, 2.0, Color::rgba(255, 0, 0, 128));
+        backend.draw_quads(&quads, false, LayerBlendMode::Alpha, (1.0, 1.0).into());
+
+        // The diagonal is drawn once
+        let blended = Color::rgba(128, 0, 0, 255);
+        assert_eq!(backend.buffer().pixel(0, 0), Some(Color::BLACK));
+        assert_eq!(backend.buffer().pixel(1, 1), Some(blended));
+        assert_eq!(backend.buffer().pixel(2, 2), Some(blended));
+        assert_eq!(backend.buffer().pixel(2, 1), Some(blended));
+        assert_eq!(backend.buffer().pixel(3, 3), Some(Color::BLACK));
+
+        // The view is zoomed on the bottom right corner
+        backend.set_view((3.0, 3.0).into(), (2.0, 2.0).into(), 0.0);
+        let quads = quad(2.0, 2.0, 1.0, Color::GREEN);
+        backend.draw_quads(&quads, false, LayerBlendMode::None, Vector2f::default());
+
+        let buffer = backend.into_buffer();
+        assert_eq!(buffer.pixel(0, 0), Some(Color::GREEN));
+        assert_eq!(buffer.pixel(1, 1), Some(Color::GREEN));
+        assert_eq!(buffer.pixel(3, 3), Some(Color::BLACK));
+    }
+
+    #[test]
+    fn test_image_backend_draw_textured_quads() {
+        let mut atlas = Image::from_color(2, 2, Color::WHITE).unwrap();
+        atlas.set_pixel(0, 0, Color::RED);
+        atlas.set_pixel(1, 1, Color::BLUE);
+        let mut backend = ImageBackend::new((2, 2), Color::BLACK, &atlas);
+
+        let quads = quad(0.0, 0.0, 2.0, Color::WHITE);
+        backend.draw_quads(&quads, true, LayerBlendMode::Alpha, Vector2f::default());
+
+        assert_eq!(backend.buffer().pixel(0, 0), Some(Color::RED));
+        assert_eq!(backend.buffer().pixel(1, 0), Some(Color::WHITE));
+        assert_eq!(backend.buffer().pixel(1, 1), Some(Color::BLUE));
+
+        // The vertices color tints the texture
+        let quads = quad(0.0, 0.0, 2.0, Color::rgb(0, 255, 255));
+        backend.draw_quads(&quads, true, LayerBlendMode::None, Vector2f::default());
+        assert_eq!(backend.buffer().pixel(0, 0), Some(Color::BLACK));
+        assert_eq!(backend.buffer().pixel(1, 0), Some(Color::rgb(0, 255, 255)));
+    }
+
+    #[test]
+    fn test_geometry_cache() {
+        let mut backend = CountingBackend::default();
+        let mut cache = GeometryCache::new();
+        let mut quads = quad(0.0, 0.0, 1.0, Color::WHITE);
+
+        assert_eq!(*cache.geometry(&mut backend, 0, &quads, true), 4);
+        assert_eq!(*cache.geometry(&mut backend, 0, &quads, true), 4);
+        assert_eq!(backend.builds, 1);
+
+        // Each key has its own geometry
+        assert_eq!(*cache.geometry(&mut backend, 1, &quads, true), 4);
+        assert_eq!(backend.builds, 2);
+
+        // The geometry is built again once the quads are modified
+        quads.append(&Vertex::default());
+        assert_eq!(*cache.geometry(&mut backend, 0, &quads, true), 5);
+        assert_eq!(backend.builds, 3);
+
+        cache.clear();
+        assert_eq!(*cache.geometry(&mut backend, 0, &quads, true), 5);
+        assert_eq!(backend.builds, 4);
+    }
+
+    #[test]
+    fn test_image_backend_render_tile_map() {
+        let tileset = pixel_tileset();
+
+        let mut tile_map = TileMap::new((2, 2), 2, 1);
+        tile_map.set_tile((1, 0), 0, 0).unwrap();
+        tile_map.set_tile((0, 1), 1, 3).unwrap();
+
+        let renderer = TileMapRenderer::new(
+            &tile_map,
+            (4, 4),
+            (2, 2),
+            View::new((2.0, 2.0).into(), (4.0, 4.0).into()),
+            &tileset,
+            false,
+        );
+        let mut backend = ImageBackend::new((4, 4), Color::TRANSPARENT, tileset.atlas());
+        renderer.render(&mut backend);
+
+        // The renderer output matches the CPU rasterizer
+        let expected = rasterize(&tile_map, &tileset, &ExportOptions::default()).unwrap();
+        assert_eq!(backend.into_buffer(), expected);
+    }
+}
@@ -0,0 +1,372 @@
+use sfml::graphics::FloatRect;
+use sfml::system::Vector2f;
+
+use crate::tilemap::TileMapRenderer;
+
+/// The default minimum zoom level of the camera
+pub const DEFAULT_MIN_ZOOM: f32 = 0.25;
+/// The default maximum zoom level of the camera
+pub const DEFAULT_MAX_ZOOM: f32 = 4.0;
+
+/// Camera control the view of a tile map renderer
+/// it smoothly moves toward its destination (i.e a followed target), keeps the view
+/// inside the configured bounds and can shake the screen
+pub struct Camera {
+    /// the world position the camera is centered on (without the shake)
+    position: Vector2f,
+    /// the world position the camera is moving to
+    destination: Vector2f,
+    /// the time (in seconds) needed to travel ~63% of the way to the destination
+    /// 0.0 means the camera reach its destination instantly
+    smoothing: f32,
+    /// the world area the view is kept in
+    bounds: Option<FloatRect>,
+    /// the extra space allowed around the bounds (in world units)
+    margins: Vector2f,
+    /// the current zoom level, 1.0 means no zoom
+    zoom: f32,
+    zoom_limits: (f32, f32),
+    /// the shake amplitude (in world units) and the remaining / total shake duration (in seconds)
+    shake: (f32, f32, f32),
+    /// the camera clock (in seconds), used to compute the shake offset
+    time: f32,
+}
+
+impl Camera {
+    /// Create a new camera centered on given world position
+    pub fn new<P: Into<Vector2f>>(position: P) -> Self {
+        let position = position.into();
+        Camera {
+            position,
+            destination: position,
+            smoothing: 0.0,
+            bounds: None,
+            margins: Vector2f::default(),
+            zoom: 1.0,
+            zoom_limits: (DEFAULT_MIN_ZOOM, DEFAULT_MAX_ZOOM),
+            shake: (0.0, 0.0, 0.0),
+            time: 0.0,
+        }
+    }
+
+    /// Move the camera instantly to given world position
+    pub fn set_position<P: Into<Vector2f>>(&mut self, position: P) {
+        self.position = position.into();
+        self.destination = self.position;
+    }
+
+    /// Returns the world position the camera is centered on
+    pub fn position(&self) -> Vector2f {
+        self.position
+    }
+
+    /// Move the camera destination by given offset
+    pub fn move_<O: Into<Vector2f>>(&mut self, offset: O) {
+        self.destination += offset.into();
+    }
+
+    /// Make the camera move toward given world position (i.e a followed target)
+    pub fn follow<P: Into<Vector2f>>(&mut self, target: P) {
+        self.destination = target.into();
+    }
+
+    /// Returns the world position the camera is moving to
+    pub fn destination(&self) -> Vector2f {
+        self.destination
+    }
+
+    /// Set the time (in seconds) needed to travel ~63% of the way to the destination
+    /// 0.0 disable the smoothing
+    pub fn set_smoothing(&mut self, smoothing: f32) {
+        self.smoothing = smoothing.max(0.0);
+    }
+
+    /// Returns the camera smoothing (in seconds)
+    pub fn smoothing(&self) -> f32 {
+        self.smoothing
+    }
+
+    /// Set the world area the view is kept in (i.e the tile map bounds)
+    /// None let the camera move freely
+    pub fn set_bounds(&mut self, bounds: Option<FloatRect>) {
+        self.bounds = bounds;
+    }
+
+    /// Returns the world area the view is kept in
+    pub fn bounds(&self) -> Option<FloatRect> {
+        self.bounds
+    }
+
+    /// Set the extra space (in world units) the view may show around the bounds
+    pub fn set_margins<M: Into<Vector2f>>(&mut self, margins: M) {
+        self.margins = margins.into();
+    }
+
+    /// Returns the extra space (in world units) the view may show around the bounds
+    pub fn margins(&self) -> Vector2f {
+        self.margins
+    }
+
+    /// Zoom the camera by given factor
+    /// a factor greater than 1.0 will zoom in, lower than 1.0 will zoom out
+    pub fn zoom(&mut self, factor: f32) {
+        self.set_zoom(self.zoom * factor);
+    }
+
+    /// Zoom the camera by given factor while keeping given screen position
+    /// on the same world position (i.e zoom to cursor)
+    /// the zoom is applied on given renderer right away
+    pub fn zoom_at<P: Into<Vector2f>>(
+        &mut self,
+        factor: f32,
+        screen_pos: P,
+        renderer: &mut TileMapRenderer,
+    ) {
+        self.zoom(factor);
+
+        let before = renderer.center();
+        renderer.zoom_at(self.zoom / renderer.zoom_level(), screen_pos);
+
+        let moved = renderer.center() - before;
+        self.position += moved;
+        self.destination += moved;
+    }
+
+    /// Set the camera zoom level, 1.0 means no zoom
+    /// the zoom level is clamped to the camera zoom limits
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(self.zoom_limits.0).min(self.zoom_limits.1);
+    }
+
+    /// Returns the camera zoom level
+    pub fn zoom_level(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Set the minimum / maximum zoom level of the camera
+    pub fn set_zoom_limits(&mut self, min: f32, max: f32) {
+        self.zoom_limits = (min, max);
+        self.set_zoom(self.zoom);
+    }
+
+    /// Shake the camera for given duration (in seconds)
+    /// the intensity is the maximum offset (in world units), it decrease over time
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        self.shake = (intensity, duration, duration);
+    }
+
+    /// Returns true if the camera is currently shaking
+    pub fn is_shaking(&self) -> bool {
+        self.shake.1 > 0.0
+    }
+
+    /// Advance the camera by given delta time (in seconds) and apply it on given renderer
+    pub fn update(&mut self, dt: f32, renderer: &mut TileMapRenderer) {
+        self.time += dt;
+
+        if renderer.zoom_level() != self.zoom {
+            renderer.set_zoom(self.zoom);
+        }
+
+        // Prevent the destination from drifting outside the bounds
+        let view_size = renderer.view_size();
+        self.destination = self.clamp(self.destination, view_size);
+
+        let factor = if self.smoothing > 0.0 {
+            1.0 - (-dt / self.smoothing).exp()
+        } else {
+            1.0
+        };
+        self.position += (self.destination - self.position) * factor;
+        self.position = self.clamp(self.position, view_size);
+
+        renderer.set_center(self.position + self.shake_offset(dt));
+    }
+
+    /// Compute the current shake offset and make the shake decrease by given delta time
+    fn shake_offset(&mut self, dt: f32) -> Vector2f {
+        let (intensity, remaining, duration) = self.shake;
+        if remaining <= 0.0 || duration <= 0.0 {
+            return Vector2f::default();
+        }
+        self.shake.1 = (remaining - dt).max(0.0);
+
+        // Cheap pseudo random noise, the amplitude fade out linearly
+        let amplitude = intensity * remaining / duration;
+        Vector2f::new(
+            (self.time * 97.3).sin() * amplitude,
+            (self.time * 79.1 + 1.3).sin() * amplitude,
+        )
+    }
+
+    /// Clamp given position so that a view of given size stays inside the camera bounds
+    fn clamp(&self, position: Vector2f, view_size: Vector2f) -> Vector2f {
+        match self.bounds {
+            Some(bounds) => clamp_center(
+                position,
+                view_size,
+                FloatRect::new(
+                    bounds.left - self.margins.x,
+                    bounds.top - self.margins.y,
+                    bounds.width + 2.0 * self.margins.x,
+                    bounds.height + 2.0 * self.margins.y,
+                ),
+            ),
+            None => position,
+        }
+    }
+}
+
+/// Clamp given view center so that a view of given size stays inside given bounds
+/// the view is centered on the bounds if it is bigger than them
+fn clamp_center(center: Vector2f, view_size: Vector2f, bounds: FloatRect) -> Vector2f {
+    let clamp = |center: f32, size: f32, start: f32, length: f32| {
+        if size >= length {
+            start + length / 2.0
+        } else {
+            center.clamp(start + size / 2.0, start + length - size / 2.0)
+        }
+    };
+
+    Vector2f::new(
+        clamp(center.x, view_size.x, bounds.left, bounds.width),
+        clamp(center.y, view_size.y, bounds.top, bounds.height),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::graphics::FloatRect;
+    use sfml::system::Vector2f;
+
+    use crate::tilemap::camera::clamp_center;
+    use crate::tilemap::test_utils::{blank_tileset, full_hd_renderer};
+    use crate::tilemap::{Camera, TileMap};
+
+    #[test]
+    fn test_camera_move() {
+        let mut camera = Camera::new((10.0, 10.0));
+
+        camera.move_((5.0, -5.0));
+
+        assert_eq!(camera.position(), (10.0, 10.0).into());
+        assert_eq!(camera.destination(), (15.0, 5.0).into());
+
+        camera.set_position((0.0, 0.0));
+        assert_eq!(camera.position(), (0.0, 0.0).into());
+        assert_eq!(camera.destination(), (0.0, 0.0).into());
+    }
+
+    #[test]
+    fn test_camera_update() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        let mut camera = Camera::new((0.0, 0.0));
+
+        // Without smoothing the destination is reached instantly
+        camera.follow((100.0, 50.0));
+        camera.update(0.1, &mut renderer);
+        assert_eq!(camera.position(), (100.0, 50.0).into());
+        assert_eq!(renderer.center(), (100.0, 50.0).into());
+
+        // With smoothing the camera move part of the way
+        camera.set_smoothing(0.5);
+        camera.follow((200.0, 50.0));
+        camera.update(0.1, &mut renderer);
+        assert!(camera.position().x > 100.0 && camera.position().x < 200.0);
+        for _ in 0..100 {
+            camera.update(0.1, &mut renderer);
+        }
+        assert!((camera.position().x - 200.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_camera_set_bounds() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        let mut camera = Camera::new((0.0, 0.0));
+        camera.set_bounds(Some(renderer.world_bounds()));
+
+        camera.move_((-1000.0, 2000.0));
+        camera.update(0.1, &mut renderer);
+        assert_eq!(camera.position(), (5.0, 1075.0).into());
+        assert_eq!(camera.destination(), (5.0, 1075.0).into());
+
+        // Margins allow to see a bit outside the map
+        camera.set_margins((10.0, 0.0));
+        camera.move_((-1000.0, 0.0));
+        camera.update(0.1, &mut renderer);
+        assert_eq!(camera.position(), (-5.0, 1075.0).into());
+    }
+
+    #[test]
+    fn test_camera_zoom() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        let mut camera = Camera::new((0.0, 0.0));
+        camera.set_zoom_limits(0.5, 2.0);
+
+        camera.zoom(4.0);
+        assert_eq!(camera.zoom_level(), 2.0);
+
+        camera.update(0.1, &mut renderer);
+        assert_eq!(renderer.zoom_level(), 2.0);
+        assert_eq!(renderer.view_size(), (5.0, 5.0).into());
+
+        // Zoom to cursor move the camera too
+        camera.zoom_at(0.5, (5.0, 5.0), &mut renderer);
+        assert_eq!(camera.zoom_level(), 1.0);
+        assert_eq!(camera.position(), renderer.center());
+
+        // The camera limits apply to the renderer too
+        camera.zoom_at(100.0, (5.0, 5.0), &mut renderer);
+        assert_eq!(camera.zoom_level(), 2.0);
+        assert_eq!(renderer.zoom_level(), 2.0);
+    }
+
+    #[test]
+    fn test_camera_shake() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        let mut camera = Camera::new((100.0, 100.0));
+
+        camera.shake(10.0, 1.0);
+        assert!(camera.is_shaking());
+        camera.update(0.1, &mut renderer);
+
+        // The shake only affect the view, not the camera position
+        assert_eq!(camera.position(), (100.0, 100.0).into());
+        let offset = renderer.center() - camera.position();
+        assert!(offset.x.abs() <= 10.0 && offset.y.abs() <= 10.0);
+        assert_ne!(offset, Vector2f::default());
+
+        camera.update(1.0, &mut renderer);
+        assert!(!camera.is_shaking());
+        camera.update(0.1, &mut renderer);
+        assert_eq!(renderer.center(), (100.0, 100.0).into());
+    }
+
+    #[test]
+    fn test_clamp_center() {
+        let bounds = FloatRect::new(0.0, 0.0, 100.0, 50.0);
+
+        assert_eq!(
+            clamp_center((50.0, 25.0).into(), (10.0, 10.0).into(), bounds),
+            (50.0, 25.0).into()
+        );
+        assert_eq!(
+            clamp_center((-50.0, 100.0).into(), (10.0, 10.0).into(), bounds),
+            (5.0, 45.0).into()
+        );
+
+        // The view is bigger than the bounds on the y axis
+        assert_eq!(
+            clamp_center((0.0, 0.0).into(), (10.0, 80.0).into(), bounds),
+            (5.0, 25.0).into()
+        );
+    }
+}
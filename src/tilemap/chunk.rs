@@ -0,0 +1,437 @@
+use sfml::graphics::{Color, IntRect};
+use sfml::system::{Vector2f, Vector2u};
+use std::collections::BTreeSet;
+use std::ops::IndexMut;
+
+use crate::geometry::{Quads, Vertex};
+
+/// The number of tiles on each side of a chunk
+pub const CHUNK_SIZE: u32 = 16;
+
+/// The color of the placeholder checker cells
+const PLACEHOLDER_COLORS: [Color; 2] = [Color::MAGENTA, Color::BLACK];
+
+/// TileDisplay define how a tile should be displayed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TileDisplay {
+    /// Nothing is displayed
+    Empty,
+    /// The tile is displayed using given texture rect, maybe flipped
+    /// offset and size are expressed in world units, relative to the tile cell
+    Texture {
+        rect: IntRect,
+        offset: Vector2f,
+        size: Vector2f,
+        flip_x: bool,
+        flip_y: bool,
+    },
+    /// The tile is displayed as a placeholder (magenta checker)
+    Placeholder,
+}
+
+/// Chunk is a rectangular part of a tile map layer rendered in a single draw call
+/// its geometry is only built once it is needed
+pub struct Chunk {
+    /// position of the chunk top left tile
+    position: Vector2u,
+    /// the chunk size in tiles, may be lower than CHUNK_SIZE on the map borders
+    size: Vector2u,
+    quads: Option<Quads>,
+    /// the (untextured) placeholders geometry
+    placeholders: Quads,
+    /// the index of the tiles displayed as placeholder
+    missing: BTreeSet<u32>,
+    /// the color the tiles are multiplied with
+    color: Color,
+}
+
+impl Chunk {
+    /// Create a new chunk starting at given tile position, with given size (in tiles)
+    pub fn new<T: Into<Vector2u>>(position: T, size: T) -> Self {
+        Chunk {
+            position: position.into(),
+            size: size.into(),
+            quads: None,
+            placeholders: Quads::new(),
+            missing: BTreeSet::new(),
+            color: Color::WHITE,
+        }
+    }
+
+    /// Build the chunk geometry
+    ///
+    /// # Arguments
+    /// - tiles: the layer tiles in row major order
+    /// - map_width: the tile map width, used to index tiles
+    /// - tile_size: the size of a tile cell in world units
+    /// - tile_display: returns how given tile (position, id) should be displayed
+    pub fn build<F: Fn(Vector2u, u32) -> TileDisplay>(
+        &mut self,
+        tiles: &[u32],
+        map_width: u32,
+        tile_size: Vector2f,
+        tile_display: F,
+    ) {
+        let mut quads = Quads::with_vertex_count((self.size.x * self.size.y * 4) as usize);
+        self.missing.clear();
+
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                let index = x + y * self.size.x;
+                let position = Vector2u::new(self.position.x + x, self.position.y + y);
+                let tile_id = tiles[(position.x + position.y * map_width) as usize];
+                let display = tile_display(position, tile_id);
+
+                if display == TileDisplay::Placeholder {
+                    self.missing.insert(index);
+                }
+                set_quad(
+                    &mut quads,
+                    index as usize,
+                    position,
+                    tile_size,
+                    self.color,
+                    display,
+                );
+            }
+        }
+
+        self.quads = Some(quads);
+        self.build_placeholders(tile_size);
+    }
+
+    /// Update the tile at given (map) position
+    /// nothing is done if the chunk is not built yet
+    pub fn set_tile(&mut self, position: Vector2u, tile_size: Vector2f, display: TileDisplay) {
+        let index = position.x - self.position.x + (position.y - self.position.y) * self.size.x;
+
+        if let Some(quads) = &mut self.quads {
+            set_quad(
+                quads,
+                index as usize,
+                position,
+                tile_size,
+                self.color,
+                display,
+            );
+
+            // Only rebuild the placeholders if they are affected by the change
+            let changed = if display == TileDisplay::Placeholder {
+                self.missing.insert(index)
+            } else {
+                self.missing.remove(&index)
+            };
+            if changed {
+                self.build_placeholders(tile_size);
+            }
+        }
+    }
+
+    /// Set the color the tiles are multiplied with
+    /// the chunk geometry will need to be built again if the color has changed
+    pub fn set_color(&mut self, color: Color) {
+        if self.color != color {
+            self.color = color;
+            self.quads = None;
+            self.placeholders.clear();
+        }
+    }
+
+    /// Returns true if the chunk geometry has been built
+    pub fn is_built(&self) -> bool {
+        self.quads.is_some()
+    }
+
+    /// Retrieve the chunk geometry, if built
+    pub fn quads(&self) -> Option<&Quads> {
+        self.quads.as_ref()
+    }
+
+    /// Retrieve the placeholders geometry (to be drawn without texture)
+    pub fn placeholders(&self) -> &Quads {
+        &self.placeholders
+    }
+
+    /// Build the placeholders geometry: a 2x2 checker for each missing tile
+    fn build_placeholders(&mut self, tile_size: Vector2f) {
+        let cell_size = tile_size / 2.0;
+        self.placeholders.clear();
+
+        for index in &self.missing {
+            let left = (self.position.x + index % self.size.x) as f32 * tile_size.x;
+            let top = (self.position.y + index / self.size.x) as f32 * tile_size.y;
+
+            for cell in 0..4 {
+                let left = left + (cell % 2) as f32 * cell_size.x;
+                let top = top + (cell / 2) as f32 * cell_size.y;
+                let color = PLACEHOLDER_COLORS[((cell % 2) ^ (cell / 2)) as usize] * self.color;
+
+                for (x, y) in &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                    self.placeholders.append(&Vertex::with_pos_color(
+                        (left + x * cell_size.x, top + y * cell_size.y),
+                        color,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Update the quad at given index to display given tile using given color
+/// the quad will be made transparent if there is no texture to display
+pub fn set_quad(
+    quads: &mut Quads,
+    index: usize,
+    position: Vector2u,
+    tile_size: Vector2f,
+    color: Color,
+    display: TileDisplay,
+) {
+    let cell_left = position.x as f32 * tile_size.x;
+    let cell_top = position.y as f32 * tile_size.y;
+
+    let (color, positions, tex_coords) = match display {
+        TileDisplay::Texture {
+            rect,
+            offset,
+            size,
+            flip_x,
+            flip_y,
+        } => {
+            let (left, top) = (cell_left + offset.x, cell_top + offset.y);
+            let (right, bottom) = (left + size.x, top + size.y);
+            let (mut tex_left, mut tex_top) = (rect.left as f32, rect.top as f32);
+            let (mut tex_right, mut tex_bottom) =
+                (tex_left + rect.width as f32, tex_top + rect.height as f32);
+            if flip_x {
+                std::mem::swap(&mut tex_left, &mut tex_right);
+            }
+            if flip_y {
+                std::mem::swap(&mut tex_top, &mut tex_bottom);
+            }
+            (
+                color,
+                [(left, top), (right, top), (right, bottom), (left, bottom)],
+                [
+                    (tex_left, tex_top),
+                    (tex_right, tex_top),
+                    (tex_right, tex_bottom),
+                    (tex_left, tex_bottom),
+                ],
+            )
+        }
+        TileDisplay::Empty | TileDisplay::Placeholder => {
+            let (right, bottom) = (cell_left + tile_size.x, cell_top + tile_size.y);
+            (
+                Color::TRANSPARENT,
+                [
+                    (cell_left, cell_top),
+                    (right, cell_top),
+                    (right, bottom),
+                    (cell_left, bottom),
+                ],
+                [(0.0, 0.0); 4],
+            )
+        }
+    };
+
+    for i in 0..4 {
+        let vertex = quads.index_mut(index * 4 + i);
+        vertex.position = positions[i].into();
+        vertex.color = color;
+        vertex.tex_coords = tex_coords[i].into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_build() {
+        let tiles = vec![0, 1, 1, 0, 1, 0];
+        let mut chunk = Chunk::new((1, 0), (2, 2));
+        assert!(!chunk.is_built());
+        assert!(chunk.quads().is_none());
+
+        chunk.build(&tiles, 3, (10.0, 10.0).into(), |_, tile_id| {
+            if tile_id == 0 {
+                TileDisplay::Empty
+            } else {
+                texture(IntRect::new(16, 0, 16, 16))
+            }
+        });
+
+        assert!(chunk.is_built());
+        let quads = chunk.quads().unwrap();
+        assert_eq!(quads.vertex_count(), 2 * 2 * 4);
+
+        // First quad is the tile (1, 0)
+        assert_eq!(quads[0].position, (10.0, 0.0).into());
+        assert_eq!(quads[2].position, (20.0, 10.0).into());
+        assert_eq!(quads[0].tex_coords, (16.0, 0.0).into());
+        assert_eq!(quads[0].color, Color::WHITE);
+
+        // Last quad is the tile (2, 1) which is air
+        assert_eq!(quads[12].position, (20.0, 10.0).into());
+        assert_eq!(quads[12].color, Color::TRANSPARENT);
+        assert_eq!(chunk.placeholders().vertex_count(), 0);
+    }
+
+    #[test]
+    fn test_chunk_build_placeholders() {
+        let tiles = vec![1, 2, 2, 1];
+        let mut chunk = Chunk::new((0, 0), (2, 2));
+
+        chunk.build(&tiles, 2, (10.0, 10.0).into(), |_, tile_id| {
+            if tile_id == 1 {
+                texture(IntRect::new(0, 0, 16, 16))
+            } else {
+                TileDisplay::Placeholder
+            }
+        });
+
+        // Placeholder tiles are transparent in the textured geometry
+        let quads = chunk.quads().unwrap();
+        assert_eq!(quads[4].color, Color::TRANSPARENT);
+
+        // 2 missing tiles * 4 checker cells * 4 vertices
+        let placeholders = chunk.placeholders();
+        assert_eq!(placeholders.vertex_count(), 2 * 4 * 4);
+        assert_eq!(placeholders[0].position, (10.0, 0.0).into());
+        assert_eq!(placeholders[0].color, Color::MAGENTA);
+        assert_eq!(placeholders[2].position, (15.0, 5.0).into());
+        assert_eq!(placeholders[4].color, Color::BLACK);
+        assert_eq!(placeholders[16].position, (0.0, 10.0).into());
+
+        // Replacing a missing tile removes its placeholder
+        chunk.set_tile((1, 0).into(), (10.0, 10.0).into(), TileDisplay::Empty);
+        assert_eq!(chunk.placeholders().vertex_count(), 4 * 4);
+        chunk.set_tile((1, 1).into(), (10.0, 10.0).into(), TileDisplay::Placeholder);
+        assert_eq!(chunk.placeholders().vertex_count(), 2 * 4 * 4);
+    }
+
+    #[test]
+    fn test_chunk_set_tile() {
+        let tiles = vec![1; 4];
+        let mut chunk = Chunk::new((0, 0), (2, 2));
+
+        // Not built yet: nothing to update
+        chunk.set_tile((1, 1).into(), (10.0, 10.0).into(), TileDisplay::Empty);
+        assert!(!chunk.is_built());
+
+        chunk.build(&tiles, 2, (10.0, 10.0).into(), |_, _| {
+            texture(IntRect::new(0, 0, 16, 16))
+        });
+        chunk.set_tile((1, 1).into(), (10.0, 10.0).into(), TileDisplay::Empty);
+
+        let quads = chunk.quads().unwrap();
+        assert_eq!(quads[0].color, Color::WHITE);
+        assert_eq!(quads[12].color, Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_chunk_set_tile_offset() {
+        let tiles = vec![0; 4];
+        let mut chunk = Chunk::new((0, 0), (2, 2));
+        chunk.build(&tiles, 2, (10.0, 20.0).into(), |_, _| TileDisplay::Empty);
+
+        // A 2x2 cells sprite anchored on the bottom left of its cell
+        chunk.set_tile(
+            (1, 1).into(),
+            (10.0, 20.0).into(),
+            TileDisplay::Texture {
+                rect: IntRect::new(0, 0, 32, 32),
+                offset: (0.0, -20.0).into(),
+                size: (20.0, 40.0).into(),
+                flip_x: false,
+                flip_y: false,
+            },
+        );
+
+        let quads = chunk.quads().unwrap();
+        assert_eq!(quads[12].position, (10.0, 0.0).into());
+        assert_eq!(quads[14].position, (30.0, 40.0).into());
+        assert_eq!(quads[14].tex_coords, (32.0, 32.0).into());
+    }
+
+    #[test]
+    fn test_chunk_set_tile_flip() {
+        let tiles = vec![0; 4];
+        let mut chunk = Chunk::new((0, 0), (2, 2));
+        chunk.build(&tiles, 2, (10.0, 10.0).into(), |_, _| TileDisplay::Empty);
+
+        chunk.set_tile(
+            (0, 0).into(),
+            (10.0, 10.0).into(),
+            TileDisplay::Texture {
+                rect: IntRect::new(16, 0, 16, 16),
+                offset: (0.0, 0.0).into(),
+                size: (10.0, 10.0).into(),
+                flip_x: true,
+                flip_y: false,
+            },
+        );
+        chunk.set_tile(
+            (1, 0).into(),
+            (10.0, 10.0).into(),
+            TileDisplay::Texture {
+                rect: IntRect::new(16, 0, 16, 16),
+                offset: (0.0, 0.0).into(),
+                size: (10.0, 10.0).into(),
+                flip_x: false,
+                flip_y: true,
+            },
+        );
+
+        let quads = chunk.quads().unwrap();
+        assert_eq!(quads[0].position, (0.0, 0.0).into());
+        assert_eq!(quads[0].tex_coords, (32.0, 0.0).into());
+        assert_eq!(quads[2].tex_coords, (16.0, 16.0).into());
+        assert_eq!(quads[4].tex_coords, (16.0, 16.0).into());
+        assert_eq!(quads[6].tex_coords, (32.0, 0.0).into());
+    }
+
+    #[test]
+    fn test_chunk_set_color() {
+        let tiles = vec![1, 2, 2, 1];
+        let mut chunk = Chunk::new((0, 0), (2, 2));
+        let build = |chunk: &mut Chunk| {
+            chunk.build(&tiles, 2, (10.0, 10.0).into(), |_, tile_id| {
+                if tile_id == 1 {
+                    texture(IntRect::new(0, 0, 16, 16))
+                } else {
+                    TileDisplay::Placeholder
+                }
+            })
+        };
+        build(&mut chunk);
+
+        // Same color: geometry is kept
+        chunk.set_color(Color::WHITE);
+        assert!(chunk.is_built());
+
+        // Color changed: geometry needs to be rebuilt
+        chunk.set_color(Color::rgba(255, 0, 0, 128));
+        assert!(!chunk.is_built());
+        assert_eq!(chunk.placeholders().vertex_count(), 0);
+
+        build(&mut chunk);
+        let quads = chunk.quads().unwrap();
+        assert_eq!(quads[0].color, Color::rgba(255, 0, 0, 128));
+        assert_eq!(quads[4].color, Color::TRANSPARENT);
+        assert_eq!(chunk.placeholders()[0].color, Color::rgba(255, 0, 0, 128));
+        assert_eq!(chunk.placeholders()[4].color, Color::rgba(0, 0, 0, 128));
+    }
+
+    /// Display the whole cell using given texture rect
+    fn texture(rect: IntRect) -> TileDisplay {
+        TileDisplay::Texture {
+            rect,
+            offset: (0.0, 0.0).into(),
+            size: (10.0, 10.0).into(),
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+}
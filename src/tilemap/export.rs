@@ -0,0 +1,293 @@
+use sfml::graphics::{Color, Image, IntRect, RenderTarget, RenderTexture, View};
+use sfml::system::{Vector2f, Vector2u};
+use std::convert::TryFrom;
+
+use crate::tilemap::{LayerSettings, MissingTilePolicy, TileMap, TileMapRenderer, Tileset};
+
+/// The maximum size (in bytes) of an exported image
+const MAX_IMAGE_BYTES: usize = 1 << 30;
+
+#[derive(Debug, PartialEq)]
+pub enum ExportError {
+    /// The exported region is empty or outside the tile map
+    InvalidRegion,
+    InvalidLayer,
+    /// The scale is not a positive finite number, or gives an empty image
+    InvalidScale,
+    /// The image size (in pixels) overflows or exceeds the maximum exported image size
+    ImageTooLarge,
+    /// The offscreen render texture cannot be created (i.e the image is too big)
+    RenderError,
+    WriteError,
+}
+
+/// ExportOptions define which part of the tile map is exported, and how
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ExportOptions {
+    /// the exported region (in tiles), None export the whole tile map
+    pub region: Option<IntRect>,
+    /// the exported layer, None export every visible layer
+    pub layer: Option<u32>,
+    /// the number of image pixels per tileset pixel
+    pub scale: f32,
+    /// the color of the image background
+    pub background: Color,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            region: None,
+            layer: None,
+            scale: 1.0,
+            background: Color::TRANSPARENT,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Retrieve the exported region (in tiles) of given tile map
+    /// this will fails if the region or the layer doesn't exist
+    pub(crate) fn resolve_region(&self, tile_map: &TileMap) -> Result<IntRect, ExportError> {
+        let map_size = tile_map.size();
+        let region = self
+            .region
+            .unwrap_or_else(|| IntRect::new(0, 0, map_size.x as i32, map_size.y as i32));
+        // The region end may overflow (i.e a huge width)
+        let fits = |start: i32, length: i32, size: u32| {
+            start
+                .checked_add(length)
+                .and_then(|end| u32::try_from(end).ok())
+                .is_some_and(|end| end <= size)
+        };
+        if region.left < 0
+            || region.top < 0
+            || region.width <= 0
+            || region.height <= 0
+            || !fits(region.left, region.width, map_size.x)
+            || !fits(region.top, region.height, map_size.y)
+        {
+            return Err(ExportError::InvalidRegion);
+        }
+
+        if self
+            .layer
+            .is_some_and(|layer| layer >= tile_map.layer_count())
+        {
+            return Err(ExportError::InvalidLayer);
+        }
+
+        Ok(region)
+    }
+
+    /// Compute the size (in pixels) of the image of given region, made of tiles of given size
+    /// this will fails if the scale is invalid or if the image is too large
+    pub(crate) fn image_size(
+        &self,
+        region: IntRect,
+        cell_size: Vector2u,
+    ) -> Result<Vector2u, ExportError> {
+        if !self.scale.is_finite() || self.scale <= 0.0 {
+            return Err(ExportError::InvalidScale);
+        }
+
+        let length = |tiles: i32, cell: u32| -> Result<u32, ExportError> {
+            let pixels = (tiles as f64 * cell as f64 * self.scale as f64).round();
+            if pixels > u32::MAX as f64 {
+                return Err(ExportError::ImageTooLarge);
+            }
+            Ok(pixels as u32)
+        };
+        let size = Vector2u::new(
+            length(region.width, cell_size.x)?,
+            length(region.height, cell_size.y)?,
+        );
+        if size.x == 0 || size.y == 0 {
+            return Err(ExportError::InvalidScale);
+        }
+
+        // The pixels are stored as RGBA bytes
+        let bytes = (size.x as usize)
+            .checked_mul(size.y as usize)
+            .and_then(|pixels| pixels.checked_mul(4));
+        if bytes.is_none_or(|bytes| bytes > MAX_IMAGE_BYTES) {
+            return Err(ExportError::ImageTooLarge);
+        }
+
+        Ok(size)
+    }
+}
+
+/// Render given tile map into an image, using an offscreen render texture
+/// the tiles missing from the tileset are skipped, as done by rasterize
+pub fn render_to_image(
+    tile_map: &TileMap,
+    tileset: &Tileset,
+    options: &ExportOptions,
+) -> Result<Image, ExportError> {
+    let region = options.resolve_region(tile_map)?;
+
+    // Use one world unit per image pixel, so that the view match the region exactly
+    let cell_size = tileset.tile_size();
+    let image_size = options.image_size(region, cell_size)?;
+    let region_size = Vector2u::new(region.width as u32, region.height as u32);
+    let size = Vector2f::new(image_size.x as f32, image_size.y as f32);
+    let origin = Vector2f::new(
+        region.left as f32 * cell_size.x as f32 * options.scale,
+        region.top as f32 * cell_size.y as f32 * options.scale,
+    );
+
+    let mut renderer = TileMapRenderer::with_policy(
+        tile_map,
+        image_size,
+        region_size,
+        View::new(origin + size / 2.0, size),
+        tileset,
+        false,
+        MissingTilePolicy::Skip,
+    )
+    .map_err(|_| ExportError::RenderError)?;
+
+    // Only keep the exported layer visible
+    if let Some(layer) = options.layer {
+        for other in (0..tile_map.layer_count()).filter(|other| *other != layer) {
+            let settings = LayerSettings {
+                visible: false,
+                ..tile_map.layer_settings(other).copied().unwrap_or_default()
+            };
+            renderer
+                .set_layer_settings(other, settings)
+                .map_err(|_| ExportError::InvalidLayer)?;
+        }
+    }
+
+    let mut target =
+        RenderTexture::new(image_size.x, image_size.y, false).ok_or(ExportError::RenderError)?;
+    target.clear(options.background);
+    target.draw(&renderer);
+    target.display();
+
+    target
+        .texture()
+        .copy_to_image()
+        .ok_or(ExportError::RenderError)
+}
+
+/// Render given tile map and save it as an image file (the format is deduced from the extension)
+pub fn export_to_file(
+    tile_map: &TileMap,
+    tileset: &Tileset,
+    options: &ExportOptions,
+    path: &str,
+) -> Result<(), ExportError> {
+    let image = render_to_image(tile_map, tileset, options)?;
+    if image.save_to_file(path) {
+        Ok(())
+    } else {
+        Err(ExportError::WriteError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::graphics::{Color, IntRect};
+
+    use crate::tilemap::test_utils::red_blue_tileset;
+    use crate::tilemap::{render_to_image, ExportError, ExportOptions, TileMap};
+
+    #[test]
+    fn test_render_to_image() {
+        let tileset = red_blue_tileset();
+        let mut tile_map = TileMap::new((4, 3), 2, 1);
+        tile_map.set_tile((1, 1), 0, 2).unwrap();
+        tile_map.set_tile((3, 2), 1, 2).unwrap();
+
+        let image = render_to_image(&tile_map, &tileset, &ExportOptions::default()).unwrap();
+        assert_eq!(image.size(), (64, 48).into());
+        assert_eq!(image.pixel_at(0, 0), Color::RED);
+        assert_eq!(image.pixel_at(20, 20), Color::BLUE);
+        assert_eq!(image.pixel_at(60, 40), Color::BLUE);
+    }
+
+    #[test]
+    fn test_render_to_image_region() {
+        let tileset = red_blue_tileset();
+        let mut tile_map = TileMap::new((4, 3), 2, 1);
+        tile_map.set_tile((1, 1), 0, 2).unwrap();
+        tile_map.set_tile((3, 2), 1, 2).unwrap();
+
+        // Only the second layer, half size
+        let options = ExportOptions {
+            region: Some(IntRect::new(1, 1, 3, 2)),
+            layer: Some(1),
+            scale: 0.5,
+            ..Default::default()
+        };
+        let image = render_to_image(&tile_map, &tileset, &options).unwrap();
+        assert_eq!(image.size(), (24, 16).into());
+        assert_eq!(image.pixel_at(2, 2), Color::TRANSPARENT);
+        assert_eq!(image.pixel_at(20, 12), Color::BLUE);
+
+        let options = ExportOptions {
+            region: Some(IntRect::new(2, 2, 3, 2)),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_to_image(&tile_map, &tileset, &options).err(),
+            Some(ExportError::InvalidRegion)
+        );
+
+        let options = ExportOptions {
+            region: Some(IntRect::new(1, 0, i32::MAX, 1)),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_to_image(&tile_map, &tileset, &options).err(),
+            Some(ExportError::InvalidRegion)
+        );
+
+        let options = ExportOptions {
+            layer: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_to_image(&tile_map, &tileset, &options).err(),
+            Some(ExportError::InvalidLayer)
+        );
+    }
+
+    #[test]
+    fn test_render_to_image_missing_tiles() {
+        let tileset = red_blue_tileset();
+        let mut tile_map = TileMap::new((2, 1), 1, 1);
+        tile_map.set_tile((1, 0), 0, 9).unwrap();
+
+        // The missing tiles are skipped, like the CPU rasterizer does
+        let image = render_to_image(&tile_map, &tileset, &ExportOptions::default()).unwrap();
+        assert_eq!(image.pixel_at(0, 0), Color::RED);
+        assert_eq!(image.pixel_at(20, 8), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_export_options_image_size() {
+        let region = IntRect::new(0, 0, 4, 3);
+        let image_size = |scale: f32| {
+            let options = ExportOptions {
+                scale,
+                ..Default::default()
+            };
+            options.image_size(region, (16, 16).into())
+        };
+
+        assert_eq!(image_size(1.0), Ok((64, 48).into()));
+        assert_eq!(image_size(0.5), Ok((32, 24).into()));
+        assert_eq!(image_size(0.0), Err(ExportError::InvalidScale));
+        assert_eq!(image_size(-1.0), Err(ExportError::InvalidScale));
+        assert_eq!(image_size(f32::NAN), Err(ExportError::InvalidScale));
+        assert_eq!(image_size(f32::INFINITY), Err(ExportError::InvalidScale));
+        assert_eq!(image_size(0.001), Err(ExportError::InvalidScale));
+        assert_eq!(image_size(1e9), Err(ExportError::ImageTooLarge));
+        assert_eq!(image_size(1e7), Err(ExportError::ImageTooLarge));
+        assert_eq!(image_size(256.0), Ok((16384, 12288).into()));
+    }
+}
@@ -0,0 +1,306 @@
+use sfml::graphics::Color;
+use sfml::system::Vector2u;
+
+use crate::tilemap::TILE_ID_MASK;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// DayNightCycle define the ambient light color over time
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayNightCycle {
+    /// the duration of a whole day (in seconds)
+    pub duration: f32,
+    /// the ambient color at given time of the day (from 0.0 to 1.0), sorted by time
+    /// the colors are interpolated between the keyframes
+    pub keyframes: Vec<(f32, Color)>,
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        DayNightCycle {
+            duration: 240.0,
+            keyframes: vec![
+                (0.0, Color::rgb(40, 50, 110)),
+                (0.25, Color::rgb(255, 180, 140)),
+                (0.5, Color::WHITE),
+                (0.75, Color::rgb(255, 150, 110)),
+            ],
+        }
+    }
+}
+
+impl DayNightCycle {
+    /// Compute the ambient color at given time (in seconds)
+    pub fn ambient_at(&self, time: f32) -> Color {
+        let (first, last) = match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Color::WHITE,
+        };
+        if self.duration <= 0.0 {
+            return first.1;
+        }
+
+        // Find the keyframes around the time of the day, wrapping around midnight
+        let time = (time / self.duration).rem_euclid(1.0);
+        let (from, to) = match self.keyframes.iter().position(|(t, _)| *t > time) {
+            Some(0) => ((last.0 - 1.0, last.1), first),
+            Some(index) => (self.keyframes[index - 1], self.keyframes[index]),
+            None => (last, (first.0 + 1.0, first.1)),
+        };
+
+        let progress = if to.0 > from.0 {
+            (time - from.0) / (to.0 - from.0)
+        } else {
+            0.0
+        };
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * progress).round() as u8;
+        Color::rgb(
+            lerp(from.1.r, to.1.r),
+            lerp(from.1.g, to.1.g),
+            lerp(from.1.b, to.1.b),
+        )
+    }
+}
+
+/// LightSource is a point light emitted by a tile (i.e a tower torch)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightSource {
+    pub color: Color,
+    /// the distance (in tiles) the light reaches
+    pub radius: f32,
+    /// the light strength at its center, it decreases linearly up to the radius
+    pub intensity: f32,
+}
+
+impl LightSource {
+    /// Retrieve the radius the light is computed with, a radius below 1.0 only lights the source cell
+    fn effective_radius(&self) -> f32 {
+        self.radius.max(1.0)
+    }
+
+    /// Retrieve the columns & rows of a grid of given size the light placed at given position may reach
+    pub fn reach(&self, size: Vector2u, center: Vector2u) -> (Range<u32>, Range<u32>) {
+        let reach = self.effective_radius().ceil() as u32;
+        (
+            center.x.saturating_sub(reach)..center.x.saturating_add(reach + 1).min(size.x),
+            center.y.saturating_sub(reach)..center.y.saturating_add(reach + 1).min(size.y),
+        )
+    }
+}
+
+/// Lighting holds the lighting settings of a tile map
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lighting {
+    pub cycle: DayNightCycle,
+    /// the lights emitted by the tiles (or objects) of given id
+    pub lights: BTreeMap<u32, LightSource>,
+    /// the tiles having this tileset property block the lights
+    pub blocking_property: String,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting {
+            cycle: DayNightCycle::default(),
+            lights: BTreeMap::new(),
+            blocking_property: "blocks_light".to_string(),
+        }
+    }
+}
+
+impl Lighting {
+    /// Retrieve the light emitted by given tile, if any
+    pub fn light(&self, tile_id: u32) -> Option<&LightSource> {
+        self.lights.get(&(tile_id & TILE_ID_MASK))
+    }
+}
+
+/// Compute the light received by each cell of a grid of given size, in row major order
+/// each source lights the cells in its radius that are in its line of sight,
+/// the blocking cells are lit but the cells behind them are not
+pub fn compute_lights<F: Fn(Vector2u) -> bool>(
+    size: Vector2u,
+    sources: &[(Vector2u, LightSource)],
+    is_blocking: F,
+) -> Vec<Color> {
+    let mut lights = vec![[0.0_f32; 3]; (size.x * size.y) as usize];
+
+    for (center, source) in sources {
+        for (position, strength) in cast_light(size, *center, source, &is_blocking) {
+            let light = &mut lights[(position.x + position.y * size.x) as usize];
+            light[0] += source.color.r as f32 * strength;
+            light[1] += source.color.g as f32 * strength;
+            light[2] += source.color.b as f32 * strength;
+        }
+    }
+
+    lights
+        .into_iter()
+        .map(|[r, g, b]| {
+            let channel = |v: f32| v.clamp(0.0, 255.0).round() as u8;
+            Color::rgb(channel(r), channel(g), channel(b))
+        })
+        .collect()
+}
+
+/// Compute the cells of a grid of given size lit by given source placed at given position,
+/// along the light strength they receive (see compute_lights)
+pub fn cast_light<F: Fn(Vector2u) -> bool>(
+    size: Vector2u,
+    center: Vector2u,
+    source: &LightSource,
+    is_blocking: F,
+) -> Vec<(Vector2u, f32)> {
+    let radius = source.effective_radius();
+    let (x_range, y_range) = source.reach(size, center);
+
+    let mut cells = Vec::new();
+    for y in y_range {
+        for x in x_range.clone() {
+            let position = Vector2u::new(x, y);
+            let (dx, dy) = (x.abs_diff(center.x) as f32, y.abs_diff(center.y) as f32);
+            let distance = (dx * dx + dy * dy).sqrt();
+            // The cells at the radius receive no light
+            if distance >= radius || !in_line_of_sight(center, position, &is_blocking) {
+                continue;
+            }
+
+            cells.push((position, source.intensity * (1.0 - distance / radius)));
+        }
+    }
+    cells
+}
+
+/// Determinate if there is no blocking cell between given cells (the cells themselves excluded)
+fn in_line_of_sight<F: Fn(Vector2u) -> bool>(
+    from: Vector2u,
+    to: Vector2u,
+    is_blocking: &F,
+) -> bool {
+    // Bresenham's line algorithm
+    let (mut x, mut y) = (from.x as i64, from.y as i64);
+    let (end_x, end_y) = (to.x as i64, to.y as i64);
+    let (dx, dy) = ((end_x - x).abs(), -(end_y - y).abs());
+    let (step_x, step_y) = ((end_x - x).signum(), (end_y - y).signum());
+    let mut error = dx + dy;
+
+    loop {
+        if (x, y) == (end_x, end_y) {
+            return true;
+        }
+        if (x, y) != (from.x as i64, from.y as i64)
+            && is_blocking(Vector2u::new(x as u32, y as u32))
+        {
+            return false;
+        }
+
+        let double_error = 2 * error;
+        if double_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if double_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::FLIP_HORIZONTAL;
+
+    #[test]
+    fn test_day_night_cycle_ambient_at() {
+        let cycle = DayNightCycle {
+            duration: 100.0,
+            keyframes: vec![(0.0, Color::BLACK), (0.5, Color::WHITE)],
+        };
+
+        assert_eq!(cycle.ambient_at(0.0), Color::BLACK);
+        assert_eq!(cycle.ambient_at(25.0), Color::rgb(128, 128, 128));
+        assert_eq!(cycle.ambient_at(50.0), Color::WHITE);
+        // Wrap around midnight
+        assert_eq!(cycle.ambient_at(75.0), Color::rgb(128, 128, 128));
+        assert_eq!(cycle.ambient_at(200.0), Color::BLACK);
+
+        let cycle = DayNightCycle {
+            duration: 100.0,
+            keyframes: vec![],
+        };
+        assert_eq!(cycle.ambient_at(10.0), Color::WHITE);
+    }
+
+    #[test]
+    fn test_lighting_light() {
+        let mut lighting = Lighting::default();
+        let torch = LightSource {
+            color: Color::YELLOW,
+            radius: 3.0,
+            intensity: 1.0,
+        };
+        lighting.lights.insert(5, torch);
+
+        assert_eq!(lighting.light(5), Some(&torch));
+        assert_eq!(lighting.light(5 | FLIP_HORIZONTAL), Some(&torch));
+        assert_eq!(lighting.light(6), None);
+    }
+
+    #[test]
+    fn test_compute_lights() {
+        let source = LightSource {
+            color: Color::rgb(200, 100, 0),
+            radius: 3.0,
+            intensity: 1.0,
+        };
+
+        // A wall on (1, 1) blocks the light on its right
+        let lights = compute_lights((4, 3).into(), &[((0, 1).into(), source)], |position| {
+            position == Vector2u::new(1, 1)
+        });
+
+        let light_at = |x: u32, y: u32| lights[(x + y * 4) as usize];
+        assert_eq!(light_at(0, 1), Color::rgb(200, 100, 0));
+        assert_eq!(light_at(1, 0), Color::rgb(106, 53, 0));
+        assert_eq!(light_at(2, 0), Color::rgb(51, 25, 0));
+        // The blocking cell is lit, not the cells behind it
+        assert_eq!(light_at(1, 1), Color::rgb(133, 67, 0));
+        assert_eq!(light_at(2, 1), Color::BLACK);
+        assert_eq!(light_at(3, 1), Color::BLACK);
+    }
+
+    #[test]
+    fn test_cast_light() {
+        // A radius below 1.0 only lights the source cell
+        let candle = LightSource {
+            color: Color::WHITE,
+            radius: 0.0,
+            intensity: 0.5,
+        };
+        assert_eq!(
+            cast_light((3, 3).into(), (1, 1).into(), &candle, |_| false),
+            vec![((1, 1).into(), 0.5)]
+        );
+
+        // The reach is clamped to the grid
+        let torch = LightSource {
+            color: Color::WHITE,
+            radius: 2.5,
+            intensity: 1.0,
+        };
+        assert_eq!(torch.reach((4, 10).into(), (1, 5).into()), (0..4, 2..9));
+        assert_eq!(candle.reach((4, 10).into(), (1, 5).into()), (0..3, 4..7));
+    }
+
+    #[test]
+    fn test_in_line_of_sight() {
+        let wall = |position: Vector2u| position == Vector2u::new(2, 2);
+
+        assert!(in_line_of_sight((0, 0).into(), (1, 1).into(), &wall));
+        assert!(in_line_of_sight((0, 0).into(), (2, 2).into(), &wall));
+        assert!(!in_line_of_sight((0, 0).into(), (3, 3).into(), &wall));
+        assert!(in_line_of_sight((2, 2).into(), (3, 3).into(), &wall));
+        assert!(in_line_of_sight((0, 0).into(), (4, 0).into(), &wall));
+    }
+}
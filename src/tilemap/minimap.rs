@@ -0,0 +1,491 @@
+use sfml::graphics::{
+    Color, Drawable, FloatRect, Image, IntRect, RenderStates, RenderTarget, Texture,
+};
+use sfml::system::{SfBox, Vector2f, Vector2u};
+
+use crate::geometry::{append_rect, Quads, Vertex};
+use crate::tilemap::raster::blend;
+use crate::tilemap::renderer::displayed_tile;
+use crate::tilemap::{
+    LayerBlendMode, RenderBackend, SfmlBackend, TileMap, TileMapChange, TileMapRenderer, Tileset,
+    TILE_ID_MASK,
+};
+use std::collections::BTreeMap;
+
+/// The color displayed behind the minimap
+const BACKGROUND_COLOR: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 160,
+};
+/// The color of the camera rectangle
+const FRAME_COLOR: Color = Color::WHITE;
+/// The thickness of the camera rectangle (in pixels)
+const FRAME_THICKNESS: f32 = 2.0;
+
+/// Minimap is a downscaled overview of a tile map, displayed on screen
+/// each cell is drawn as a single pixel using the average color of its tiles,
+/// and the area visible through the renderer view is framed
+pub struct Minimap<'s> {
+    tileset: &'s Tileset,
+    /// a copy of the tileset atlas, used to compute the tiles colors
+    atlas: Image,
+    /// the average color of each tile, computed on first use
+    colors: BTreeMap<u32, Color>,
+    map_size: Vector2u,
+    /// the cells colors, one pixel per cell
+    image: Image,
+    texture: SfBox<Texture>,
+    /// the screen area the minimap is fitted in
+    area: FloatRect,
+    /// the screen area covered by the tile map (fitted in the area, keeping its aspect ratio)
+    bounds: FloatRect,
+    /// the tile map bounds in world units
+    world_bounds: FloatRect,
+    /// the area visible through the renderer view (in screen coordinates)
+    frame: Option<FloatRect>,
+}
+
+impl<'s> Minimap<'s> {
+    /// Create a new minimap of given tile map, displayed in given screen area
+    /// this will return None if the minimap texture cannot be created
+    pub fn new(tile_map: &TileMap, tileset: &'s Tileset, area: FloatRect) -> Option<Self> {
+        let mut minimap = Minimap {
+            tileset,
+            atlas: tileset.texture().copy_to_image()?,
+            colors: BTreeMap::new(),
+            map_size: Default::default(),
+            image: Image::new(1, 1),
+            texture: Texture::new(1, 1)?,
+            area,
+            bounds: area,
+            world_bounds: FloatRect::new(0.0, 0.0, 0.0, 0.0),
+            frame: None,
+        };
+        minimap.update(tile_map)?;
+
+        Some(minimap)
+    }
+
+    /// Re-create the minimap using given tile map (i.e after loading a new tile map)
+    /// this will return None if the minimap texture cannot be created
+    pub fn update(&mut self, tile_map: &TileMap) -> Option<()> {
+        let map_size = tile_map.size();
+        self.map_size = map_size;
+        self.image = Image::new(map_size.x.max(1), map_size.y.max(1));
+        self.texture = Texture::new(map_size.x.max(1), map_size.y.max(1))?;
+        self.bounds = self.fit_bounds();
+
+        for y in 0..map_size.y {
+            for x in 0..map_size.x {
+                self.update_cell(tile_map, Vector2u::new(x, y));
+            }
+        }
+        self.texture.update_from_image(&self.image, 0, 0);
+
+        Some(())
+    }
+
+    /// Apply the changes made on given tile map, only the changed cells are updated
+    pub fn apply_changes(&mut self, tile_map: &TileMap, changes: &[TileMapChange]) {
+        if changes.is_empty() {
+            return;
+        }
+
+        for change in changes {
+            match *change {
+                TileMapChange::Tile { position, .. } => self.update_cell(tile_map, position),
+                // Layers & objects may affect many cells, update them all
+                TileMapChange::LayerSettings { .. } | TileMapChange::Objects => {
+                    for y in 0..self.map_size.y {
+                        for x in 0..self.map_size.x {
+                            self.update_cell(tile_map, Vector2u::new(x, y));
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+        self.texture.update_from_image(&self.image, 0, 0);
+    }
+
+    /// Frame the area visible through given renderer view
+    pub fn update_view(&mut self, renderer: &TileMapRenderer) {
+        self.world_bounds = renderer.world_bounds();
+        let size = renderer.view_size();
+        let top_left = self.world_to_minimap(renderer.center() - size / 2.0);
+        let bottom_right = self.world_to_minimap(renderer.center() + size / 2.0);
+
+        self.frame = FloatRect::new(
+            top_left.x,
+            top_left.y,
+            bottom_right.x - top_left.x,
+            bottom_right.y - top_left.y,
+        )
+        .intersection(&self.bounds);
+    }
+
+    /// Translate given screen position to the world position it represents on the tile map
+    /// this will return None if the position is outside the minimap (i.e to jump the view there)
+    pub fn world_position<P: Into<Vector2f>>(&self, screen_pos: P) -> Option<Vector2f> {
+        let screen_pos = screen_pos.into();
+        if !self.bounds.contains(screen_pos) {
+            return None;
+        }
+
+        Some(Vector2f::new(
+            self.world_bounds.left
+                + (screen_pos.x - self.bounds.left) / self.bounds.width * self.world_bounds.width,
+            self.world_bounds.top
+                + (screen_pos.y - self.bounds.top) / self.bounds.height * self.world_bounds.height,
+        ))
+    }
+
+    /// Move the minimap to given screen area (i.e when the window is resized)
+    /// the view frame is updated on the next update_view
+    pub fn set_area(&mut self, area: FloatRect) {
+        self.area = area;
+        self.bounds = self.fit_bounds();
+    }
+
+    /// Determinate if given screen position is on the tile map displayed by the minimap
+    /// the letterboxed parts of the minimap area are not considered, like in world_position
+    pub fn contains<P: Into<Vector2f>>(&self, screen_pos: P) -> bool {
+        self.bounds.contains(screen_pos.into())
+    }
+
+    /// Retrieve the screen area covered by the tile map
+    pub fn bounds(&self) -> FloatRect {
+        self.bounds
+    }
+
+    /// Compute the color of the cell at given position by blending its layers
+    fn update_cell(&mut self, tile_map: &TileMap, position: Vector2u) {
+        let mut color = Color::TRANSPARENT;
+
+        for layer in 0..tile_map.layer_count() {
+            let settings = tile_map.layer_settings(layer).copied().unwrap_or_default();
+            if !settings.visible {
+                continue;
+            }
+
+            // The objects covering the cell are considered as its tile
+            let tile_id = match tile_map.get_tile(position, layer) {
+                Some(tile_id) if tile_id & TILE_ID_MASK != 0 => tile_id,
+                _ => match tile_map.object_at(position, layer) {
+                    Some(object) => object.tile_id,
+                    None => continue,
+                },
+            };
+            let tile_id = displayed_tile(self.tileset, 0.0, position, tile_id);
+            let source = self.tile_color(tile_id) * settings.color();
+            color = blend(source, color, settings.blend_mode);
+        }
+
+        self.image.set_pixel(position.x, position.y, color);
+    }
+
+    /// Retrieve the average color of given tile, transparent if the tile is unknown
+    fn tile_color(&mut self, tile_id: u32) -> Color {
+        let tile_id = tile_id & TILE_ID_MASK;
+        if let Some(color) = self.colors.get(&tile_id) {
+            return *color;
+        }
+
+        let color = match self.tileset.tile_rect(tile_id) {
+            Some(rect) => average_color(&self.atlas, rect),
+            None => Color::TRANSPARENT,
+        };
+        self.colors.insert(tile_id, color);
+        color
+    }
+
+    /// Compute the screen area covered by the tile map, centered in the minimap area
+    fn fit_bounds(&self) -> FloatRect {
+        let scale = (self.area.width / self.map_size.x.max(1) as f32)
+            .min(self.area.height / self.map_size.y.max(1) as f32);
+        let width = self.map_size.x as f32 * scale;
+        let height = self.map_size.y as f32 * scale;
+
+        FloatRect::new(
+            self.area.left + (self.area.width - width) / 2.0,
+            self.area.top + (self.area.height - height) / 2.0,
+            width,
+            height,
+        )
+    }
+
+    /// Translate given world position to its screen position on the minimap
+    fn world_to_minimap(&self, world_pos: Vector2f) -> Vector2f {
+        if self.world_bounds.width <= 0.0 || self.world_bounds.height <= 0.0 {
+            return Vector2f::new(self.bounds.left, self.bounds.top);
+        }
+
+        Vector2f::new(
+            self.bounds.left
+                + (world_pos.x - self.world_bounds.left) / self.world_bounds.width
+                    * self.bounds.width,
+            self.bounds.top
+                + (world_pos.y - self.world_bounds.top) / self.world_bounds.height
+                    * self.bounds.height,
+        )
+    }
+}
+
+/// Compute the average color of the pixels of given image rect
+/// the colors are weighted by their alpha, so that transparent pixels don't darken the tile
+/// the rect is clipped to the image, and the color is transparent if nothing is left of it
+fn average_color(image: &Image, rect: IntRect) -> Color {
+    let size = image.size();
+    let image_rect = IntRect::new(0, 0, size.x as i32, size.y as i32);
+    let rect = match rect.intersection(&image_rect) {
+        Some(rect) => rect,
+        None => return Color::TRANSPARENT,
+    };
+
+    let (mut r, mut g, mut b, mut a) = (0.0, 0.0, 0.0, 0.0);
+    for y in rect.top..rect.top + rect.height {
+        for x in rect.left..rect.left + rect.width {
+            let pixel = image.pixel_at(x as u32, y as u32);
+            let alpha = pixel.a as f32;
+            r += pixel.r as f32 * alpha;
+            g += pixel.g as f32 * alpha;
+            b += pixel.b as f32 * alpha;
+            a += alpha;
+        }
+    }
+
+    let count = (rect.width * rect.height).max(1) as f32;
+    if a == 0.0 {
+        return Color::TRANSPARENT;
+    }
+    Color::rgba(
+        (r / a).round() as u8,
+        (g / a).round() as u8,
+        (b / a).round() as u8,
+        (a / count).round() as u8,
+    )
+}
+
+/// Append the quads of given rectangle outline to given batch
+fn append_outline(quads: &mut Quads, rect: FloatRect, thickness: f32, color: Color) {
+    let (right, bottom) = (rect.left + rect.width, rect.top + rect.height);
+    for side in [
+        FloatRect::new(rect.left, rect.top, rect.width, thickness),
+        FloatRect::new(rect.left, bottom - thickness, rect.width, thickness),
+        FloatRect::new(rect.left, rect.top, thickness, rect.height),
+        FloatRect::new(right - thickness, rect.top, thickness, rect.height),
+    ] {
+        append_rect(quads, side, color);
+    }
+}
+
+impl<'s> Drawable for Minimap<'s> {
+    fn draw<'a: 'shader, 'texture, 'shader, 'shader_texture>(
+        &'a self,
+        target: &mut dyn RenderTarget,
+        states: RenderStates<'texture, 'shader, 'shader_texture>,
+    ) {
+        let (area, bounds) = (self.area, self.bounds);
+        let mut backend = SfmlBackend::new(target, states, &self.texture);
+        let origin = Vector2f::default();
+
+        let mut background = Quads::new();
+        append_rect(&mut background, area, BACKGROUND_COLOR);
+        backend.draw_quads(&background, false, LayerBlendMode::Alpha, origin);
+
+        let (width, height) = (self.map_size.x as f32, self.map_size.y as f32);
+        let mut map = Quads::new();
+        for (position, tex_coords) in [
+            ((bounds.left, bounds.top), (0.0, 0.0)),
+            ((bounds.left + bounds.width, bounds.top), (width, 0.0)),
+            (
+                (bounds.left + bounds.width, bounds.top + bounds.height),
+                (width, height),
+            ),
+            ((bounds.left, bounds.top + bounds.height), (0.0, height)),
+        ] {
+            map.append(&Vertex::new(position, Color::WHITE, tex_coords.into()));
+        }
+        backend.draw_quads(&map, true, LayerBlendMode::Alpha, origin);
+
+        if let Some(frame) = self.frame {
+            let mut outline = Quads::new();
+            append_outline(&mut outline, frame, FRAME_THICKNESS, FRAME_COLOR);
+            backend.draw_quads(&outline, false, LayerBlendMode::Alpha, origin);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::graphics::{Color, FloatRect, Image, IntRect, View};
+
+    use crate::tilemap::minimap::average_color;
+    use crate::tilemap::test_utils::red_blue_tileset;
+    use crate::tilemap::{LayerSettings, MapObject, Minimap, TileMap, TileMapRenderer};
+
+    #[test]
+    fn test_minimap_new() {
+        let tileset = red_blue_tileset();
+        let mut tile_map = TileMap::new((4, 2), 2, 1);
+        tile_map.set_tile((1, 1), 1, 2).unwrap();
+        tile_map.set_tile((2, 0), 0, 0).unwrap();
+
+        let minimap = Minimap::new(
+            &tile_map,
+            &tileset,
+            FloatRect::new(10.0, 10.0, 100.0, 100.0),
+        )
+        .unwrap();
+
+        // The tile map is centered in the area, keeping its aspect ratio
+        assert_eq!(minimap.bounds(), FloatRect::new(10.0, 35.0, 100.0, 50.0));
+        assert_eq!(minimap.image.size(), (4, 2).into());
+        assert_eq!(minimap.image.pixel_at(0, 0), Color::RED);
+        assert_eq!(minimap.image.pixel_at(1, 1), Color::BLUE);
+        assert_eq!(minimap.image.pixel_at(2, 0), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_minimap_set_area() {
+        let tileset = red_blue_tileset();
+        let tile_map = TileMap::new((4, 2), 1, 1);
+        let mut minimap = Minimap::new(
+            &tile_map,
+            &tileset,
+            FloatRect::new(10.0, 10.0, 100.0, 100.0),
+        )
+        .unwrap();
+
+        minimap.set_area(FloatRect::new(50.0, 0.0, 40.0, 40.0));
+        assert_eq!(minimap.bounds(), FloatRect::new(50.0, 10.0, 40.0, 20.0));
+        assert!(minimap.contains((85.0, 15.0)));
+        assert!(!minimap.contains((20.0, 20.0)));
+        // The letterboxed part of the area is not covered by the tile map
+        assert!(!minimap.contains((85.0, 5.0)));
+        assert_eq!(minimap.world_position((85.0, 5.0)), None);
+    }
+
+    #[test]
+    fn test_minimap_apply_changes() {
+        let tileset = red_blue_tileset();
+        let mut tile_map = TileMap::new((4, 2), 2, 1);
+        let mut minimap =
+            Minimap::new(&tile_map, &tileset, FloatRect::new(0.0, 0.0, 40.0, 20.0)).unwrap();
+
+        tile_map.set_tile((3, 1), 0, 2).unwrap();
+        tile_map
+            .place_object(MapObject::new(2, (0, 0), 1, (2, 1)))
+            .unwrap();
+        let changes = tile_map.take_changes();
+        minimap.apply_changes(&tile_map, &changes);
+
+        assert_eq!(minimap.image.pixel_at(3, 1), Color::BLUE);
+        assert_eq!(minimap.image.pixel_at(0, 0), Color::BLUE);
+        assert_eq!(minimap.image.pixel_at(1, 0), Color::BLUE);
+        assert_eq!(minimap.image.pixel_at(2, 0), Color::RED);
+
+        // Hidden layers are not displayed
+        tile_map
+            .set_layer_settings(
+                1,
+                LayerSettings {
+                    visible: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let changes = tile_map.take_changes();
+        minimap.apply_changes(&tile_map, &changes);
+        assert_eq!(minimap.image.pixel_at(0, 0), Color::RED);
+    }
+
+    #[test]
+    fn test_minimap_update_view() {
+        let tileset = red_blue_tileset();
+        let tile_map = TileMap::new((10, 10), 1, 1);
+        let mut renderer = TileMapRenderer::new(
+            &tile_map,
+            (100, 100),
+            (5, 5),
+            View::new((50.0, 50.0).into(), (100.0, 100.0).into()),
+            &tileset,
+            false,
+        );
+        let mut minimap =
+            Minimap::new(&tile_map, &tileset, FloatRect::new(0.0, 0.0, 50.0, 50.0)).unwrap();
+
+        // The view shows the top left quarter of the tile map
+        minimap.update_view(&renderer);
+        assert_eq!(minimap.frame, Some(FloatRect::new(0.0, 0.0, 25.0, 25.0)));
+
+        // The frame is clipped to the tile map
+        renderer.set_center((0.0, 0.0));
+        minimap.update_view(&renderer);
+        assert_eq!(minimap.frame, Some(FloatRect::new(0.0, 0.0, 12.5, 12.5)));
+    }
+
+    #[test]
+    fn test_minimap_world_position() {
+        let tileset = red_blue_tileset();
+        let tile_map = TileMap::new((10, 10), 1, 1);
+        let renderer = TileMapRenderer::new(
+            &tile_map,
+            (100, 100),
+            (5, 5),
+            View::new((50.0, 50.0).into(), (100.0, 100.0).into()),
+            &tileset,
+            false,
+        );
+        let mut minimap =
+            Minimap::new(&tile_map, &tileset, FloatRect::new(10.0, 10.0, 50.0, 50.0)).unwrap();
+        minimap.update_view(&renderer);
+
+        assert_eq!(
+            minimap.world_position((35.0, 20.0)),
+            Some((100.0, 40.0).into())
+        );
+        assert!(minimap.contains((35.0, 20.0)));
+        assert_eq!(minimap.world_position((5.0, 20.0)), None);
+        assert!(!minimap.contains((5.0, 20.0)));
+    }
+
+    #[test]
+    fn test_average_color() {
+        let mut image = Image::from_color(2, 2, Color::RED).unwrap();
+        image.set_pixel(1, 0, Color::BLUE);
+        image.set_pixel(0, 1, Color::TRANSPARENT);
+        image.set_pixel(1, 1, Color::TRANSPARENT);
+
+        assert_eq!(
+            average_color(&image, IntRect::new(0, 0, 2, 2)),
+            Color::rgba(128, 0, 128, 128)
+        );
+        assert_eq!(
+            average_color(&image, IntRect::new(0, 1, 2, 1)),
+            Color::TRANSPARENT
+        );
+    }
+
+    #[test]
+    fn test_average_color_oversize_rect() {
+        let mut image = Image::from_color(2, 2, Color::RED).unwrap();
+        image.set_pixel(1, 1, Color::BLUE);
+
+        // Only the part of the rect inside the image is considered
+        assert_eq!(
+            average_color(&image, IntRect::new(1, 1, 10, 10)),
+            Color::BLUE
+        );
+        assert_eq!(
+            average_color(&image, IntRect::new(-4, -4, 5, 5)),
+            Color::RED
+        );
+        assert_eq!(
+            average_color(&image, IntRect::new(2, 0, 4, 4)),
+            Color::TRANSPARENT
+        );
+    }
+}
@@ -1,5 +1,28 @@
+pub mod animation;
+pub mod backend;
+pub mod camera;
+mod chunk;
+pub mod export;
+pub mod lighting;
+pub mod minimap;
+pub mod raster;
 pub mod renderer;
+#[allow(clippy::module_inception)]
 pub mod tilemap;
+pub mod tileset;
+pub mod visibility;
 
+pub use animation::*;
+pub use backend::*;
+pub use camera::*;
+pub use export::*;
+pub use lighting::*;
+pub use minimap::*;
+pub use raster::*;
 pub use renderer::*;
 pub use tilemap::*;
+pub use tileset::*;
+pub use visibility::*;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
@@ -0,0 +1,428 @@
+use sfml::graphics::{Color, Image};
+use sfml::system::Vector2u;
+
+use crate::tilemap::renderer::displayed_tile;
+use crate::tilemap::{
+    ExportError, ExportOptions, LayerBlendMode, LayerSettings, TileMap, Tileset, FLIP_HORIZONTAL,
+    FLIP_VERTICAL, TILE_ID_MASK,
+};
+
+/// PixelBuffer is an RGBA image stored in memory, in row major order
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelBuffer {
+    size: Vector2u,
+    pixels: Vec<u8>,
+}
+
+impl PixelBuffer {
+    /// Create a new buffer of given size, filled with given color
+    pub fn new<T: Into<Vector2u>>(size: T, color: Color) -> Self {
+        let size = size.into();
+        PixelBuffer {
+            size,
+            pixels: [color.r, color.g, color.b, color.a].repeat(size.x as usize * size.y as usize),
+        }
+    }
+
+    /// Retrieve the buffer size (in pixels)
+    pub fn size(&self) -> Vector2u {
+        self.size
+    }
+
+    /// Retrieve the color of the pixel at given position
+    /// this will return None if the position is outside the buffer
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        let index = self.index(x, y)?;
+        let p = &self.pixels[index..index + 4];
+        Some(Color::rgba(p[0], p[1], p[2], p[3]))
+    }
+
+    /// Set the color of the pixel at given position
+    /// nothing is done if the position is outside the buffer
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        if let Some(index) = self.index(x, y) {
+            self.pixels[index..index + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    /// Retrieve the raw RGBA pixels, in row major order
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Convert the buffer into an image (i.e to save it as PNG)
+    /// this will return None if the image cannot be created
+    pub fn to_image(&self) -> Option<Image> {
+        Image::create_from_pixels(self.size.x, self.size.y, &self.pixels)
+    }
+
+    /// Compute the index of the pixel at given position
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.size.x || y >= self.size.y {
+            return None;
+        }
+
+        Some((x as usize + y as usize * self.size.x as usize) * 4)
+    }
+}
+
+/// Render given tile map into a pixel buffer, without using the GPU
+/// the tiles are drawn layer by layer in row major order, using nearest neighbour sampling,
+/// followed by the layer objects sorted from back to front
+///
+/// the layers display settings (visibility, tint, opacity & blend mode) are applied,
+/// animated tiles display their first frame and the tiles missing from the tileset are skipped
+pub fn rasterize(
+    tile_map: &TileMap,
+    tileset: &Tileset<Image>,
+    options: &ExportOptions,
+) -> Result<PixelBuffer, ExportError> {
+    let region = options.resolve_region(tile_map)?;
+    let cell_size = tileset.tile_size();
+    let scale = options.scale;
+
+    let mut buffer = PixelBuffer::new(options.image_size(region, cell_size)?, options.background);
+
+    let atlas = tileset.atlas();
+    let atlas_size = atlas.size();
+    let atlas_pixels = atlas.pixel_data();
+    let map_size = tile_map.size();
+
+    // Draw given tile, anchored on the cell at given position
+    let draw_tile = |buffer: &mut PixelBuffer,
+                     position: Vector2u,
+                     tile_id: u32,
+                     settings: &LayerSettings| {
+        let tile_id = displayed_tile(tileset, 0.0, position, tile_id);
+        let tile = match tileset.tile(tile_id) {
+            Some(tile) if tile_id & TILE_ID_MASK != 0 => tile,
+            _ => return,
+        };
+        let (flip_x, flip_y) = (tile_id & FLIP_HORIZONTAL != 0, tile_id & FLIP_VERTICAL != 0);
+        let layer_color = settings.color();
+
+        // The tile rect in buffer pixels
+        let left =
+            ((position.x as i32 - region.left) * cell_size.x as i32 - tile.anchor.x) as f32 * scale;
+        let top =
+            ((position.y as i32 - region.top) * cell_size.y as i32 - tile.anchor.y) as f32 * scale;
+        let right = left + tile.rect.width as f32 * scale;
+        let bottom = top + tile.rect.height as f32 * scale;
+
+        let start_x = left.round().max(0.0) as u32;
+        let end_x = (right.round().max(0.0) as u32).min(buffer.size.x);
+        let start_y = top.round().max(0.0) as u32;
+        let end_y = (bottom.round().max(0.0) as u32).min(buffer.size.y);
+
+        for pixel_y in start_y..end_y {
+            // Sample the texel at the center of the pixel
+            let texel_y =
+                (((pixel_y as f32 + 0.5 - top) / scale) as i32).clamp(0, tile.rect.height - 1);
+            let texel_y = if flip_y {
+                tile.rect.height - 1 - texel_y
+            } else {
+                texel_y
+            };
+
+            for pixel_x in start_x..end_x {
+                let texel_x =
+                    (((pixel_x as f32 + 0.5 - left) / scale) as i32).clamp(0, tile.rect.width - 1);
+                let texel_x = if flip_x {
+                    tile.rect.width - 1 - texel_x
+                } else {
+                    texel_x
+                };
+
+                // The tile rect may extend past the atlas, those texels are skipped
+                let (atlas_x, atlas_y) = (tile.rect.left + texel_x, tile.rect.top + texel_y);
+                if atlas_x < 0
+                    || atlas_y < 0
+                    || atlas_x as u32 >= atlas_size.x
+                    || atlas_y as u32 >= atlas_size.y
+                {
+                    continue;
+                }
+                let index = ((atlas_y as u32 * atlas_size.x + atlas_x as u32) * 4) as usize;
+                let texel = &atlas_pixels[index..index + 4];
+                let source = Color::rgba(texel[0], texel[1], texel[2], texel[3]) * layer_color;
+
+                let destination = buffer.pixel(pixel_x, pixel_y).unwrap();
+                buffer.set_pixel(
+                    pixel_x,
+                    pixel_y,
+                    blend(source, destination, settings.blend_mode),
+                );
+            }
+        }
+    };
+
+    for layer in 0..tile_map.layer_count() {
+        let settings = tile_map.layer_settings(layer).copied().unwrap_or_default();
+        if !settings.visible || options.layer.is_some_and(|exported| exported != layer) {
+            continue;
+        }
+
+        for y in 0..map_size.y {
+            for x in 0..map_size.x {
+                let tile_id = tile_map.get_tile((x, y), layer).unwrap();
+                draw_tile(&mut buffer, Vector2u::new(x, y), tile_id, &settings);
+            }
+        }
+
+        // Then the layer objects, in the same order as the renderer
+        for object in tile_map.sorted_objects(layer) {
+            draw_tile(&mut buffer, object.position, object.tile_id, &settings);
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Blend given source color on given destination color
+/// the same equations as the renderer blend modes are used
+pub(crate) fn blend(source: Color, destination: Color, mode: LayerBlendMode) -> Color {
+    let s = [source.r, source.g, source.b, source.a].map(|v| v as f32 / 255.0);
+    let d = [destination.r, destination.g, destination.b, destination.a].map(|v| v as f32 / 255.0);
+    let alpha = s[3];
+
+    let result = match mode {
+        LayerBlendMode::Alpha => [
+            s[0] * alpha + d[0] * (1.0 - alpha),
+            s[1] * alpha + d[1] * (1.0 - alpha),
+            s[2] * alpha + d[2] * (1.0 - alpha),
+            alpha + d[3] * (1.0 - alpha),
+        ],
+        LayerBlendMode::Add => [
+            s[0] * alpha + d[0],
+            s[1] * alpha + d[1],
+            s[2] * alpha + d[2],
+            alpha + d[3],
+        ],
+        LayerBlendMode::Multiply => [s[0] * d[0], s[1] * d[1], s[2] * d[2], s[3] * d[3]],
+        LayerBlendMode::None => s,
+    };
+
+    let [r, g, b, a] = result.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Color::rgba(r, g, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::graphics::{Color, IntRect};
+
+    use crate::tilemap::raster::blend;
+    use crate::tilemap::test_utils::pixel_tileset;
+    use crate::tilemap::{
+        rasterize, ExportError, ExportOptions, LayerBlendMode, LayerSettings, MapObject,
+        PixelBuffer, TileMap, Tileset, FLIP_HORIZONTAL, FLIP_VERTICAL,
+    };
+
+    #[test]
+    fn test_pixel_buffer() {
+        let mut buffer = PixelBuffer::new((2, 3), Color::RED);
+        assert_eq!(buffer.size(), (2, 3).into());
+        assert_eq!(buffer.pixels().len(), 2 * 3 * 4);
+        assert_eq!(buffer.pixel(1, 2), Some(Color::RED));
+        assert_eq!(buffer.pixel(2, 0), None);
+
+        buffer.set_pixel(1, 2, Color::BLUE);
+        buffer.set_pixel(5, 5, Color::BLUE);
+        assert_eq!(buffer.pixel(1, 2), Some(Color::BLUE));
+        assert_eq!(&buffer.pixels()[20..24], &[0, 0, 255, 255]);
+
+        let image = buffer.to_image().unwrap();
+        assert_eq!(image.pixel_at(1, 2), Color::BLUE);
+    }
+
+    #[test]
+    fn test_rasterize() {
+        let tileset = pixel_tileset();
+        let mut tile_map = TileMap::new((2, 2), 2, 1);
+        tile_map.set_tile((1, 0), 0, 0).unwrap();
+        tile_map.set_tile((0, 1), 1, 3).unwrap();
+
+        let buffer = rasterize(&tile_map, &tileset, &ExportOptions::default()).unwrap();
+
+        // Golden image: tile 1 everywhere except the air tile,
+        // with tile 3 (half transparent green) on top of the bottom left one
+        let mut expected = PixelBuffer::new((4, 4), Color::TRANSPARENT);
+        for (x, y) in [(0, 0), (0, 2), (2, 2)] {
+            fill_tile_1(&mut expected, x, y);
+        }
+        for y in 2..4 {
+            for x in 0..2 {
+                let color = blend(
+                    Color::rgba(0, 255, 0, 128),
+                    expected.pixel(x, y).unwrap(),
+                    LayerBlendMode::Alpha,
+                );
+                expected.set_pixel(x, y, color);
+            }
+        }
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_rasterize_outside_atlas() {
+        // The tile 5 rect goes past the right border of the 4x2 atlas
+        let mut tileset = pixel_tileset();
+        tileset.add_tile(5, IntRect::new(2, 0, 4, 2));
+        let mut tile_map = TileMap::new((2, 1), 1, 0);
+        tile_map.set_tile((0, 0), 0, 5).unwrap();
+
+        let buffer = rasterize(&tile_map, &tileset, &ExportOptions::default()).unwrap();
+
+        let green = blend(
+            Color::rgba(0, 255, 0, 128),
+            Color::TRANSPARENT,
+            LayerBlendMode::Alpha,
+        );
+        assert_eq!(buffer.pixel(1, 0), Some(green));
+        assert_eq!(buffer.pixel(2, 0), Some(Color::TRANSPARENT));
+        assert_eq!(buffer.pixel(3, 1), Some(Color::TRANSPARENT));
+    }
+
+    #[test]
+    fn test_rasterize_flip() {
+        let tileset = pixel_tileset();
+        let mut tile_map = TileMap::new((3, 1), 1, 1);
+        tile_map.set_tile((1, 0), 0, 1 | FLIP_HORIZONTAL).unwrap();
+        tile_map.set_tile((2, 0), 0, 1 | FLIP_VERTICAL).unwrap();
+
+        let buffer = rasterize(&tile_map, &tileset, &ExportOptions::default()).unwrap();
+
+        assert_eq!(buffer.pixel(0, 0), Some(Color::RED));
+        assert_eq!(buffer.pixel(1, 0), Some(Color::BLUE));
+        assert_eq!(buffer.pixel(2, 0), Some(Color::BLUE));
+        assert_eq!(buffer.pixel(3, 0), Some(Color::RED));
+        assert_eq!(buffer.pixel(4, 0), Some(Color::RED));
+        assert_eq!(buffer.pixel(5, 0), Some(Color::WHITE));
+        assert_eq!(buffer.pixel(5, 1), Some(Color::BLUE));
+    }
+
+    #[test]
+    fn test_rasterize_objects() {
+        let mut tileset = Tileset::new(pixel_tileset().atlas().clone(), (1, 1));
+        tileset.add_object(10, IntRect::new(0, 0, 2, 2), (2, 1));
+        tileset.add_object(11, IntRect::new(2, 0, 1, 2), (1, 1));
+
+        // Placed out of order, the objects are drawn from back to front
+        let mut tile_map = TileMap::new((3, 3), 1, 0);
+        tile_map
+            .place_object(MapObject::new(11, (1, 2), 0, (1, 1)))
+            .unwrap();
+        tile_map
+            .place_object(MapObject::new(10, (0, 1), 0, (2, 1)))
+            .unwrap();
+        tile_map
+            .place_object(MapObject::new(11, (1, 0), 0, (1, 1)))
+            .unwrap();
+
+        let buffer = rasterize(&tile_map, &tileset, &ExportOptions::default()).unwrap();
+
+        // The object behind is hidden, the one in front overlaps the house bottom
+        assert_eq!(buffer.pixel(0, 0), Some(Color::RED));
+        assert_eq!(buffer.pixel(1, 0), Some(Color::BLUE));
+        assert_eq!(buffer.pixel(0, 1), Some(Color::RED));
+        assert_eq!(
+            buffer.pixel(1, 1),
+            Some(blend(
+                Color::rgba(0, 255, 0, 128),
+                Color::WHITE,
+                LayerBlendMode::Alpha
+            ))
+        );
+        assert_eq!(
+            buffer.pixel(1, 2),
+            Some(blend(
+                Color::rgba(0, 255, 0, 128),
+                Color::TRANSPARENT,
+                LayerBlendMode::Alpha
+            ))
+        );
+        assert_eq!(buffer.pixel(2, 2), Some(Color::TRANSPARENT));
+    }
+
+    #[test]
+    fn test_rasterize_options() {
+        let tileset = pixel_tileset();
+        let mut tile_map = TileMap::new((3, 2), 2, 1);
+        tile_map.set_tile((2, 1), 1, 3).unwrap();
+        tile_map
+            .set_layer_settings(
+                1,
+                LayerSettings {
+                    tint: Color::rgb(0, 0, 255),
+                    blend_mode: LayerBlendMode::None,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // Region of a single layer, scaled up
+        let options = ExportOptions {
+            region: Some(IntRect::new(2, 1, 1, 1)),
+            layer: Some(1),
+            scale: 2.0,
+            background: Color::BLACK,
+        };
+        let buffer = rasterize(&tile_map, &tileset, &options).unwrap();
+        assert_eq!(buffer.size(), (4, 4).into());
+        assert_eq!(buffer, PixelBuffer::new((4, 4), Color::rgba(0, 0, 0, 128)));
+
+        // Hidden layers are not displayed
+        tile_map
+            .set_layer_settings(
+                0,
+                LayerSettings {
+                    visible: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let options = ExportOptions {
+            region: Some(IntRect::new(0, 0, 1, 1)),
+            ..Default::default()
+        };
+        let buffer = rasterize(&tile_map, &tileset, &options).unwrap();
+        assert_eq!(buffer, PixelBuffer::new((2, 2), Color::TRANSPARENT));
+
+        let options = ExportOptions {
+            region: Some(IntRect::new(0, 0, 4, 1)),
+            ..Default::default()
+        };
+        assert_eq!(
+            rasterize(&tile_map, &tileset, &options).err(),
+            Some(ExportError::InvalidRegion)
+        );
+    }
+
+    #[test]
+    fn test_blend() {
+        let source = Color::rgba(255, 0, 0, 128);
+        let destination = Color::rgb(0, 0, 255);
+
+        assert_eq!(
+            blend(source, destination, LayerBlendMode::Alpha),
+            Color::rgba(128, 0, 127, 255)
+        );
+        assert_eq!(
+            blend(source, destination, LayerBlendMode::Add),
+            Color::rgba(128, 0, 255, 255)
+        );
+        assert_eq!(
+            blend(source, destination, LayerBlendMode::Multiply),
+            Color::rgba(0, 0, 0, 128)
+        );
+        assert_eq!(blend(source, destination, LayerBlendMode::None), source);
+    }
+
+    /// Draw the pixels of the tile 1 of the pixel tileset at given position
+    fn fill_tile_1(buffer: &mut PixelBuffer, x: u32, y: u32) {
+        buffer.set_pixel(x, y, Color::RED);
+        buffer.set_pixel(x, y + 1, Color::RED);
+        buffer.set_pixel(x + 1, y, Color::BLUE);
+        buffer.set_pixel(x + 1, y + 1, Color::WHITE);
+    }
+}
@@ -1,28 +1,282 @@
-use sfml::graphics::{
-    Color, Drawable, PrimitiveType, RenderStates, RenderTarget, Sprite, Texture, Transformable,
-    VertexArray, View,
-};
+use sfml::graphics::{Color, Drawable, FloatRect, RenderStates, RenderTarget, Texture, View};
 use sfml::system::{SfBox, Vector2f, Vector2u};
 
-use crate::tilemap::TileMap;
-use std::collections::BTreeMap;
-use std::ops::{IndexMut, Sub};
+use crate::font::{append_text, GLYPH_HEIGHT};
+use crate::geometry::{append_rect, Quads, Vertex};
+use crate::tilemap::chunk::{set_quad, Chunk, TileDisplay, CHUNK_SIZE};
+use crate::tilemap::{
+    cast_light, GeometryCache, LayerBlendMode, LayerSettings, LightSource, Lighting, MapObject,
+    RenderBackend, SfmlBackend, TileMap, TileMapChange, Tileset, Visibility, VisibilityGrid,
+    FLIP_HORIZONTAL, FLIP_VERTICAL, TILE_ID_MASK,
+};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Deref, Range};
+
+/// The distance (in tiles) under which a position is snapped onto the closest cell border
+/// it absorbs the rounding errors of the view rotation
+const CELL_EPSILON: f32 = 1e-3;
+
+#[derive(Debug, PartialEq)]
+pub enum RendererError {
+    /// The tile map contains tiles not present in the tileset
+    UnknownTiles(BTreeSet<u32>),
+    InvalidLayer,
+}
+
+/// MissingTilePolicy define how the renderer handle tiles that are not present in the tileset
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MissingTilePolicy {
+    /// Display the missing tiles using a magenta checker
+    Placeholder,
+    /// Do not display the missing tiles
+    Skip,
+    /// Refuse to display a tile map with missing tiles
+    Error,
+}
+
+/// GridSettings holds the display settings of the grid overlay
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GridSettings {
+    /// the color of the lines
+    pub color: Color,
+    /// the thickness of the lines (in world units, i.e pixels at zoom level 1.0)
+    pub thickness: f32,
+    /// display a major line every major_interval lines, 0 means no major lines
+    pub major_interval: u32,
+    /// the color of the major lines
+    pub major_color: Color,
+    /// the thickness of the major lines (in world units)
+    pub major_thickness: f32,
+    /// display the coordinates of the major lines (or every line if there is no major lines)
+    pub show_labels: bool,
+    /// the color of the coordinate labels
+    pub label_color: Color,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        GridSettings {
+            color: Color::BLACK,
+            thickness: 1.0,
+            major_interval: 0,
+            major_color: Color::BLACK,
+            major_thickness: 2.0,
+            show_labels: false,
+            label_color: Color::WHITE,
+        }
+    }
+}
+
+/// OverlaySettings holds the display settings of the editing overlays
+/// (hovered cell, selection & placement preview)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OverlaySettings {
+    /// the color the hovered cell is filled with
+    pub hover_color: Color,
+    /// the color the selected cells are filled with
+    pub selection_color: Color,
+    /// the color of the selection outline
+    pub selection_outline_color: Color,
+    /// the thickness of the selection outline (in world units)
+    pub outline_thickness: f32,
+    /// the opacity of the placement preview, from 0.0 (transparent) to 1.0 (opaque)
+    pub ghost_opacity: f32,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        OverlaySettings {
+            hover_color: Color::rgba(255, 255, 255, 64),
+            selection_color: Color::rgba(64, 128, 255, 64),
+            selection_outline_color: Color::rgb(64, 128, 255),
+            outline_thickness: 2.0,
+            ghost_opacity: 0.5,
+        }
+    }
+}
+
+/// FogSettings holds the display settings of the fog of war
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FogSettings {
+    /// the color of the fog, the unseen cells are hidden using this color
+    pub color: Color,
+    /// the opacity of the fog on the explored cells, from 0.0 (transparent) to 1.0 (opaque)
+    pub explored_opacity: f32,
+    /// should the fog fade between neighbours cells or not?
+    pub soft_edges: bool,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        FogSettings {
+            color: Color::BLACK,
+            explored_opacity: 0.5,
+            soft_edges: true,
+        }
+    }
+}
+
+/// DebugSettings holds the display settings of the debug overlays
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugSettings {
+    /// print the tile ids of every layer on each cell
+    pub show_tile_ids: bool,
+    /// color each cell by its top most occupied layer
+    pub show_layers: bool,
+    /// color the cells with a tile having this property (i.e walkable)
+    pub property: Option<String>,
+    /// display the chunks boundaries
+    pub show_chunks: bool,
+    /// the color of the tile ids
+    pub text_color: Color,
+    /// the color of each layer, repeated if there is more layers than colors
+    pub layer_colors: Vec<Color>,
+    /// the color of the cells having the property
+    pub property_color: Color,
+    /// the color of the chunks boundaries
+    pub chunk_color: Color,
+    /// the thickness of the chunks boundaries (in world units)
+    pub chunk_thickness: f32,
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        DebugSettings {
+            show_tile_ids: false,
+            show_layers: false,
+            property: None,
+            show_chunks: false,
+            text_color: Color::WHITE,
+            layer_colors: vec![
+                Color::rgba(0, 128, 255, 96),
+                Color::rgba(255, 128, 0, 96),
+                Color::rgba(0, 255, 128, 96),
+                Color::rgba(255, 0, 128, 96),
+            ],
+            property_color: Color::rgba(0, 255, 0, 96),
+            chunk_color: Color::RED,
+            chunk_thickness: 3.0,
+        }
+    }
+}
+
+impl DebugSettings {
+    /// Determinate if at least one debug overlay is displayed
+    pub fn is_enabled(&self) -> bool {
+        self.show_tile_ids || self.show_layers || self.property.is_some() || self.show_chunks
+    }
+}
+
+/// GeometryKey identifies a batch of quads drawn by the renderer, to cache its backend geometry
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum GeometryKey {
+    /// the tiles of a layer chunk
+    Tiles { layer: usize, chunk: usize },
+    /// the placeholders of a layer chunk
+    Placeholders { layer: usize, chunk: usize },
+    /// the objects of a layer
+    Objects { layer: usize },
+    /// a per chunk overlay (light, fog, grid & debug, in drawing order)
+    Overlay { overlay: usize, chunk: usize },
+    /// the placement preview
+    Ghost,
+    /// the hover highlight & selection
+    Editing,
+}
 
-// TODO error management
+/// The backend geometry of the batches drawn by the renderer
+type RendererCache<G> = GeometryCache<GeometryKey, G>;
+
+/// PlacedLight is a light source placed on the tile map, along the light strength of the cells it lights
+struct PlacedLight {
+    position: Vector2u,
+    source: LightSource,
+    /// the index (in row major order) & light strength of the lit cells
+    lit_cells: Vec<(usize, f32)>,
+}
 
 /// Tile map renderer is used to render a tile map on the screen
-pub struct TileMapRenderer<'s> {
-    layers: Vec<Vec<Sprite<'s>>>,
-    grid: Vec<VertexArray>,
+/// each layer is split into chunks of textured quads, only the chunks
+/// visible through the renderer view are built and drawn
+pub struct TileMapRenderer<'s, A = SfBox<Texture>> {
+    /// the layers chunks, in row major order
+    layers: Vec<Vec<Chunk>>,
+    /// the layers tiles, in row major order
+    tiles: Vec<Vec<u32>>,
+    layer_settings: Vec<LayerSettings>,
+    chunk_count: Vector2u,
+    /// the layers objects, in drawing order (from back to front)
+    objects: Vec<Vec<MapObject>>,
+    /// the layers objects, as one batch of textured quads per layer
+    object_quads: Vec<Quads>,
+    /// the grid lines & labels of each chunk (in row major order), built once visible
+    grid: Vec<Option<Quads>>,
+    grid_settings: GridSettings,
     show_grid: bool,
+    overlay_settings: OverlaySettings,
+    /// the layer the tiles are picked on, the editing overlays follow it
+    edited_layer: u32,
+    /// the hovered cell, if any
+    hover: Option<Vector2u>,
+    /// the selected cells
+    selection: BTreeSet<(u32, u32)>,
+    /// the placement preview position, and its tiles (relative to the position)
+    ghost_position: Option<Vector2u>,
+    ghost_tiles: Vec<(Vector2u, u32)>,
+    /// the hover highlight & selection, as a single batch of untextured quads
+    overlay: Quads,
+    /// the placement preview, as a single batch of textured quads
+    ghost: Quads,
+    debug_settings: DebugSettings,
+    fog_settings: FogSettings,
+    /// the cells visibility, None disable the fog of war
+    visibility: Option<VisibilityGrid>,
+    /// the fog of war of each chunk (in row major order), built once visible
+    fog: Vec<Option<Quads>>,
+    /// the lighting settings, None disable the lighting
+    lighting: Option<Lighting>,
+    /// the light sources placed on the map, along the cells they light
+    light_sources: Vec<PlacedLight>,
+    /// the light received by each cell from the light sources, in row major order
+    light_map: Vec<[f32; 3]>,
+    /// whether each cell blocks the light, in row major order
+    light_blocking: Vec<bool>,
+    /// the ambient light the light chunks are built with
+    ambient: Color,
+    /// the light (ambient & light sources) of each chunk (in row major order), built once visible
+    light: Vec<Option<Quads>>,
+    /// the debug overlays of each chunk (in row major order), built once visible
+    debug: Vec<Option<Quads>>,
+    /// the geometry built by the last backend the renderer was drawn with (a RendererCache),
+    /// the batches are only converted again once they are modified
+    geometry: RefCell<Option<Box<dyn Any>>>,
     view: SfBox<View>,
     original_view_center: Vector2f,
-    tile_size: f32,
+    original_view_size: Vector2f,
+    /// the world units per screen pixel at zoom level 1 (changed when the screen is resized)
+    screen_scale: f32,
+    /// the view center (in world units) the parallax layers are aligned on
+    parallax_origin: Vector2f,
+    /// the size of a tile cell in world units
+    tile_size: Vector2f,
+    /// the ratio between world units and tileset pixels
+    tile_scale: f32,
     map_size: Vector2u,
-    textures: &'s BTreeMap<u32, SfBox<Texture>>,
+    tileset: &'s Tileset<A>,
+    missing_tile_policy: MissingTilePolicy,
+    /// the tiles found in the tile map but not in the tileset,
+    /// with their number of occurrences (cells & objects)
+    unknown_tiles: BTreeMap<u32, usize>,
+    /// the animation clock (in seconds)
+    time: f32,
+    /// the cells displaying an animated tile, indexed by (layer, tile index)
+    /// the value is the tile (frame) currently displayed
+    animated_cells: BTreeMap<(u32, usize), u32>,
 }
 
-impl<'s> TileMapRenderer<'s> {
+impl<'s, A> TileMapRenderer<'s, A> {
     /// Create a new renderer using given tile map & display parameters
     ///
     /// # Arguments
@@ -30,58 +284,243 @@ impl<'s> TileMapRenderer<'s> {
     /// - screen_size: the screen size in pixel
     /// - viewport_size: the expected viewport size (will affect number of tiles displayed on screen)
     /// - default_view: the default view to apply
+    /// - tileset: the tileset used to render the tiles
     /// - show_grid: should the grid be visible or not?
+    ///
+    /// the tiles that are not present in the tileset are displayed as placeholders
     pub fn new<T: Into<Vector2u>>(
         tile_map: &TileMap,
         screen_size: T,
         viewport_size: T,
         default_view: SfBox<View>,
-        textures: &'s BTreeMap<u32, SfBox<Texture>>,
+        tileset: &'s Tileset<A>,
+        show_grid: bool,
+    ) -> Self {
+        let mut renderer = Self::empty(
+            default_view,
+            tileset,
+            show_grid,
+            MissingTilePolicy::Placeholder,
+        );
+        renderer.rebuild(tile_map, screen_size.into(), viewport_size.into());
+
+        renderer
+    }
+
+    /// Create a new renderer using given missing tile policy, see new
+    ///
+    /// this will fails if the tile map contains unknown tiles and the policy is Error,
+    /// otherwise they can be retrieved using unknown_tiles
+    pub fn with_policy<T: Into<Vector2u>>(
+        tile_map: &TileMap,
+        screen_size: T,
+        viewport_size: T,
+        default_view: SfBox<View>,
+        tileset: &'s Tileset<A>,
+        show_grid: bool,
+        policy: MissingTilePolicy,
+    ) -> Result<Self, RendererError> {
+        let mut renderer = Self::empty(default_view, tileset, show_grid, policy);
+        renderer.update(tile_map, screen_size, viewport_size)?;
+
+        Ok(renderer)
+    }
+
+    /// Create a renderer displaying nothing, it needs to be built using a tile map
+    fn empty(
+        default_view: SfBox<View>,
+        tileset: &'s Tileset<A>,
         show_grid: bool,
+        missing_tile_policy: MissingTilePolicy,
     ) -> Self {
-        let mut renderer = TileMapRenderer {
+        TileMapRenderer {
             layers: vec![],
+            tiles: vec![],
+            layer_settings: vec![],
+            chunk_count: Default::default(),
+            objects: vec![],
+            object_quads: vec![],
             grid: vec![],
+            grid_settings: GridSettings::default(),
             show_grid,
+            overlay_settings: OverlaySettings::default(),
+            edited_layer: 0,
+            hover: None,
+            selection: BTreeSet::new(),
+            ghost_position: None,
+            ghost_tiles: vec![],
+            overlay: Quads::new(),
+            ghost: Quads::new(),
+            debug_settings: DebugSettings::default(),
+            fog_settings: FogSettings::default(),
+            visibility: None,
+            fog: vec![],
+            lighting: None,
+            light_sources: vec![],
+            light_map: vec![],
+            light_blocking: vec![],
+            ambient: Color::WHITE,
+            light: vec![],
+            debug: vec![],
+            geometry: RefCell::new(None),
             original_view_center: default_view.center(),
+            original_view_size: default_view.size(),
+            screen_scale: 1.0,
+            parallax_origin: default_view.center(),
             view: default_view,
-            tile_size: 0.0,
+            tile_size: Default::default(),
+            tile_scale: 0.0,
             map_size: Default::default(),
-            textures,
-        };
-        renderer.update(tile_map, screen_size, viewport_size);
-
-        renderer
+            tileset,
+            missing_tile_policy,
+            unknown_tiles: BTreeMap::new(),
+            time: 0.0,
+            animated_cells: BTreeMap::new(),
+        }
     }
 
     /// Move the renderer by given offset
     /// this will update the renderer inner view and 'move' the tile map
     pub fn move_<O: Into<Vector2f>>(&mut self, offset: O) {
-        self.view.move_(offset)
+        self.view.move_(offset);
+        self.build_visible_chunks();
     }
 
-    /// Translate world position to tile position
-    pub fn get_tile_position<O: Into<Vector2f>>(&self, world_pos: O) -> Option<Vector2u> {
-        // Compute the offset between initial map view center and current one
-        // this allows us to compute the 'real' tile position if the map has been moved
-        let view_offset = Vector2f::new(
-            self.original_view_center.x - self.view.center().x,
-            self.original_view_center.y - self.view.center().y,
-        );
+    /// Center the renderer view on given world position
+    pub fn set_center<P: Into<Vector2f>>(&mut self, center: P) {
+        self.view.set_center(center);
+        self.build_visible_chunks();
+    }
+
+    /// Returns the world position the renderer view is centered on
+    pub fn center(&self) -> Vector2f {
+        self.view.center()
+    }
 
-        // Apply the offset to retrieve the world pos relative to the tile map
-        let world_pos = world_pos.into();
-        let world_pos = world_pos.sub(view_offset);
+    /// Returns the size of the renderer view in world units (depends on the zoom level)
+    pub fn view_size(&self) -> Vector2f {
+        self.view.size()
+    }
 
-        let position = Vector2u::new(
-            world_pos.x as u32 / self.tile_size as u32,
-            world_pos.y as u32 / self.tile_size as u32,
-        );
-        if position.x >= self.map_size.x || position.y >= self.map_size.y {
+    /// Zoom the renderer by given factor
+    /// a factor greater than 1.0 will zoom in, lower than 1.0 will zoom out
+    pub fn zoom(&mut self, factor: f32) {
+        self.set_zoom(self.zoom_level() * factor);
+    }
+
+    /// Zoom the renderer by given factor while keeping given screen position
+    /// on the same world position (i.e zoom to cursor)
+    pub fn zoom_at<P: Into<Vector2f>>(&mut self, factor: f32, screen_pos: P) {
+        let screen_pos = screen_pos.into();
+        let before = self.screen_to_world(screen_pos);
+        self.set_zoom(self.zoom_level() * factor);
+        let after = self.screen_to_world(screen_pos);
+
+        self.move_(before - after);
+    }
+
+    /// Set the renderer zoom level, 1.0 means no zoom
+    /// the zoom level is applied as is, the zoom limits are enforced by the camera
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.view
+            .set_size(self.original_view_size * self.screen_scale / zoom);
+        self.build_visible_chunks();
+    }
+
+    /// Returns the renderer zoom level, deduced from the view size
+    pub fn zoom_level(&self) -> f32 {
+        self.original_view_size.x * self.screen_scale / self.view.size().x
+    }
+
+    /// Set the renderer rotation (in degrees)
+    pub fn set_rotation(&mut self, angle: f32) {
+        self.view.set_rotation(angle);
+        self.build_visible_chunks();
+    }
+
+    /// Rotate the renderer by given angle (in degrees)
+    pub fn rotate(&mut self, angle: f32) {
+        self.view.rotate(angle);
+        self.build_visible_chunks();
+    }
+
+    /// Returns the renderer rotation (in degrees)
+    pub fn rotation(&self) -> f32 {
+        self.view.rotation()
+    }
+
+    /// Translate screen position (expressed in the default view) to tile position
+    /// this will return None if the position is outside of the tile map
+    ///
+    /// the position is picked on the edited layer, following its parallax & scrolling
+    /// (only the original copy of a repeated layer can be picked)
+    pub fn get_tile_position<O: Into<Vector2f>>(&self, screen_pos: O) -> Option<Vector2u> {
+        // Apply the renderer view (move, zoom & rotation) to retrieve the 'real' position
+        let world_pos = self.screen_to_world(screen_pos) - self.edited_layer_offset();
+
+        let x = cell_index(world_pos.x / self.tile_size.x);
+        let y = cell_index(world_pos.y / self.tile_size.y);
+        if x < 0.0 || y < 0.0 || x >= self.map_size.x as f32 || y >= self.map_size.y as f32 {
             return None;
         }
 
-        Some(position)
+        Some(Vector2u::new(x as u32, y as u32))
+    }
+
+    /// Translate tile position to world position (i.e position in the renderer view)
+    /// the returned position is the top left corner of the tile
+    pub fn tile_to_world<T: Into<Vector2u>>(&self, position: T) -> Vector2f {
+        let position = position.into();
+        Vector2f::new(
+            position.x as f32 * self.tile_size.x,
+            position.y as f32 * self.tile_size.y,
+        )
+    }
+
+    /// Retrieve the bounds of given tile in world position (i.e position in the renderer view)
+    pub fn tile_bounds<T: Into<Vector2u>>(&self, position: T) -> FloatRect {
+        let position = self.tile_to_world(position);
+        FloatRect::new(position.x, position.y, self.tile_size.x, self.tile_size.y)
+    }
+
+    /// Retrieve the bounds of the whole tile map in world position
+    pub fn world_bounds(&self) -> FloatRect {
+        FloatRect::new(
+            0.0,
+            0.0,
+            self.map_size.x as f32 * self.tile_size.x,
+            self.map_size.y as f32 * self.tile_size.y,
+        )
+    }
+
+    /// Translate screen position (expressed in the default view)
+    /// to world position (i.e position in the renderer view)
+    pub fn screen_to_world<P: Into<Vector2f>>(&self, screen_pos: P) -> Vector2f {
+        let offset = screen_pos.into() - self.original_view_center;
+        let scale = self.view_scale();
+        let offset = Vector2f::new(offset.x * scale.x, offset.y * scale.y);
+
+        let (sin, cos) = self.view.rotation().to_radians().sin_cos();
+        self.view.center()
+            + Vector2f::new(
+                offset.x * cos - offset.y * sin,
+                offset.x * sin + offset.y * cos,
+            )
+    }
+
+    /// Translate world position (i.e position in the renderer view)
+    /// to screen position (expressed in the default view)
+    pub fn world_to_screen<P: Into<Vector2f>>(&self, world_pos: P) -> Vector2f {
+        let offset = world_pos.into() - self.view.center();
+
+        let (sin, cos) = self.view.rotation().to_radians().sin_cos();
+        let offset = Vector2f::new(
+            offset.x * cos + offset.y * sin,
+            -offset.x * sin + offset.y * cos,
+        );
+
+        let scale = self.view_scale();
+        self.original_view_center + Vector2f::new(offset.x / scale.x, offset.y / scale.y)
     }
 
     /// Update the renderer using given tile map & display parameters
@@ -90,251 +529,2454 @@ impl<'s> TileMapRenderer<'s> {
     /// - tile_map: the inner tile map details
     /// - screen_size: the screen size in pixel
     /// - viewport_size: the expected viewport size (will affect number of tiles displayed on screen)
+    ///
+    /// this will fails if the tile map contains unknown tiles and the missing tile policy is Error,
+    /// in this case the renderer is left untouched
     pub fn update<T: Into<Vector2u>>(
         &mut self,
         tile_map: &TileMap,
         screen_size: T,
         viewport_size: T,
+    ) -> Result<(), RendererError> {
+        if self.missing_tile_policy == MissingTilePolicy::Error {
+            let unknown_tiles = self.count_unknown_tiles(tile_map);
+            if !unknown_tiles.is_empty() {
+                return Err(RendererError::UnknownTiles(
+                    unknown_tiles.into_keys().collect(),
+                ));
+            }
+        }
+
+        self.rebuild(tile_map, screen_size.into(), viewport_size.into());
+        Ok(())
+    }
+
+    /// Resize the renderer to given screen size (i.e when the window is resized)
+    ///
+    /// # Arguments
+    /// - screen_size: the new screen size in pixel
+    /// - viewport_size: the expected viewport size (will affect number of tiles displayed on screen)
+    /// - default_view: the new default view (matching the new screen size)
+    ///
+    /// the tiles keep their size in world units, only the view size is adjusted so the view
+    /// keeps showing the same area of the tile map (same center, rotation & zoom level)
+    pub fn resize<T: Into<Vector2u>>(
+        &mut self,
+        screen_size: T,
+        viewport_size: T,
+        default_view: SfBox<View>,
     ) {
-        let tile_map_size = tile_map.size();
-        let screen_size = screen_size.into();
-        let viewport_size = viewport_size.into();
+        let zoom = self.zoom_level();
 
-        let mut layers = Vec::with_capacity(tile_map.layer_count() as usize);
+        let tile_scale = fit_tile_scale(
+            self.tileset.tile_size(),
+            screen_size.into(),
+            viewport_size.into(),
+        );
+        if tile_scale > 0.0 && self.tile_scale > 0.0 {
+            self.screen_scale = self.tile_scale / tile_scale;
+        }
+        self.original_view_center = default_view.center();
+        self.original_view_size = default_view.size();
+        self.set_zoom(zoom);
+    }
 
-        // Determinate tile size to fix them on whole screen
-        // this algorithm will try to display at least the expected viewport size
-        // this means that they **may** be more tiles displayed, depending on screen resolution
-        let tile_width = screen_size.x / viewport_size.x;
-        let tile_height = screen_size.y / viewport_size.y;
-        let tile_size = if tile_width < tile_height {
-            tile_width
-        } else {
-            tile_height
-        } as f32;
+    /// Set the tile at given pos
+    /// only the quad of the given tile is updated
+    ///
+    /// this will fails if the layer doesn't exist, or if the tile is unknown and the missing tile policy is Error
+    pub fn set_tile<T: Into<Vector2u>>(
+        &mut self,
+        position: T,
+        layer: u32,
+        tile: u32,
+    ) -> Result<(), RendererError> {
+        let position = position.into();
+        let index = (position.x + position.y * self.map_size.x) as usize;
+        let chunk_index =
+            (position.x / CHUNK_SIZE + position.y / CHUNK_SIZE * self.chunk_count.x) as usize;
 
-        for layer in 0..tile_map.layer_count() {
-            let mut tiles = Vec::with_capacity((tile_map_size.x * tile_map_size.y) as usize);
-            for y in 0..tile_map_size.y {
-                for x in 0..tile_map_size.x {
-                    let mut tile = Sprite::new();
-                    tile.set_scale(((1.0 / 16.0) * tile_size, (1.0 / 16.0) * tile_size));
-                    tile.set_position((x as f32 * tile_size, y as f32 * tile_size));
+        if layer as usize >= self.tiles.len() {
+            return Err(RendererError::InvalidLayer);
+        }
+        if !self.is_known_tile(tile) {
+            if self.missing_tile_policy == MissingTilePolicy::Error {
+                return Err(RendererError::UnknownTiles(
+                    vec![tile].into_iter().collect(),
+                ));
+            }
+            self.add_unknown_tile(tile);
+        }
+        // The overwritten tile may have been the last occurrence of an unknown tile
+        let previous = self.tiles[layer as usize][index];
+        if !self.is_known_tile(previous) {
+            self.remove_unknown_tile(previous);
+        }
+        let displayed_tile = displayed_tile(self.tileset, self.time, position, tile);
+        let display = tile_display(
+            self.tileset,
+            self.missing_tile_policy,
+            self.tile_scale,
+            displayed_tile,
+        );
 
-                    let tile_id = tile_map.get_tile((x, y), layer).unwrap();
+        self.animated_cells.remove(&(layer, index));
+        if self.tileset.animation(tile).is_some() {
+            self.animated_cells.insert((layer, index), displayed_tile);
+        }
+
+        self.tiles.get_mut(layer as usize).unwrap()[index] = tile;
+        self.layers.get_mut(layer as usize).unwrap()[chunk_index].set_tile(
+            position,
+            self.tile_size,
+            display,
+        );
+        // Only the debug overlays of the changed chunk are outdated
+        if self.debug[chunk_index].is_some() {
+            let chunk = Vector2u::new(position.x / CHUNK_SIZE, position.y / CHUNK_SIZE);
+            self.debug[chunk_index] = Some(self.build_debug_chunk(chunk));
+        }
+        self.update_light_at(&[position]);
+
+        Ok(())
+    }
+
+    /// Update the objects displayed using given tile map
+    /// only the objects geometry is rebuilt
+    ///
+    /// this will fails if an object tile is unknown and the missing tile policy is Error,
+    /// in this case the renderer is left untouched
+    pub fn update_objects(&mut self, tile_map: &TileMap) -> Result<(), RendererError> {
+        let objects = sorted_objects(tile_map);
+        let unknown_tiles: Vec<u32> = objects
+            .iter()
+            .flatten()
+            .filter(|object| !self.is_known_tile(object.tile_id))
+            .map(|object| object.tile_id)
+            .collect();
+        if self.missing_tile_policy == MissingTilePolicy::Error && !unknown_tiles.is_empty() {
+            return Err(RendererError::UnknownTiles(
+                unknown_tiles
+                    .into_iter()
+                    .map(|tile_id| tile_id & TILE_ID_MASK)
+                    .collect(),
+            ));
+        }
 
-                    // Tile_id == 0 is transparent
-                    if tile_id == 0 {
-                        tile.set_color(Color::TRANSPARENT);
-                    } else {
-                        tile.set_texture(self.textures.get(&tile_id).unwrap(), true);
+        let previous = std::mem::replace(&mut self.objects, objects);
+        for object in previous.iter().flatten() {
+            if !self.is_known_tile(object.tile_id) {
+                self.remove_unknown_tile(object.tile_id);
+            }
+        }
+        for tile_id in unknown_tiles {
+            self.add_unknown_tile(tile_id);
+        }
+        self.object_quads = self.build_objects();
+        if self.debug_settings.is_enabled() {
+            self.debug = self.empty_chunks();
+            self.build_visible_chunks();
+        }
+
+        // Only the light around the placed & removed objects changes
+        if self.lighting.is_some() {
+            let contains = |objects: &[Vec<MapObject>], object: &MapObject| {
+                objects.iter().flatten().any(|other| other == object)
+            };
+            let changed_objects = previous
+                .iter()
+                .flatten()
+                .filter(|object| !contains(&self.objects, object))
+                .chain(
+                    self.objects
+                        .iter()
+                        .flatten()
+                        .filter(|object| !contains(&previous, object)),
+                );
+            let mut cells = BTreeSet::new();
+            for object in changed_objects {
+                let end = Vector2u::new(
+                    object.position.x.saturating_add(object.footprint.x),
+                    object.position.y.saturating_add(object.footprint.y),
+                );
+                for y in object.position.y..end.y.min(self.map_size.y) {
+                    for x in object.position.x..end.x.min(self.map_size.x) {
+                        cells.insert((x, y));
                     }
+                }
+            }
+            let cells: Vec<Vector2u> = cells.into_iter().map(Vector2u::from).collect();
+            self.update_light_at(&cells);
+        }
+        Ok(())
+    }
+
+    /// Apply the changes made on given tile map, only the changed parts are updated
+    /// this keeps the renderer in sync with the tile map without a full update
+    ///
+    /// every change is applied even if some of them fail, the cells that fail keep their previous display
+    /// the unknown tiles of all the changes are reported together, other errors take precedence
+    pub fn apply_changes(
+        &mut self,
+        tile_map: &TileMap,
+        changes: &[TileMapChange],
+    ) -> Result<(), RendererError> {
+        let mut unknown_tiles = BTreeSet::new();
+        let mut error = None;
+        let mut objects_changed = false;
+
+        for change in changes {
+            let result = match *change {
+                TileMapChange::Tile { position, layer } => match tile_map.get_tile(position, layer)
+                {
+                    Some(tile) => self.set_tile(position, layer, tile),
+                    None => Err(RendererError::InvalidLayer),
+                },
+                TileMapChange::LayerSettings { layer } => match tile_map.layer_settings(layer) {
+                    Some(settings) => self.set_layer_settings(layer, *settings),
+                    None => Err(RendererError::InvalidLayer),
+                },
+                TileMapChange::Objects => {
+                    objects_changed = true;
+                    Ok(())
+                }
+            };
+
+            match result {
+                Err(RendererError::UnknownTiles(tiles)) => unknown_tiles.extend(tiles),
+                Err(e) => error = error.or(Some(e)),
+                Ok(_) => {}
+            }
+        }
+
+        if objects_changed {
+            match self.update_objects(tile_map) {
+                Err(RendererError::UnknownTiles(tiles)) => unknown_tiles.extend(tiles),
+                Err(e) => error = error.or(Some(e)),
+                Ok(_) => {}
+            }
+        }
+
+        match error {
+            Some(e) => Err(e),
+            None if !unknown_tiles.is_empty() => Err(RendererError::UnknownTiles(unknown_tiles)),
+            None => Ok(()),
+        }
+    }
 
-                    tiles.push(tile);
+    /// Take the pending changes of given tile map and apply them
+    /// see apply_changes
+    pub fn sync(&mut self, tile_map: &mut TileMap) -> Result<(), RendererError> {
+        let changes = tile_map.take_changes();
+        self.apply_changes(tile_map, &changes)
+    }
+
+    /// Advance the animation clock by given delta time (in seconds)
+    /// only the animated cells whose frame has changed are updated
+    pub fn advance(&mut self, dt: f32) {
+        self.time += dt;
+
+        // The ambient light changes over time, the light is only rebuilt when its color changes
+        if let Some(lighting) = &self.lighting {
+            let ambient = lighting.cycle.ambient_at(self.time);
+            if ambient != self.ambient {
+                self.ambient = ambient;
+                self.light = self.empty_chunks();
+                self.build_visible_chunks();
+            }
+        }
+
+        // Only the quads of the animated objects are updated
+        for (layer, objects) in self.objects.iter().enumerate() {
+            let color = self.layer_settings[layer].color();
+            for (index, object) in objects.iter().enumerate() {
+                if self.tileset.animation(object.tile_id).is_none() {
+                    continue;
                 }
+                let tile_id =
+                    displayed_tile(self.tileset, self.time, object.position, object.tile_id);
+                set_quad(
+                    &mut self.object_quads[layer],
+                    index,
+                    object.position,
+                    self.tile_size,
+                    color,
+                    tile_display(
+                        self.tileset,
+                        MissingTilePolicy::Skip,
+                        self.tile_scale,
+                        tile_id,
+                    ),
+                );
             }
-            layers.push(tiles);
         }
 
-        // Create the grid
-        let mut grid = Vec::new();
-        for y in 0..tile_map_size.y {
-            let mut line = VertexArray::new(PrimitiveType::Lines, 2);
-            line.index_mut(0).position = Vector2f::new(0.0, y as f32 * tile_size);
-            line.index_mut(0).color = Color::BLACK;
-            line.index_mut(1).position =
-                Vector2f::new((tile_map_size.x) as f32 * tile_size, y as f32 * tile_size);
-            line.index_mut(1).color = Color::BLACK;
-            grid.push(line);
+        // Scrolling layers may reveal new chunks
+        if self
+            .layer_settings
+            .iter()
+            .any(|settings| settings.scroll_speed != Vector2f::new(0.0, 0.0))
+        {
+            self.build_visible_chunks();
         }
-        for x in 0..tile_map_size.x {
-            let mut line = VertexArray::new(PrimitiveType::Lines, 2);
-            line.index_mut(0).position = Vector2f::new(x as f32 * tile_size, 0.0);
-            line.index_mut(0).color = Color::BLACK;
-            line.index_mut(1).position =
-                Vector2f::new(x as f32 * tile_size, (tile_map_size.y) as f32 * tile_size);
-            line.index_mut(1).color = Color::BLACK;
-            grid.push(line);
+
+        for ((layer, index), displayed) in &mut self.animated_cells {
+            let tile_id = self.tiles[*layer as usize][*index];
+            let position = Vector2u::new(
+                *index as u32 % self.map_size.x,
+                *index as u32 / self.map_size.x,
+            );
+
+            let tile = displayed_tile(self.tileset, self.time, position, tile_id);
+            if tile == *displayed {
+                continue;
+            }
+            *displayed = tile;
+
+            let chunk_index =
+                (position.x / CHUNK_SIZE + position.y / CHUNK_SIZE * self.chunk_count.x) as usize;
+            self.layers[*layer as usize][chunk_index].set_tile(
+                position,
+                self.tile_size,
+                tile_display(
+                    self.tileset,
+                    self.missing_tile_policy,
+                    self.tile_scale,
+                    tile,
+                ),
+            );
         }
+    }
 
-        self.layers = layers;
-        self.grid = grid;
-        self.map_size = tile_map_size;
-        self.tile_size = tile_size;
+    /// Set the policy used to display the tiles that are not present in the tileset
+    /// the policy will apply to the next updates
+    pub fn set_missing_tile_policy(&mut self, policy: MissingTilePolicy) {
+        self.missing_tile_policy = policy;
     }
 
-    /// Set the tile at given pos
-    pub fn set_tile<T: Into<Vector2u>>(&mut self, position: T, layer: u32, tile: u32) {
-        let position = position.into();
-        let index = (position.x + position.y * self.map_size.x) as usize;
+    /// Returns the policy used to display the tiles that are not present in the tileset
+    pub fn missing_tile_policy(&self) -> MissingTilePolicy {
+        self.missing_tile_policy
+    }
+
+    /// Returns the tiles found in the tile map that are not present in the tileset
+    pub fn unknown_tiles(&self) -> BTreeSet<u32> {
+        self.unknown_tiles.keys().copied().collect()
+    }
 
-        let sprite = self
-            .layers
+    /// Retrieve the display settings of given layer
+    /// this will return None if the layer doesn't exist
+    pub fn layer_settings(&self, layer: u32) -> Option<&LayerSettings> {
+        self.layer_settings.get(layer as usize)
+    }
+
+    /// Set the display settings of given layer
+    /// this operation will fails if the layer doesn't exist
+    pub fn set_layer_settings(
+        &mut self,
+        layer: u32,
+        settings: LayerSettings,
+    ) -> Result<(), RendererError> {
+        let current = self
+            .layer_settings
             .get_mut(layer as usize)
-            .unwrap()
-            .get_mut(index)
-            .unwrap();
+            .ok_or(RendererError::InvalidLayer)?;
+        *current = settings;
 
-        if tile == 0 {
-            sprite.set_color(Color::TRANSPARENT);
-        } else {
-            sprite.set_color(Color::WHITE);
-            sprite.set_texture(&self.textures.get(&tile).unwrap(), true);
+        // Changing the layer color require to rebuild the layer chunks
+        for chunk in &mut self.layers[layer as usize] {
+            chunk.set_color(settings.color());
         }
+        self.build_visible_chunks();
+        self.object_quads = self.build_objects();
+
+        Ok(())
+    }
+
+    /// Set the layer the tiles are picked on and the editing overlays are attached to
+    /// (hovered cell, selection & placement preview), they follow the layer parallax & scrolling
+    pub fn set_edited_layer(&mut self, layer: u32) -> Result<(), RendererError> {
+        if layer as usize >= self.layer_settings.len() {
+            return Err(RendererError::InvalidLayer);
+        }
+        self.edited_layer = layer;
+        Ok(())
+    }
+
+    /// Returns the layer the tiles are picked on
+    pub fn edited_layer(&self) -> u32 {
+        self.edited_layer
+    }
+
+    /// Returns the size of a tile cell in world units
+    pub fn tile_size(&self) -> Vector2f {
+        self.tile_size
     }
 
     /// Toggle the grid visibility
     pub fn set_show_grid(&mut self, show: bool) {
-        self.show_grid = show
+        self.show_grid = show;
+        self.build_visible_chunks();
     }
 
     /// Returns the grid visibility
     pub fn show_grid(&self) -> bool {
         self.show_grid
     }
-}
 
-impl<'s> Drawable for TileMapRenderer<'s> {
-    fn draw<'a: 'shader, 'texture, 'shader, 'shader_texture>(
-        &'a self,
-        target: &mut dyn RenderTarget,
-        states: RenderStates<'texture, 'shader, 'shader_texture>,
-    ) {
-        target.set_view(&self.view);
-        for tiles in &self.layers {
-            for tile in tiles {
-                target.draw_with_renderstates(tile, states);
+    /// Set the grid display settings
+    pub fn set_grid_settings(&mut self, settings: GridSettings) {
+        self.grid_settings = settings;
+        self.grid = self.empty_chunks();
+        self.build_visible_chunks();
+    }
+
+    /// Returns the grid display settings
+    pub fn grid_settings(&self) -> &GridSettings {
+        &self.grid_settings
+    }
+
+    /// Set the display settings of the editing overlays
+    pub fn set_overlay_settings(&mut self, settings: OverlaySettings) {
+        self.overlay_settings = settings;
+        self.overlay = self.build_overlay();
+        self.ghost = self.build_ghost();
+    }
+
+    /// Returns the display settings of the editing overlays
+    pub fn overlay_settings(&self) -> &OverlaySettings {
+        &self.overlay_settings
+    }
+
+    /// Set the cells visibility used to display the fog of war, None disable the fog
+    /// the cells outside the grid are visible
+    ///
+    /// only the fog of the chunks around the cells whose visibility changed is rebuilt
+    pub fn set_visibility(&mut self, visibility: Option<&VisibilityGrid>) {
+        match (&mut self.visibility, visibility) {
+            (Some(current), Some(visibility)) if current.size() == visibility.size() => {
+                let size = visibility.size();
+                for y in 0..size.y {
+                    for x in 0..size.x {
+                        let cell = visibility.visibility((x, y)).unwrap();
+                        if current.visibility((x, y)) == Some(cell) {
+                            continue;
+                        }
+                        current.set_visibility((x, y), cell).unwrap();
+
+                        // The soft edges of the neighbours cells fade to the changed one
+                        for y in y.saturating_sub(1)..=y + 1 {
+                            for x in x.saturating_sub(1)..=x + 1 {
+                                if x < self.map_size.x && y < self.map_size.y {
+                                    let index =
+                                        x / CHUNK_SIZE + y / CHUNK_SIZE * self.chunk_count.x;
+                                    self.fog[index as usize] = None;
+                                }
+                            }
+                        }
+                    }
+                }
             }
-        }
-        if self.show_grid {
-            for line in &self.grid {
-                target.draw_vertex_array(line, states);
+            (current, visibility) => {
+                *current = visibility.cloned();
+                self.fog = self.empty_chunks();
             }
         }
-        target.set_view(&target.default_view().to_owned());
+        self.build_visible_chunks();
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use sfml::graphics::{Texture, View};
-    use sfml::system::{SfBox, Vector2f};
+    /// Set the display settings of the fog of war
+    pub fn set_fog_settings(&mut self, settings: FogSettings) {
+        self.fog_settings = settings;
+        self.fog = self.empty_chunks();
+        self.build_visible_chunks();
+    }
 
-    use crate::tilemap::{TileMap, TileMapRenderer};
-    use std::collections::BTreeMap;
+    /// Retrieve the display settings of the fog of war
+    pub fn fog_settings(&self) -> &FogSettings {
+        &self.fog_settings
+    }
 
-    #[test]
-    fn test_tile_map_renderer_new() {
-        let textures = load_textures();
-        let tile_map = TileMap::new((5, 5), 1, 2);
-        let renderer = TileMapRenderer::new(
-            &tile_map,
-            (1920, 1080),
-            (5, 5),
-            View::new((0.0, 0.0).into(), (10.0, 10.0).into()),
-            &textures,
-            true,
-        );
+    /// Set the lighting of the tile map (day / night cycle & light sources), None disable it
+    pub fn set_lighting(&mut self, lighting: Option<Lighting>) {
+        self.lighting = lighting;
+        self.update_light();
+        self.build_visible_chunks();
+    }
 
-        assert_eq!(renderer.layers.len(), 1);
-        assert_eq!(renderer.layers.get(0).unwrap().len(), 25);
-        assert_eq!(renderer.tile_size, 216.0); // We want a 5x5 viewport, therefore size will be 1080/5
-        assert_eq!(renderer.map_size, (5, 5).into());
+    /// Retrieve the lighting of the tile map, if enabled
+    pub fn lighting(&self) -> Option<&Lighting> {
+        self.lighting.as_ref()
     }
 
-    #[test]
-    fn test_tile_map_renderer_move() {
-        let textures = load_textures();
-        let tile_map = TileMap::new((5, 5), 1, 2);
-        let mut renderer = TileMapRenderer::new(
-            &tile_map,
-            (1920, 1080),
-            (5, 5),
-            View::new((0.0, 0.0).into(), (10.0, 10.0).into()),
-            &textures,
-            true,
-        );
+    /// Draw the tile map using given backend
+    /// the layers are drawn from bottom to top, followed by the light, fog of war, grid,
+    /// debug overlays, placement preview and hover highlight & selection
+    ///
+    /// the backend geometry is kept between draws, only the modified batches are built again
+    pub fn render<B>(&self, backend: &mut B)
+    where
+        B: RenderBackend + ?Sized,
+        B::Geometry: 'static,
+    {
+        let mut geometry = self.geometry.borrow_mut();
+        if !matches!(geometry.as_ref(), Some(cache) if cache.is::<RendererCache<B::Geometry>>()) {
+            *geometry = Some(Box::new(RendererCache::<B::Geometry>::new()));
+        }
+        let cache = geometry
+            .as_mut()
+            .and_then(|cache| cache.downcast_mut::<RendererCache<B::Geometry>>())
+            .unwrap();
 
-        assert_eq!(renderer.view.size(), (10.0, 10.0).into());
-        assert_eq!(renderer.view.center(), (0.0, 0.0).into());
+        backend.set_view(self.view.center(), self.view.size(), self.view.rotation());
 
-        renderer.move_(Vector2f::new(10.0, 0.0));
+        for (layer, (chunks, settings)) in self.layers.iter().zip(&self.layer_settings).enumerate()
+        {
+            if !settings.visible {
+                continue;
+            }
 
-        assert_eq!(renderer.view.center(), (10.0, 0.0).into());
-    }
+            let offset = self.layer_offset(settings);
+            for (shift, x_range, y_range) in self.visible_chunks(layer) {
+                let offset = offset + shift;
+                for y in y_range {
+                    for x in x_range.clone() {
+                        let index = (x + y * self.chunk_count.x) as usize;
+                        let chunk = &chunks[index];
+                        if let Some(quads) = chunk.quads() {
+                            let key = GeometryKey::Tiles {
+                                layer,
+                                chunk: index,
+                            };
+                            let geometry = cache.geometry(backend, key, quads, true);
+                            backend.draw(geometry, settings.blend_mode, offset);
+                        }
+                        let key = GeometryKey::Placeholders {
+                            layer,
+                            chunk: index,
+                        };
+                        let geometry = cache.geometry(backend, key, chunk.placeholders(), false);
+                        backend.draw(geometry, settings.blend_mode, offset);
+                    }
+                }
+
+                // Objects are drawn above the layer tiles, from back to front
+                let key = GeometryKey::Objects { layer };
+                let geometry = cache.geometry(backend, key, &self.object_quads[layer], true);
+                backend.draw(geometry, settings.blend_mode, offset);
+            }
+        }
+
+        // The light is multiplied with the layers colors
+        // the grid is kept once built, even while hidden
+        let origin = Vector2f::default();
+        let (x_range, y_range) = self.visible_map_chunks();
+        let overlays = [
+            (true, &self.light, LayerBlendMode::Multiply),
+            (true, &self.fog, LayerBlendMode::Alpha),
+            (self.show_grid, &self.grid, LayerBlendMode::Alpha),
+            (true, &self.debug, LayerBlendMode::Alpha),
+        ];
+        for (overlay, (_, overlays, blend_mode)) in overlays
+            .iter()
+            .enumerate()
+            .filter(|(_, (shown, _, _))| *shown)
+        {
+            for y in y_range.clone() {
+                for x in x_range.clone() {
+                    let index = (x + y * self.chunk_count.x) as usize;
+                    if let Some(quads) = &overlays[index] {
+                        let key = GeometryKey::Overlay {
+                            overlay,
+                            chunk: index,
+                        };
+                        let geometry = cache.geometry(backend, key, quads, false);
+                        backend.draw(geometry, *blend_mode, origin);
+                    }
+                }
+            }
+        }
+
+        // The editing overlays move along with the edited layer
+        let offset = self.edited_layer_offset();
+        let geometry = cache.geometry(backend, GeometryKey::Ghost, &self.ghost, true);
+        backend.draw(geometry, LayerBlendMode::Alpha, offset);
+        let geometry = cache.geometry(backend, GeometryKey::Editing, &self.overlay, false);
+        backend.draw(geometry, LayerBlendMode::Alpha, offset);
+    }
+
+    /// Set the display settings of the debug overlays
+    pub fn set_debug_settings(&mut self, settings: DebugSettings) {
+        self.debug_settings = settings;
+        self.debug = self.empty_chunks();
+        self.build_visible_chunks();
+    }
+
+    /// Retrieve the display settings of the debug overlays
+    pub fn debug_settings(&self) -> &DebugSettings {
+        &self.debug_settings
+    }
+
+    /// Highlight given cell (i.e the cell under the cursor), None remove the highlight
+    pub fn set_hover(&mut self, position: Option<Vector2u>) {
+        if self.hover != position {
+            self.hover = position;
+            self.overlay = self.build_overlay();
+        }
+    }
+
+    /// Returns the highlighted cell
+    pub fn hover(&self) -> Option<Vector2u> {
+        self.hover
+    }
+
+    /// Select given cells, the selection is displayed as a filled & outlined mask
+    /// the cells outside the tile map are ignored
+    pub fn set_selection<I: IntoIterator<Item = Vector2u>>(&mut self, cells: I) {
+        let map_size = self.map_size;
+        self.selection = cells
+            .into_iter()
+            .filter(|cell| cell.x < map_size.x && cell.y < map_size.y)
+            .map(|cell| (cell.x, cell.y))
+            .collect();
+        self.overlay = self.build_overlay();
+    }
+
+    /// Select the rectangle of cells between given corners (included)
+    pub fn set_selection_rect<T: Into<Vector2u>>(&mut self, start: T, end: T) {
+        let (start, end) = (start.into(), end.into());
+        let (min_x, max_x) = (start.x.min(end.x), start.x.max(end.x));
+        let (min_y, max_y) = (start.y.min(end.y), start.y.max(end.y));
+
+        self.set_selection(
+            (min_y..=max_y).flat_map(|y| (min_x..=max_x).map(move |x| Vector2u::new(x, y))),
+        );
+    }
+
+    /// Returns the selected cells
+    pub fn selection(&self) -> impl Iterator<Item = Vector2u> + '_ {
+        self.selection.iter().map(|(x, y)| Vector2u::new(*x, *y))
+    }
+
+    /// Display a semi transparent preview of given tiles at given position
+    /// the tiles position are relative to the preview position (i.e a stamp)
+    /// None hide the preview
+    pub fn set_ghost(&mut self, position: Option<Vector2u>, tiles: &[(Vector2u, u32)]) {
+        if self.ghost_position == position && self.ghost_tiles == tiles {
+            return;
+        }
+
+        self.ghost_position = position;
+        self.ghost_tiles = tiles.to_vec();
+        self.ghost = self.build_ghost();
+    }
+
+    /// Re-create the renderer geometry using given tile map & display parameters
+    fn rebuild(&mut self, tile_map: &TileMap, screen_size: Vector2u, viewport_size: Vector2u) {
+        let tile_map_size = tile_map.size();
+
+        // Determinate tile size to fix them on whole screen
+        // this algorithm will try to display at least the expected viewport size
+        // this means that they **may** be more tiles displayed, depending on screen resolution
+        // the tiles are scaled uniformly, so non square tiles keep their aspect ratio
+        let cell_size = self.tileset.tile_size();
+        let tile_scale = fit_tile_scale(cell_size, screen_size, viewport_size);
+        let tile_size = Vector2f::new(
+            cell_size.x as f32 * tile_scale,
+            cell_size.y as f32 * tile_scale,
+        );
+
+        // Split the layers into chunks, their geometry will be built once visible
+        let chunk_count = Vector2u::new(
+            tile_map_size.x.div_ceil(CHUNK_SIZE),
+            tile_map_size.y.div_ceil(CHUNK_SIZE),
+        );
+        let mut layers = Vec::with_capacity(tile_map.layer_count() as usize);
+        let mut tiles = Vec::with_capacity(tile_map.layer_count() as usize);
+        let mut layer_settings = Vec::with_capacity(tile_map.layer_count() as usize);
+        for layer in 0..tile_map.layer_count() {
+            let settings = tile_map.layer_settings(layer).copied().unwrap_or_default();
+            layer_settings.push(settings);
+
+            let mut chunks = Vec::with_capacity((chunk_count.x * chunk_count.y) as usize);
+            for y in 0..chunk_count.y {
+                for x in 0..chunk_count.x {
+                    let position = Vector2u::new(x * CHUNK_SIZE, y * CHUNK_SIZE);
+                    let size = Vector2u::new(
+                        CHUNK_SIZE.min(tile_map_size.x - position.x),
+                        CHUNK_SIZE.min(tile_map_size.y - position.y),
+                    );
+                    let mut chunk = Chunk::new(position, size);
+                    chunk.set_color(settings.color());
+                    chunks.push(chunk);
+                }
+            }
+            layers.push(chunks);
+
+            let mut layer_tiles = Vec::with_capacity((tile_map_size.x * tile_map_size.y) as usize);
+            for y in 0..tile_map_size.y {
+                for x in 0..tile_map_size.x {
+                    layer_tiles.push(tile_map.get_tile((x, y), layer).unwrap());
+                }
+            }
+            tiles.push(layer_tiles);
+        }
+
+        self.layers = layers;
+        self.tiles = tiles;
+        self.layer_settings = layer_settings;
+        self.chunk_count = chunk_count;
+        self.map_size = tile_map_size;
+        self.tile_size = tile_size;
+        self.tile_scale = tile_scale;
+        self.screen_scale = 1.0;
+        self.objects = sorted_objects(tile_map);
+        self.object_quads = self.build_objects();
+        self.grid = self.empty_chunks();
+        self.selection
+            .retain(|(x, y)| *x < tile_map_size.x && *y < tile_map_size.y);
+        if self.edited_layer >= tile_map.layer_count() {
+            self.edited_layer = 0;
+        }
+        self.overlay = self.build_overlay();
+        self.ghost = self.build_ghost();
+        self.debug = self.empty_chunks();
+        self.fog = self.empty_chunks();
+        // The chunks may have changed, drop the geometry built from the previous ones
+        self.geometry = RefCell::new(None);
+        self.update_light();
+        self.unknown_tiles = self.count_unknown_tiles(tile_map);
+        self.animated_cells = self.find_animated_cells();
+        self.build_visible_chunks();
+    }
+
+    /// Create a per chunk overlay of the tile map (i.e the grid), without geometry
+    fn empty_chunks(&self) -> Vec<Option<Quads>> {
+        (0..self.chunk_count.x * self.chunk_count.y)
+            .map(|_| None)
+            .collect()
+    }
+
+    /// Build the grid lines and labels of the chunk at given position (in chunks)
+    /// each chunk holds the lines starting on its cells
+    fn build_grid_chunk(&self, chunk: Vector2u) -> Quads {
+        let settings = &self.grid_settings;
+        let mut grid = Quads::new();
+        let (start, end) = self.chunk_cells(chunk);
+        let line_style = |index: u32| {
+            if settings.major_interval > 0 && index.is_multiple_of(settings.major_interval) {
+                (settings.major_color, settings.major_thickness)
+            } else {
+                (settings.color, settings.thickness)
+            }
+        };
+        // The lines extend over the map borders to cover the corners
+        let extent = |start: u32, end: u32, count: u32, size: f32, thickness: f32| {
+            let from = start as f32 * size - if start == 0 { thickness / 2.0 } else { 0.0 };
+            let to = end as f32 * size + if end == count { thickness / 2.0 } else { 0.0 };
+            (from, to - from)
+        };
+        // The last chunks also hold the right / bottom border lines
+        let lines_end = |end: u32, count: u32| if end == count { end + 1 } else { end };
+
+        for y in start.y..lines_end(end.y, self.map_size.y) {
+            let (color, thickness) = line_style(y);
+            let top = y as f32 * self.tile_size.y - thickness / 2.0;
+            let (left, width) =
+                extent(start.x, end.x, self.map_size.x, self.tile_size.x, thickness);
+            append_rect(
+                &mut grid,
+                FloatRect::new(left, top, width, thickness),
+                color,
+            );
+        }
+        for x in start.x..lines_end(end.x, self.map_size.x) {
+            let (color, thickness) = line_style(x);
+            let left = x as f32 * self.tile_size.x - thickness / 2.0;
+            let (top, height) =
+                extent(start.y, end.y, self.map_size.y, self.tile_size.y, thickness);
+            append_rect(
+                &mut grid,
+                FloatRect::new(left, top, thickness, height),
+                color,
+            );
+        }
+
+        // Label the first cell of the labelled rows / columns
+        if settings.show_labels {
+            let interval = settings.major_interval.max(1);
+            let pixel_size = (self.tile_size.y / 4.0 / GLYPH_HEIGHT as f32).max(1.0);
+            let margin = settings.major_thickness.max(settings.thickness) + pixel_size;
+
+            let columns = (start.x..end.x).filter(|x| start.y == 0 && x % interval == 0);
+            let rows = (start.y..end.y).filter(|y| start.x == 0 && *y > 0 && y % interval == 0);
+            for (label, position) in columns
+                .map(|x| (x, Vector2u::new(x, 0)))
+                .chain(rows.map(|y| (y, Vector2u::new(0, y))))
+            {
+                let position = self.tile_to_world(position) + Vector2f::new(margin, margin);
+                append_text(
+                    &mut grid,
+                    &label.to_string(),
+                    position,
+                    pixel_size,
+                    settings.label_color,
+                );
+            }
+        }
+
+        grid
+    }
+
+    /// Build the hover highlight & the selection mask (filled, with an outline on its borders)
+    fn build_overlay(&self) -> Quads {
+        let settings = &self.overlay_settings;
+        let mut overlay = Quads::new();
+        let thickness = settings.outline_thickness;
+
+        for (x, y) in &self.selection {
+            let bounds = self.tile_bounds((*x, *y));
+            append_rect(&mut overlay, bounds, settings.selection_color);
+
+            // Only outline the sides that are not shared with another selected cell
+            let (right, bottom) = (
+                bounds.left + bounds.width - thickness,
+                bounds.top + bounds.height - thickness,
+            );
+            let sides = [
+                (
+                    *x > 0 && self.selection.contains(&(x - 1, *y)),
+                    FloatRect::new(bounds.left, bounds.top, thickness, bounds.height),
+                ),
+                (
+                    self.selection.contains(&(x + 1, *y)),
+                    FloatRect::new(right, bounds.top, thickness, bounds.height),
+                ),
+                (
+                    *y > 0 && self.selection.contains(&(*x, y - 1)),
+                    FloatRect::new(bounds.left, bounds.top, bounds.width, thickness),
+                ),
+                (
+                    self.selection.contains(&(*x, y + 1)),
+                    FloatRect::new(bounds.left, bottom, bounds.width, thickness),
+                ),
+            ];
+            for (shared, rect) in sides {
+                if !shared {
+                    append_rect(&mut overlay, rect, settings.selection_outline_color);
+                }
+            }
+        }
+
+        if let Some(hover) = self.hover {
+            append_rect(&mut overlay, self.tile_bounds(hover), settings.hover_color);
+        }
+
+        overlay
+    }
+
+    /// Build the placement preview, the tiles outside the tile map are not displayed
+    fn build_ghost(&self) -> Quads {
+        let mut ghost = Quads::new();
+        let position = match self.ghost_position {
+            Some(position) => position,
+            None => return ghost,
+        };
+
+        let alpha = 255.0 * self.overlay_settings.ghost_opacity.clamp(0.0, 1.0);
+        let color = Color::rgba(255, 255, 255, alpha as u8);
+        for (offset, tile_id) in &self.ghost_tiles {
+            let cell = position + *offset;
+            if cell.x >= self.map_size.x || cell.y >= self.map_size.y {
+                continue;
+            }
+
+            let tile_id = displayed_tile(self.tileset, self.time, cell, *tile_id);
+            let display = tile_display(
+                self.tileset,
+                MissingTilePolicy::Skip,
+                self.tile_scale,
+                tile_id,
+            );
+            let index = ghost.vertex_count() / 4;
+            ghost.resize(ghost.vertex_count() + 4);
+            set_quad(&mut ghost, index, cell, self.tile_size, color, display);
+        }
+
+        ghost
+    }
+
+    /// Find the light sources & the blocking cells, and compute the light received by each cell
+    /// the light chunks are rebuilt once visible
+    fn update_light(&mut self) {
+        self.light = self.empty_chunks();
+        self.light_sources.clear();
+        let lighting = match &self.lighting {
+            Some(lighting) => lighting,
+            None => {
+                self.light_map = vec![];
+                self.light_blocking = vec![];
+                return;
+            }
+        };
+
+        let mut sources = Vec::new();
+        for tiles in &self.tiles {
+            for (index, tile_id) in tiles.iter().enumerate() {
+                if let Some(source) = lighting.light(*tile_id) {
+                    sources.push((self.cell_position(index), *source));
+                }
+            }
+        }
+        // The objects emit light from the center of their footprint
+        for object in self.objects.iter().flatten() {
+            if let Some(source) = lighting.light(object.tile_id) {
+                sources.push((object_center(object), *source));
+            }
+        }
+        self.ambient = lighting.cycle.ambient_at(self.time);
+
+        let cell_count = (self.map_size.x * self.map_size.y) as usize;
+        self.light_blocking = (0..cell_count)
+            .map(|index| self.is_blocking(self.cell_position(index)))
+            .collect();
+        self.light_map = vec![[0.0; 3]; cell_count];
+        for (position, source) in sources {
+            self.add_light(position, source);
+        }
+    }
+
+    /// Update the light after the tiles or objects of given cells changed
+    /// only the light sources placed on those cells, or reaching a cell that stopped / started
+    /// blocking the light, are recomputed
+    fn update_light_at(&mut self, cells: &[Vector2u]) {
+        if self.lighting.is_none() {
+            return;
+        }
+
+        let mut blocking_changes = Vec::new();
+        for cell in cells {
+            let index = (cell.x + cell.y * self.map_size.x) as usize;
+            let blocking = self.is_blocking(*cell);
+            if self.light_blocking[index] != blocking {
+                self.light_blocking[index] = blocking;
+                blocking_changes.push(*cell);
+            }
+        }
+
+        let map_size = self.map_size;
+        let (outdated, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.light_sources)
+            .into_iter()
+            .partition(|light| {
+                let (x_range, y_range) = light.source.reach(map_size, light.position);
+                cells.contains(&light.position)
+                    || blocking_changes
+                        .iter()
+                        .any(|cell| x_range.contains(&cell.x) && y_range.contains(&cell.y))
+            });
+        self.light_sources = kept;
+
+        // The sources placed on the changed cells are found again, the others are recast
+        let mut sources: Vec<_> = outdated
+            .iter()
+            .filter(|light| !cells.contains(&light.position))
+            .map(|light| (light.position, light.source))
+            .collect();
+        for cell in cells {
+            sources.extend(self.light_sources_at(*cell));
+        }
+
+        for light in outdated {
+            for (index, strength) in &light.lit_cells {
+                let color = light.source.color;
+                let cell = &mut self.light_map[*index];
+                cell[0] -= color.r as f32 * strength;
+                cell[1] -= color.g as f32 * strength;
+                cell[2] -= color.b as f32 * strength;
+            }
+            self.invalidate_light(light.position, &light.source);
+        }
+        for (position, source) in sources {
+            self.invalidate_light(position, &source);
+            self.add_light(position, source);
+        }
+        self.build_visible_chunks();
+    }
+
+    /// Place given light source at given position, and add the light it casts to the cells it reaches
+    fn add_light(&mut self, position: Vector2u, source: LightSource) {
+        let (blocking, width) = (&self.light_blocking, self.map_size.x);
+        let lit_cells: Vec<(usize, f32)> = cast_light(self.map_size, position, &source, |cell| {
+            blocking[(cell.x + cell.y * width) as usize]
+        })
+        .into_iter()
+        .map(|(cell, strength)| ((cell.x + cell.y * width) as usize, strength))
+        .collect();
+
+        for (index, strength) in &lit_cells {
+            let cell = &mut self.light_map[*index];
+            cell[0] += source.color.r as f32 * strength;
+            cell[1] += source.color.g as f32 * strength;
+            cell[2] += source.color.b as f32 * strength;
+        }
+        self.light_sources.push(PlacedLight {
+            position,
+            source,
+            lit_cells,
+        });
+    }
+
+    /// Discard the light chunks given light source placed at given position may reach
+    /// (the corners of the cells around the reached ones are affected too)
+    fn invalidate_light(&mut self, position: Vector2u, source: &LightSource) {
+        let (x_range, y_range) = source.reach(self.map_size, position);
+        let chunk_range = |range: Range<u32>, count: u32| {
+            range.start.saturating_sub(1) / CHUNK_SIZE..(range.end / CHUNK_SIZE + 1).min(count)
+        };
+        let chunk_count = self.chunk_count;
+        for y in chunk_range(y_range, chunk_count.y) {
+            for x in chunk_range(x_range.clone(), chunk_count.x) {
+                self.light[(x + y * chunk_count.x) as usize] = None;
+            }
+        }
+    }
+
+    /// Retrieve the light sources placed at given position: the tiles of the cell emitting light
+    /// and the objects emitting light from this cell
+    fn light_sources_at(&self, position: Vector2u) -> Vec<(Vector2u, LightSource)> {
+        let lighting = match &self.lighting {
+            Some(lighting) => lighting,
+            None => return vec![],
+        };
+
+        let index = (position.x + position.y * self.map_size.x) as usize;
+        let tiles = self.tiles.iter().map(|tiles| tiles[index]);
+        let objects = self
+            .objects
+            .iter()
+            .flatten()
+            .filter(|object| object_center(object) == position)
+            .map(|object| object.tile_id);
+        tiles
+            .chain(objects)
+            .filter_map(|tile_id| lighting.light(tile_id))
+            .map(|source| (position, *source))
+            .collect()
+    }
+
+    /// Determinate if the cell at given position blocks the light
+    /// i.e if one of its tiles (or objects) has the lighting blocking property
+    fn is_blocking(&self, position: Vector2u) -> bool {
+        let lighting = match &self.lighting {
+            Some(lighting) => lighting,
+            None => return false,
+        };
+
+        (0..self.tiles.len()).any(|layer| {
+            self.tileset
+                .has_property(self.cell_tile(layer, position), &lighting.blocking_property)
+        })
+    }
+
+    /// Build the light of the chunk at given position (in chunks): the ambient light plus the
+    /// light sources one, the light of each corner is the average of the cells around it
+    fn build_light_chunk(&self, chunk: Vector2u) -> Quads {
+        let mut light = Quads::new();
+        if self.lighting.is_none() {
+            return light;
+        }
+
+        let ambient = self.ambient;
+        let cell_light = |x: u32, y: u32| {
+            let source = self.light_map[(x + y * self.map_size.x) as usize];
+            let channel =
+                |ambient: u8, source: f32| ambient as f32 + source.clamp(0.0, 255.0).round();
+            [
+                channel(ambient.r, source[0]),
+                channel(ambient.g, source[1]),
+                channel(ambient.b, source[2]),
+            ]
+        };
+        let corner_light = |x: u32, y: u32| {
+            let light = corner_average(self.map_size, x, y, cell_light);
+            let channel = |value: f32| value.clamp(0.0, 255.0).round() as u8;
+            Color::rgb(channel(light[0]), channel(light[1]), channel(light[2]))
+        };
+
+        let (start, end) = self.chunk_cells(chunk);
+        for y in start.y..end.y {
+            for x in start.x..end.x {
+                let bounds = self.tile_bounds((x, y));
+                let (right, bottom) = (bounds.left + bounds.width, bounds.top + bounds.height);
+                for (position, color) in [
+                    ((bounds.left, bounds.top), corner_light(x, y)),
+                    ((right, bounds.top), corner_light(x + 1, y)),
+                    ((right, bottom), corner_light(x + 1, y + 1)),
+                    ((bounds.left, bottom), corner_light(x, y + 1)),
+                ] {
+                    light.append(&Vertex::with_pos_color(position, color));
+                }
+            }
+        }
+
+        light
+    }
+
+    /// Build the fog of war of the chunk at given position (in chunks),
+    /// hiding the unseen cells and darkening the explored ones
+    /// with soft edges, the fog opacity of each corner is the average of the cells around it
+    fn build_fog_chunk(&self, chunk: Vector2u) -> Quads {
+        let mut fog = Quads::new();
+        let visibility = match &self.visibility {
+            Some(visibility) => visibility,
+            None => return fog,
+        };
+
+        let settings = &self.fog_settings;
+        let opacity = |x: u32, y: u32| match visibility.visibility((x, y)) {
+            Some(Visibility::Unseen) => [1.0],
+            Some(Visibility::Explored) => [settings.explored_opacity.clamp(0.0, 1.0)],
+            Some(Visibility::Visible) | None => [0.0],
+        };
+        let corner_opacity = |x: u32, y: u32| corner_average(self.map_size, x, y, opacity)[0];
+
+        let (start, end) = self.chunk_cells(chunk);
+        for y in start.y..end.y {
+            for x in start.x..end.x {
+                let corners = if settings.soft_edges {
+                    [
+                        corner_opacity(x, y),
+                        corner_opacity(x + 1, y),
+                        corner_opacity(x + 1, y + 1),
+                        corner_opacity(x, y + 1),
+                    ]
+                } else {
+                    [opacity(x, y)[0]; 4]
+                };
+                if corners.iter().all(|opacity| *opacity <= 0.0) {
+                    continue;
+                }
+
+                let bounds = self.tile_bounds((x, y));
+                let (right, bottom) = (bounds.left + bounds.width, bounds.top + bounds.height);
+                for (position, opacity) in [
+                    (bounds.left, bounds.top),
+                    (right, bounds.top),
+                    (right, bottom),
+                    (bounds.left, bottom),
+                ]
+                .iter()
+                .zip(corners)
+                {
+                    let alpha = settings.color.a as f32 * opacity;
+                    let color = Color::rgba(
+                        settings.color.r,
+                        settings.color.g,
+                        settings.color.b,
+                        alpha.round() as u8,
+                    );
+                    fog.append(&Vertex::with_pos_color(*position, color));
+                }
+            }
+        }
+
+        fog
+    }
+
+    /// Build the debug overlays of the chunk at given position (in chunks):
+    /// layers / property colors, chunks boundaries and tile ids
+    fn build_debug_chunk(&self, chunk: Vector2u) -> Quads {
+        let settings = &self.debug_settings;
+        let mut debug = Quads::new();
+        let (start, end) = self.chunk_cells(chunk);
+
+        let pixel_size = (self.tile_size.y / 4.0 / GLYPH_HEIGHT as f32).max(1.0);
+        for y in start.y..end.y {
+            for x in start.x..end.x {
+                let position = Vector2u::new(x, y);
+                let bounds = self.tile_bounds(position);
+                let tiles = self.cell_tiles(position);
+
+                if settings.show_layers && !settings.layer_colors.is_empty() {
+                    if let Some(layer) = tiles.iter().rposition(|tile_id| *tile_id != 0) {
+                        let color = settings.layer_colors[layer % settings.layer_colors.len()];
+                        append_rect(&mut debug, bounds, color);
+                    }
+                }
+
+                if let Some(property) = &settings.property {
+                    if tiles
+                        .iter()
+                        .any(|tile_id| self.tileset.has_property(*tile_id, property))
+                    {
+                        append_rect(&mut debug, bounds, settings.property_color);
+                    }
+                }
+
+                if settings.show_tile_ids {
+                    // One line per occupied layer, suffixed by the flip flags
+                    let text: Vec<String> = tiles
+                        .iter()
+                        .filter(|tile_id| **tile_id != 0)
+                        .map(|tile_id| {
+                            let mut text = (tile_id & TILE_ID_MASK).to_string();
+                            if tile_id & FLIP_HORIZONTAL != 0 {
+                                text.push('h');
+                            }
+                            if tile_id & FLIP_VERTICAL != 0 {
+                                text.push('v');
+                            }
+                            text
+                        })
+                        .collect();
+                    append_text(
+                        &mut debug,
+                        &text.join("\n"),
+                        Vector2f::new(bounds.left + pixel_size, bounds.top + pixel_size),
+                        pixel_size,
+                        settings.text_color,
+                    );
+                }
+            }
+        }
+
+        // Each chunk holds its top & left boundaries, and its bottom & right ones if they are
+        // both a chunk boundary and a map border
+        if settings.show_chunks {
+            let thickness = settings.chunk_thickness;
+            let bounds = FloatRect::new(
+                start.x as f32 * self.tile_size.x,
+                start.y as f32 * self.tile_size.y,
+                (end.x - start.x) as f32 * self.tile_size.x,
+                (end.y - start.y) as f32 * self.tile_size.y,
+            );
+            let is_border = |end: u32, size: u32| end == size && size.is_multiple_of(CHUNK_SIZE);
+
+            let mut rows = vec![bounds.top];
+            if is_border(end.y, self.map_size.y) {
+                rows.push(bounds.top + bounds.height);
+            }
+            for top in rows {
+                append_rect(
+                    &mut debug,
+                    FloatRect::new(bounds.left, top - thickness / 2.0, bounds.width, thickness),
+                    settings.chunk_color,
+                );
+            }
+            let mut columns = vec![bounds.left];
+            if is_border(end.x, self.map_size.x) {
+                columns.push(bounds.left + bounds.width);
+            }
+            for left in columns {
+                append_rect(
+                    &mut debug,
+                    FloatRect::new(left - thickness / 2.0, bounds.top, thickness, bounds.height),
+                    settings.chunk_color,
+                );
+            }
+        }
+
+        debug
+    }
+
+    /// Retrieve the tile of each layer at given position, 0 if the cell is empty
+    /// the objects covering the cell are considered as its tile
+    fn cell_tiles(&self, position: Vector2u) -> Vec<u32> {
+        (0..self.tiles.len())
+            .map(|layer| self.cell_tile(layer, position))
+            .collect()
+    }
+
+    /// Retrieve the tile of given layer at given position, 0 if the cell is empty
+    /// the object covering the cell is considered as its tile
+    fn cell_tile(&self, layer: usize, position: Vector2u) -> u32 {
+        let tile_id = self.tiles[layer][(position.x + position.y * self.map_size.x) as usize];
+        if tile_id & TILE_ID_MASK != 0 {
+            return tile_id;
+        }
+        self.objects[layer]
+            .iter()
+            .find(|object| object.contains(position))
+            .map(|object| object.tile_id)
+            .unwrap_or(0)
+    }
+
+    /// Retrieve the position of the cell at given index (in row major order)
+    fn cell_position(&self, index: usize) -> Vector2u {
+        Vector2u::new(
+            index as u32 % self.map_size.x,
+            index as u32 / self.map_size.x,
+        )
+    }
+
+    /// Retrieve the cells covered by the chunk at given position (in chunks)
+    /// as its first cell and the cell after its last one
+    fn chunk_cells(&self, chunk: Vector2u) -> (Vector2u, Vector2u) {
+        let start = chunk * CHUNK_SIZE;
+        let end = Vector2u::new(
+            (start.x + CHUNK_SIZE).min(self.map_size.x),
+            (start.y + CHUNK_SIZE).min(self.map_size.y),
+        );
+        (start, end)
+    }
+
+    /// Build the objects of each layer, in drawing order
+    /// the objects whose tile is missing are not displayed
+    fn build_objects(&self) -> Vec<Quads> {
+        self.objects
+            .iter()
+            .zip(&self.layer_settings)
+            .map(|(objects, settings)| {
+                let mut quads = Quads::with_vertex_count(objects.len() * 4);
+                for (index, object) in objects.iter().enumerate() {
+                    let tile_id =
+                        displayed_tile(self.tileset, self.time, object.position, object.tile_id);
+                    let display = tile_display(
+                        self.tileset,
+                        MissingTilePolicy::Skip,
+                        self.tile_scale,
+                        tile_id,
+                    );
+                    set_quad(
+                        &mut quads,
+                        index,
+                        object.position,
+                        self.tile_size,
+                        settings.color(),
+                        display,
+                    );
+                }
+                quads
+            })
+            .collect()
+    }
+
+    /// Find the cells displaying an animated tile, with their current frame
+    fn find_animated_cells(&self) -> BTreeMap<(u32, usize), u32> {
+        let mut animated_cells = BTreeMap::new();
+
+        for (layer, tiles) in self.tiles.iter().enumerate() {
+            for (index, tile_id) in tiles.iter().enumerate() {
+                if self.tileset.animation(*tile_id).is_some() {
+                    let position = Vector2u::new(
+                        index as u32 % self.map_size.x,
+                        index as u32 / self.map_size.x,
+                    );
+                    animated_cells.insert(
+                        (layer as u32, index),
+                        displayed_tile(self.tileset, self.time, position, *tile_id),
+                    );
+                }
+            }
+        }
+
+        animated_cells
+    }
+
+    /// Returns true if given tile is air or present in the tileset
+    fn is_known_tile(&self, tile_id: u32) -> bool {
+        tile_id & TILE_ID_MASK == 0
+            || self.tileset.tile_rect(tile_id).is_some()
+            || self.tileset.animation(tile_id).is_some()
+    }
+
+    /// Count the occurrences of the tile map tiles that are not present in the tileset
+    fn count_unknown_tiles(&self, tile_map: &TileMap) -> BTreeMap<u32, usize> {
+        let size = tile_map.size();
+        let mut unknown_tiles = BTreeMap::new();
+
+        for layer in 0..tile_map.layer_count() {
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    let tile_id = tile_map.get_tile((x, y), layer).unwrap();
+                    if !self.is_known_tile(tile_id) {
+                        *unknown_tiles.entry(tile_id & TILE_ID_MASK).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        for object in tile_map.objects() {
+            if !self.is_known_tile(object.tile_id) {
+                *unknown_tiles
+                    .entry(object.tile_id & TILE_ID_MASK)
+                    .or_insert(0) += 1;
+            }
+        }
+
+        unknown_tiles
+    }
+
+    /// Register a new occurrence of given unknown tile
+    fn add_unknown_tile(&mut self, tile_id: u32) {
+        *self
+            .unknown_tiles
+            .entry(tile_id & TILE_ID_MASK)
+            .or_insert(0) += 1;
+    }
+
+    /// Forget an occurrence of given unknown tile, the tile is no longer reported once
+    /// its last occurrence is removed
+    fn remove_unknown_tile(&mut self, tile_id: u32) {
+        let tile_id = tile_id & TILE_ID_MASK;
+        if let Some(count) = self.unknown_tiles.get_mut(&tile_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.unknown_tiles.remove(&tile_id);
+            }
+        }
+    }
+
+    /// Compute the ratio between the renderer view size and the default view size
+    fn view_scale(&self) -> Vector2f {
+        let size = self.view.size();
+        Vector2f::new(
+            size.x / self.original_view_size.x,
+            size.y / self.original_view_size.y,
+        )
+    }
+
+    /// Compute the offset (in world units) of given layer, due to its parallax & scrolling
+    fn layer_offset(&self, settings: &LayerSettings) -> Vector2f {
+        let moved = self.view.center() - self.parallax_origin;
+        Vector2f::new(
+            moved.x * (1.0 - settings.parallax.x)
+                + settings.scroll_speed.x * self.tile_size.x * self.time,
+            moved.y * (1.0 - settings.parallax.y)
+                + settings.scroll_speed.y * self.tile_size.y * self.time,
+        )
+    }
+
+    /// Compute the offset of the edited layer, see layer_offset
+    fn edited_layer_offset(&self) -> Vector2f {
+        self.layer_settings
+            .get(self.edited_layer as usize)
+            .map(|settings| self.layer_offset(settings))
+            .unwrap_or_default()
+    }
+
+    /// Compute the chunks of given layer visible through the renderer view
+    /// each entry is the translation to apply to the chunks and the range of visible chunks
+    /// (on x and y axis), repeated layers may be visible multiple times
+    fn visible_chunks(&self, layer: usize) -> Vec<(Vector2f, Range<u32>, Range<u32>)> {
+        // Tiles bigger than their cell may overflow on the neighbours chunks
+        let (before, after) = self.tileset.max_overflow();
+        let before = Vector2f::new(before.x as f32, before.y as f32) * self.tile_scale;
+        let after = Vector2f::new(after.x as f32, after.y as f32) * self.tile_scale;
+
+        self.chunks_in_view(&self.layer_settings[layer], before, after)
+    }
+
+    /// Compute the range of chunks visible through the renderer view (on x and y axis)
+    /// for the geometry covering the map cells without layer offset (i.e the grid)
+    fn visible_map_chunks(&self) -> (Range<u32>, Range<u32>) {
+        let origin = Vector2f::default();
+        let (_, x_range, y_range) = self
+            .chunks_in_view(&LayerSettings::default(), origin, origin)
+            .remove(0);
+        (x_range, y_range)
+    }
+
+    /// Compute the chunks of a layer using given settings visible through the renderer view,
+    /// see visible_chunks, before & after are how far the chunks geometry may overflow
+    /// on the previous / next chunks (in world units)
+    fn chunks_in_view(
+        &self,
+        settings: &LayerSettings,
+        before: Vector2f,
+        after: Vector2f,
+    ) -> Vec<(Vector2f, Range<u32>, Range<u32>)> {
+        let chunk_size = self.tile_size * CHUNK_SIZE as f32;
+        let center = self.view.center() - self.layer_offset(settings);
+
+        // Use the bounding box of the (maybe rotated) view
+        let (sin, cos) = self.view.rotation().to_radians().sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        let view_size = self.view.size();
+        let size = Vector2f::new(
+            view_size.x * cos + view_size.y * sin,
+            view_size.x * sin + view_size.y * cos,
+        );
+        let min = center - size / 2.0 - after;
+        let max = center + size / 2.0 + before;
+
+        // Repeated layers are displayed once per tile map size
+        let map_size = Vector2f::new(
+            self.map_size.x as f32 * self.tile_size.x,
+            self.map_size.y as f32 * self.tile_size.y,
+        );
+        let copies = |repeat: bool, min: f32, max: f32, size: f32| {
+            if repeat && size > 0.0 {
+                (min / size).floor() as i32..(max / size).floor() as i32 + 1
+            } else {
+                0..1
+            }
+        };
+        let range = |min: f32, max: f32, chunk_size: f32, count: u32| {
+            let start = (min / chunk_size).floor().max(0.0) as u32;
+            let end = ((max / chunk_size).ceil().max(0.0) as u32).min(count);
+            start.min(end)..end
+        };
+
+        let mut chunks = Vec::new();
+        for copy_y in copies(settings.repeat_y, min.y, max.y, map_size.y) {
+            for copy_x in copies(settings.repeat_x, min.x, max.x, map_size.x) {
+                let shift = Vector2f::new(copy_x as f32 * map_size.x, copy_y as f32 * map_size.y);
+                chunks.push((
+                    shift,
+                    range(
+                        min.x - shift.x,
+                        max.x - shift.x,
+                        chunk_size.x,
+                        self.chunk_count.x,
+                    ),
+                    range(
+                        min.y - shift.y,
+                        max.y - shift.y,
+                        chunk_size.y,
+                        self.chunk_count.y,
+                    ),
+                ));
+            }
+        }
+
+        chunks
+    }
+
+    /// Build the geometry of the visible chunks that are not built yet
+    fn build_visible_chunks(&mut self) {
+        let visible_chunks: Vec<_> = (0..self.layers.len())
+            .map(|layer| self.visible_chunks(layer))
+            .collect();
+
+        let tileset = self.tileset;
+        let policy = self.missing_tile_policy;
+        let scale = self.tile_scale;
+        let time = self.time;
+        for ((chunks, tiles), visible_chunks) in
+            self.layers.iter_mut().zip(&self.tiles).zip(visible_chunks)
+        {
+            for (_, x_range, y_range) in visible_chunks {
+                for y in y_range {
+                    for x in x_range.clone() {
+                        let chunk = &mut chunks[(x + y * self.chunk_count.x) as usize];
+                        if !chunk.is_built() {
+                            chunk.build(tiles, self.map_size.x, self.tile_size, |position, id| {
+                                let id = displayed_tile(tileset, time, position, id);
+                                tile_display(tileset, policy, scale, id)
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        let (x_range, y_range) = self.visible_map_chunks();
+        for y in y_range {
+            for x in x_range.clone() {
+                let index = (x + y * self.chunk_count.x) as usize;
+                if self.show_grid && self.grid[index].is_none() {
+                    self.grid[index] = Some(self.build_grid_chunk(Vector2u::new(x, y)));
+                }
+                if self.lighting.is_some() && self.light[index].is_none() {
+                    self.light[index] = Some(self.build_light_chunk(Vector2u::new(x, y)));
+                }
+                if self.visibility.is_some() && self.fog[index].is_none() {
+                    self.fog[index] = Some(self.build_fog_chunk(Vector2u::new(x, y)));
+                }
+                if self.debug_settings.is_enabled() && self.debug[index].is_none() {
+                    self.debug[index] = Some(self.build_debug_chunk(Vector2u::new(x, y)));
+                }
+            }
+        }
+    }
+}
+
+/// Average the values of the cells sharing the corner at given position (the top left corner of
+/// the cell at that position), the cells outside the map are ignored
+/// i.e to fade the fog & light between neighbours cells
+fn corner_average<const N: usize>(
+    map_size: Vector2u,
+    x: u32,
+    y: u32,
+    cell_value: impl Fn(u32, u32) -> [f32; N],
+) -> [f32; N] {
+    let mut sum = [0.0; N];
+    let mut count = 0;
+    for (x, y) in [
+        (x.wrapping_sub(1), y.wrapping_sub(1)),
+        (x, y.wrapping_sub(1)),
+        (x.wrapping_sub(1), y),
+        (x, y),
+    ] {
+        if x < map_size.x && y < map_size.y {
+            for (sum, value) in sum.iter_mut().zip(cell_value(x, y)) {
+                *sum += value;
+            }
+            count += 1;
+        }
+    }
+
+    sum.map(|sum| sum / count.max(1) as f32)
+}
+
+/// Retrieve the cell an object emits light from: the center of its footprint
+fn object_center(object: &MapObject) -> Vector2u {
+    object.position + object.footprint / 2
+}
+
+/// Retrieve the index of the cell containing given coordinate (expressed in tiles)
+/// a coordinate lying on a cell border (within CELL_EPSILON) belongs to the following cell
+fn cell_index(coordinate: f32) -> f32 {
+    let border = coordinate.round();
+    if (coordinate - border).abs() < CELL_EPSILON {
+        border
+    } else {
+        coordinate.floor()
+    }
+}
+
+/// Retrieve the objects of each layer of given tile map, in drawing order
+fn sorted_objects(tile_map: &TileMap) -> Vec<Vec<MapObject>> {
+    (0..tile_map.layer_count())
+        .map(|layer| tile_map.sorted_objects(layer))
+        .collect()
+}
+
+/// Compute the ratio between world units and tileset pixels
+/// so the expected viewport size fits on given screen size
+fn fit_tile_scale(cell_size: Vector2u, screen_size: Vector2u, viewport_size: Vector2u) -> f32 {
+    (screen_size.x as f32 / (viewport_size.x * cell_size.x) as f32)
+        .min(screen_size.y as f32 / (viewport_size.y * cell_size.y) as f32)
+}
+
+/// Retrieve the tile displayed by the cell at given position at given time
+/// this will be the current animation frame for animated tiles (keeping the flip flags),
+/// or the tile itself
+pub(crate) fn displayed_tile<A>(
+    tileset: &Tileset<A>,
+    time: f32,
+    position: Vector2u,
+    tile_id: u32,
+) -> u32 {
+    tileset
+        .animation(tile_id)
+        .and_then(|animation| animation.tile_at(time, position))
+        .map(|frame| frame | (tile_id & !TILE_ID_MASK))
+        .unwrap_or(tile_id)
+}
+
+/// Determinate how given tile should be displayed
+/// the tile is scaled using given scale factor, tile_id == 0 is transparent
+fn tile_display<A>(
+    tileset: &Tileset<A>,
+    policy: MissingTilePolicy,
+    scale: f32,
+    tile_id: u32,
+) -> TileDisplay {
+    if tile_id & TILE_ID_MASK == 0 {
+        return TileDisplay::Empty;
+    }
+
+    match tileset.tile(tile_id) {
+        Some(tile) => TileDisplay::Texture {
+            flip_x: tile_id & FLIP_HORIZONTAL != 0,
+            flip_y: tile_id & FLIP_VERTICAL != 0,
+            rect: tile.rect,
+            offset: Vector2f::new(-tile.anchor.x as f32 * scale, -tile.anchor.y as f32 * scale),
+            size: Vector2f::new(
+                tile.rect.width as f32 * scale,
+                tile.rect.height as f32 * scale,
+            ),
+        },
+        None if policy == MissingTilePolicy::Placeholder => TileDisplay::Placeholder,
+        None => TileDisplay::Empty,
+    }
+}
+
+impl<'s, A: Deref<Target = Texture>> Drawable for TileMapRenderer<'s, A> {
+    fn draw<'a: 'shader, 'texture, 'shader, 'shader_texture>(
+        &'a self,
+        target: &mut dyn RenderTarget,
+        states: RenderStates<'texture, 'shader, 'shader_texture>,
+    ) {
+        // Restore the target view once drawn (i.e the view matching the window size)
+        let view = target.view().to_owned();
+        self.render(&mut SfmlBackend::new(target, states, self.tileset.atlas()));
+        target.set_view(&view);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::graphics::{Color, FloatRect, IntRect, Texture, View};
+    use sfml::system::{Vector2f, Vector2u};
+
+    use crate::tilemap::renderer::cell_index;
+    use crate::tilemap::test_utils::{blank_tileset, full_hd_renderer, CountingBackend};
+    use crate::tilemap::{
+        Animation, AnimationFrame, AnimationMode, AnimationPhase, DayNightCycle, DebugSettings,
+        FogSettings, GridSettings, LayerBlendMode, LayerSettings, LightSource, Lighting, MapObject,
+        MissingTilePolicy, OverlaySettings, RendererError, TileMap, TileMapChange, TileMapRenderer,
+        Tileset, Visibility, VisibilityGrid, FLIP_HORIZONTAL, FLIP_VERTICAL,
+    };
+
+    #[test]
+    fn test_tile_map_renderer_new() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(renderer.layers.len(), 1);
+        assert_eq!(renderer.layers.first().unwrap().len(), 1);
+        assert_eq!(renderer.tiles.first().unwrap().len(), 25);
+        assert_eq!(renderer.chunk_count, (1, 1).into());
+        assert_eq!(renderer.tile_size, (216.0, 216.0).into()); // We want a 5x5 viewport, therefore size will be 1080/5
+        assert_eq!(renderer.map_size, (5, 5).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_move() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(renderer.view.size(), (10.0, 10.0).into());
+        assert_eq!(renderer.view.center(), (0.0, 0.0).into());
+
+        renderer.move_(Vector2f::new(10.0, 0.0));
+
+        assert_eq!(renderer.view.center(), (10.0, 0.0).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_center() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        renderer.set_center((100.0, 50.0));
+        assert_eq!(renderer.center(), (100.0, 50.0).into());
+        assert_eq!(renderer.view_size(), (10.0, 10.0).into());
+
+        renderer.set_zoom(2.0);
+        assert_eq!(renderer.view_size(), (5.0, 5.0).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_zoom() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(renderer.zoom_level(), 1.0);
+
+        renderer.zoom(2.0);
+        assert_eq!(renderer.zoom_level(), 2.0);
+        assert_eq!(renderer.view.size(), (5.0, 5.0).into());
+
+        // Geometry stays in world units
+        assert_eq!(renderer.tile_size(), (216.0, 216.0).into());
+        assert_eq!(
+            renderer.get_tile_position((432.0, 0.0)),
+            Some((1, 0).into())
+        );
+
+        // Zoom level is applied as is (the camera clamps it)
+        renderer.zoom(100.0);
+        assert_eq!(renderer.zoom_level(), 200.0);
+        renderer.set_zoom(0.5);
+        assert_eq!(renderer.view.size(), (20.0, 20.0).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_zoom_at() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(
+            renderer.get_tile_position((440.0, 10.0)),
+            Some((2, 0).into())
+        );
+
+        // The position under the cursor does not change
+        renderer.zoom_at(2.0, (440.0, 10.0));
+        assert_eq!(renderer.view.center(), (220.0, 5.0).into());
+        assert_eq!(
+            renderer.get_tile_position((440.0, 10.0)),
+            Some((2, 0).into())
+        );
+        assert_eq!(
+            renderer.get_tile_position((440.0 + 216.0, 10.0)),
+            Some((2, 0).into())
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_rotation() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        renderer.set_rotation(90.0);
+        assert_eq!(renderer.rotation(), 90.0);
+
+        // Moving right on the screen means moving down on the map
+        assert_eq!(
+            renderer.get_tile_position((442.0, 0.0)),
+            Some((0, 2).into())
+        );
+
+        renderer.rotate(-90.0);
+        assert_eq!(renderer.rotation(), 0.0);
+    }
+
+    #[test]
+    fn test_cell_index() {
+        assert_eq!(cell_index(1.5), 1.0);
+        assert_eq!(cell_index(-0.5), -1.0);
+
+        // Rounding errors on a cell border are absorbed
+        assert_eq!(cell_index(-1e-5), 0.0);
+        assert_eq!(cell_index(2.0 - 1e-5), 2.0);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_get_tile_position() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(renderer.get_tile_position((0.0, 0.0)), Some((0, 0).into()));
+        assert_eq!(
+            renderer.get_tile_position((210.0, 120.0)),
+            Some((0, 0).into())
+        );
+        assert_eq!(
+            renderer.get_tile_position((420.0, 210.0)),
+            Some((1, 0).into())
+        );
+        assert_eq!(renderer.get_tile_position((-420.0, 210.0)), None);
+        assert_eq!(renderer.get_tile_position((-1.0, 0.0)), None);
+        assert_eq!(renderer.get_tile_position((0.0, -1.0)), None);
+        assert_eq!(renderer.get_tile_position((12420.0, 210.0)), None);
+
+        // Move the map and check if we are retrieving the 'real' tile position
+        renderer.move_(Vector2f::new(400.0, 200.0));
+        assert_eq!(
+            renderer.get_tile_position((420.0, 210.0)),
+            Some((3, 1).into())
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_tile_to_world() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(renderer.tile_to_world((0, 0)), (0.0, 0.0).into());
+        assert_eq!(renderer.tile_to_world((2, 3)), (432.0, 648.0).into());
+        assert_eq!(
+            renderer.tile_bounds((2, 3)),
+            FloatRect::new(432.0, 648.0, 216.0, 216.0)
+        );
+
+        // World position does not depend on the view
+        renderer.move_(Vector2f::new(100.0, 50.0));
+        renderer.zoom(2.0);
+        assert_eq!(renderer.tile_to_world((2, 3)), (432.0, 648.0).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_world_bounds() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 3), 1, 2);
+        let renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(
+            renderer.world_bounds(),
+            FloatRect::new(0.0, 0.0, 5.0 * 216.0, 3.0 * 216.0)
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_screen_to_world() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(renderer.screen_to_world((10.0, 20.0)), (10.0, 20.0).into());
+
+        renderer.move_(Vector2f::new(100.0, 50.0));
+        renderer.zoom(2.0);
+        assert_eq!(renderer.screen_to_world((10.0, 20.0)), (105.0, 60.0).into());
+        assert_eq!(renderer.world_to_screen((105.0, 60.0)), (10.0, 20.0).into());
+
+        // Screen position of a tile
+        let position = renderer.world_to_screen(renderer.tile_to_world((1, 1)));
+        assert_eq!(position, (232.0, 332.0).into());
+        assert_eq!(renderer.get_tile_position(position), Some((1, 1).into()));
+    }
+
+    #[test]
+    fn test_tile_map_renderer_update() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // Update renderer
+        let tile_map = TileMap::new((10, 10), 1, 2);
+        assert!(renderer.update(&tile_map, (1920, 1080), (10, 10)).is_ok());
+
+        assert_eq!(renderer.layers.len(), 1);
+        assert_eq!(renderer.layers.first().unwrap().len(), 1);
+        assert_eq!(renderer.tiles.first().unwrap().len(), 100);
+        assert_eq!(renderer.tile_size, (108.0, 108.0).into()); // We want a 10x10 viewport, therefore size will be 1080/10
+        assert_eq!(renderer.map_size, (10, 10).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_resize() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((10, 10), 1, 2);
+        let mut renderer = TileMapRenderer::new(
+            &tile_map,
+            (1920, 1080),
+            (5, 5),
+            View::new((960.0, 540.0).into(), (1920.0, 1080.0).into()),
+            &tileset,
+            true,
+        );
+        renderer.set_center((432.0, 216.0));
+        renderer.set_zoom(2.0);
+        renderer.set_rotation(90.0);
+        assert_eq!(renderer.tile_size, (216.0, 216.0).into());
+
+        let quads = renderer.layers[0][0].quads().cloned();
+
+        renderer.resize(
+            (960, 540),
+            (5, 5),
+            View::new((480.0, 270.0).into(), (960.0, 540.0).into()),
+        );
+
+        // The tiles keep their size, the view shows the same area of the tile map
+        assert_eq!(renderer.tile_size, (216.0, 216.0).into());
+        assert_eq!(renderer.layers[0][0].quads().cloned(), quads);
+        assert_eq!(renderer.center(), (432.0, 216.0).into());
+        assert_eq!(renderer.view_size(), (960.0, 540.0).into());
+        assert_eq!(renderer.zoom_level(), 2.0);
+        assert_eq!(renderer.rotation(), 90.0);
+        assert_eq!(
+            renderer.world_bounds(),
+            FloatRect::new(0.0, 0.0, 2160.0, 2160.0)
+        );
+        assert_eq!(
+            renderer.world_to_screen((432.0, 216.0)),
+            (480.0, 270.0).into()
+        );
+        assert_eq!(
+            renderer.screen_to_world((480.0, 270.0)),
+            (432.0, 216.0).into()
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_tile() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 2, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // Air tile is transparent
+        let quads = renderer.layers[1][0].quads().unwrap();
+        assert_eq!(quads[(1 + 5) * 4].color, Color::TRANSPARENT);
+
+        assert!(renderer.set_tile((1, 1), 1, 2).is_ok());
+
+        // Only the quad of the tile is updated
+        let quads = renderer.layers[1][0].quads().unwrap();
+        for i in 0..4 {
+            assert_eq!(quads[(1 + 5) * 4 + i].color, Color::WHITE);
+        }
+        assert_eq!(quads[(1 + 5) * 4].position, (216.0, 216.0).into());
+        assert_eq!(quads[(1 + 5) * 4 + 2].tex_coords, (16.0, 16.0).into());
+        assert_eq!(quads[0].color, Color::TRANSPARENT);
+        assert_eq!(renderer.tiles[1][1 + 5], 2);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_flip() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((5, 5), 1, 2 | FLIP_HORIZONTAL);
+        tile_map.set_tile((1, 0), 0, 42 | FLIP_VERTICAL).unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // Flipped unknown tiles are reported without their flags
+        assert_eq!(
+            renderer
+                .unknown_tiles()
+                .iter()
+                .copied()
+                .collect::<Vec<u32>>(),
+            vec![42]
+        );
+
+        let quads = renderer.layers[0][0].quads().unwrap();
+        assert_eq!(quads[0].tex_coords, (16.0, 0.0).into());
+        assert_eq!(quads[2].tex_coords, (0.0, 16.0).into());
+
+        assert!(renderer.set_tile((0, 0), 0, 2 | FLIP_VERTICAL).is_ok());
+        let quads = renderer.layers[0][0].quads().unwrap();
+        assert_eq!(quads[0].tex_coords, (0.0, 16.0).into());
+        assert_eq!(quads[2].tex_coords, (16.0, 0.0).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_visible_chunks() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((40, 40), 2, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // 40x40 map is split into 3x3 chunks of 16*216 pixels
+        assert_eq!(renderer.chunk_count, (3, 3).into());
+        assert_eq!(
+            renderer.visible_chunks(0),
+            vec![(Vector2f::new(0.0, 0.0), 0..1, 0..1)]
+        );
+
+        // Only the visible chunk is built
+        for layer in &renderer.layers {
+            assert!(layer[0].is_built());
+            for chunk in &layer[1..] {
+                assert!(!chunk.is_built());
+            }
+        }
+
+        // Move the view on the middle chunk
+        renderer.move_(Vector2f::new(16.0 * 216.0 + 100.0, 16.0 * 216.0 + 100.0));
+        assert_eq!(
+            renderer.visible_chunks(1),
+            vec![(Vector2f::new(0.0, 0.0), 1..2, 1..2)]
+        );
+        assert!(renderer.layers[0][4].is_built());
+        assert!(renderer.layers[1][4].is_built());
+        assert!(!renderer.layers[0][8].is_built());
+
+        // Move the view outside the map
+        renderer.move_(Vector2f::new(-100000.0, 0.0));
+        assert_eq!(
+            renderer.visible_chunks(0),
+            vec![(Vector2f::new(0.0, 0.0), 0..0, 1..2)]
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_parallax() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((40, 40), 3, 2);
+        tile_map
+            .set_layer_settings(
+                1,
+                LayerSettings {
+                    parallax: Vector2f::new(0.0, 0.0),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        tile_map
+            .set_layer_settings(
+                2,
+                LayerSettings {
+                    parallax: Vector2f::new(0.5, 1.0),
+                    scroll_speed: Vector2f::new(1.0, 0.0),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        renderer.move_(Vector2f::new(16.0 * 216.0 + 100.0, 16.0 * 216.0 + 100.0));
+
+        // The regular layer moves with the view, the fixed one stays on screen
+        let settings = *renderer.layer_settings(1).unwrap();
+        assert_eq!(
+            renderer.layer_offset(&settings),
+            Vector2f::new(16.0 * 216.0 + 100.0, 16.0 * 216.0 + 100.0)
+        );
+        assert_eq!(
+            renderer.visible_chunks(0),
+            vec![(Vector2f::new(0.0, 0.0), 1..2, 1..2)]
+        );
+        assert_eq!(
+            renderer.visible_chunks(1),
+            vec![(Vector2f::new(0.0, 0.0), 0..1, 0..1)]
+        );
+        assert!(renderer.layers[1][0].is_built());
+        assert!(!renderer.layers[1][4].is_built());
+
+        // Half speed horizontally, plus automatic scrolling
+        let settings = *renderer.layer_settings(2).unwrap();
+        assert_eq!(
+            renderer.layer_offset(&settings),
+            Vector2f::new(8.0 * 216.0 + 50.0, 0.0)
+        );
+        renderer.advance(2.0);
+        assert_eq!(
+            renderer.layer_offset(&settings),
+            Vector2f::new(8.0 * 216.0 + 50.0 + 2.0 * 216.0, 0.0)
+        );
+    }
 
     #[test]
-    fn test_tile_map_renderer_get_tile_position() {
-        let textures = load_textures();
-        let tile_map = TileMap::new((5, 5), 1, 2);
-        let mut renderer = TileMapRenderer::new(
-            &tile_map,
-            (1920, 1080),
-            (5, 5),
-            View::new((0.0, 0.0).into(), (10.0, 10.0).into()),
-            &textures,
-            true,
+    fn test_tile_map_renderer_edited_layer() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((40, 40), 2, 2);
+        tile_map
+            .set_layer_settings(
+                1,
+                LayerSettings {
+                    parallax: Vector2f::new(0.5, 1.0),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        renderer.move_(Vector2f::new(4.0 * 216.0, 0.0));
+
+        // The tiles are picked on the layer 0 by default
+        assert_eq!(renderer.edited_layer(), 0);
+        assert_eq!(renderer.edited_layer_offset(), Vector2f::default());
+        assert_eq!(
+            renderer.get_tile_position((100.0, 100.0)),
+            Some((4, 0).into())
         );
 
-        assert_eq!(renderer.get_tile_position((0.0, 0.0)), Some((0, 0).into()));
+        // The layer 1 moved half the way of the view
+        assert!(renderer.set_edited_layer(1).is_ok());
         assert_eq!(
-            renderer.get_tile_position((210.0, 120.0)),
-            Some((0, 0).into())
+            renderer.edited_layer_offset(),
+            Vector2f::new(2.0 * 216.0, 0.0)
         );
         assert_eq!(
-            renderer.get_tile_position((420.0, 210.0)),
-            Some((1, 0).into())
+            renderer.get_tile_position((100.0, 100.0)),
+            Some((2, 0).into())
         );
+        assert_eq!(renderer.get_tile_position((-500.0, 100.0)), None);
+
         assert_eq!(
-            renderer.get_tile_position((-420.0, 210.0)),
-            Some((0, 0).into())
+            renderer.set_edited_layer(2),
+            Err(RendererError::InvalidLayer)
         );
-        assert_eq!(renderer.get_tile_position((12420.0, 210.0)), None);
+        assert_eq!(renderer.edited_layer(), 1);
+    }
 
-        // Move the map and check if we are retrieving the 'real' tile position
-        renderer.move_(Vector2f::new(400.0, 200.0));
+    #[test]
+    fn test_tile_map_renderer_repeat() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((40, 40), 1, 2);
+        tile_map
+            .set_layer_settings(
+                0,
+                LayerSettings {
+                    repeat_x: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // The view overlaps the end of the previous copy and the start of the map
         assert_eq!(
-            renderer.get_tile_position((420.0, 210.0)),
-            Some((3, 1).into())
+            renderer.visible_chunks(0),
+            vec![
+                (Vector2f::new(-40.0 * 216.0, 0.0), 2..3, 0..1),
+                (Vector2f::new(0.0, 0.0), 0..1, 0..1)
+            ]
         );
+        assert!(renderer.layers[0][2].is_built());
+
+        // Far away on the x axis the layer is still displayed, but not on the y axis
+        renderer.move_(Vector2f::new(100.0 * 216.0, -100.0 * 216.0));
+        let chunks = renderer.visible_chunks(0);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].0, Vector2f::new(2.0 * 40.0 * 216.0, 0.0));
+        assert_eq!(chunks[0].1, 1..2);
+        assert!(chunks[0].2.is_empty());
     }
 
     #[test]
-    fn test_tile_map_renderer_update() {
-        let textures = load_textures();
+    fn test_tile_map_renderer_missing_tile_placeholder() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((5, 5), 2, 2);
+        tile_map.set_tile((1, 0), 0, 42).unwrap();
+        tile_map.set_tile((2, 0), 1, 43).unwrap();
+
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert_eq!(
+            renderer.missing_tile_policy(),
+            MissingTilePolicy::Placeholder
+        );
+        assert_eq!(
+            renderer
+                .unknown_tiles()
+                .iter()
+                .copied()
+                .collect::<Vec<u32>>(),
+            vec![42, 43]
+        );
+        assert_eq!(renderer.layers[0][0].placeholders().vertex_count(), 16);
+        assert_eq!(renderer.layers[1][0].placeholders().vertex_count(), 16);
+
+        assert!(renderer.set_tile((3, 3), 1, 44).is_ok());
+        assert!(renderer.unknown_tiles().contains(&44));
+        assert_eq!(renderer.layers[1][0].placeholders().vertex_count(), 32);
+
+        // Overwriting the last occurrence of an unknown tile forget it
+        assert!(renderer.set_tile((4, 4), 0, 42).is_ok());
+        assert!(renderer.set_tile((1, 0), 0, 2).is_ok());
+        assert!(renderer.unknown_tiles().contains(&42));
+        assert!(renderer.set_tile((4, 4), 0, 2).is_ok());
+        assert!(renderer.set_tile((3, 3), 1, 2).is_ok());
+        assert_eq!(
+            renderer
+                .unknown_tiles()
+                .iter()
+                .copied()
+                .collect::<Vec<u32>>(),
+            vec![43]
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_missing_tile_skip() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((5, 5), 1, 2);
+        tile_map.set_tile((1, 0), 0, 42).unwrap();
+
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        renderer.set_missing_tile_policy(MissingTilePolicy::Skip);
+        assert!(renderer.update(&tile_map, (1920, 1080), (5, 5)).is_ok());
+
+        assert!(renderer.unknown_tiles().contains(&42));
+        let chunk = &renderer.layers[0][0];
+        assert_eq!(chunk.placeholders().vertex_count(), 0);
+        assert_eq!(chunk.quads().unwrap()[4].color, Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_missing_tile_error() {
+        let tileset = blank_tileset();
         let tile_map = TileMap::new((5, 5), 1, 2);
-        let mut renderer = TileMapRenderer::new(
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        renderer.set_missing_tile_policy(MissingTilePolicy::Error);
+
+        assert_eq!(
+            renderer.set_tile((0, 0), 0, 42),
+            Err(RendererError::UnknownTiles(vec![42].into_iter().collect()))
+        );
+        assert_eq!(renderer.tiles[0][0], 2);
+
+        let mut broken_map = TileMap::new((10, 10), 1, 2);
+        broken_map.set_tile((0, 0), 0, 42).unwrap();
+        broken_map.set_tile((1, 0), 0, 43).unwrap();
+        assert_eq!(
+            renderer.update(&broken_map, (1920, 1080), (5, 5)),
+            Err(RendererError::UnknownTiles(
+                vec![42, 43].into_iter().collect()
+            ))
+        );
+
+        // Renderer is left untouched
+        assert_eq!(renderer.map_size, (5, 5).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_with_policy() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((5, 5), 1, 2);
+        tile_map.set_tile((1, 0), 0, 42).unwrap();
+        let view = || View::new((0.0, 0.0).into(), (10.0, 10.0).into());
+
+        // The unknown tiles are rejected at construction
+        let renderer = TileMapRenderer::with_policy(
             &tile_map,
             (1920, 1080),
             (5, 5),
-            View::new((0.0, 0.0).into(), (10.0, 10.0).into()),
-            &textures,
+            view(),
+            &tileset,
             true,
+            MissingTilePolicy::Error,
+        );
+        assert_eq!(
+            renderer.err(),
+            Some(RendererError::UnknownTiles(vec![42].into_iter().collect()))
         );
 
-        // Update renderer
-        let tile_map = TileMap::new((10, 10), 1, 2);
-        renderer.update(&tile_map, (1920, 1080), (10, 10));
-
-        assert_eq!(renderer.layers.len(), 1);
-        assert_eq!(renderer.layers.get(0).unwrap().len(), 100);
-        assert_eq!(renderer.tile_size, 108.0); // We want a 10x10 viewport, therefore size will be 1080/10
-        assert_eq!(renderer.map_size, (10, 10).into());
+        // Or reported at construction
+        let renderer = TileMapRenderer::with_policy(
+            &tile_map,
+            (1920, 1080),
+            (5, 5),
+            view(),
+            &tileset,
+            true,
+            MissingTilePolicy::Skip,
+        )
+        .unwrap();
+        assert_eq!(renderer.missing_tile_policy(), MissingTilePolicy::Skip);
+        assert!(renderer.unknown_tiles().contains(&42));
+        assert_eq!(renderer.layers[0][0].placeholders().vertex_count(), 0);
     }
 
     #[test]
-    fn test_tile_map_renderer_set_show_grid() {
-        let textures = load_textures();
-        let tile_map = TileMap::new((5, 5), 2, 2);
-        let mut renderer = TileMapRenderer::new(
+    fn test_tile_map_renderer_non_square_tiles() {
+        let mut tileset = Tileset::new(Texture::new(64, 64).unwrap(), (16, 8));
+        tileset.add_tile(1, IntRect::new(0, 0, 16, 8));
+        tileset.add_tile(2, IntRect::new(16, 0, 32, 32));
+
+        let mut tile_map = TileMap::new((5, 5), 2, 1);
+        tile_map.set_tile((1, 4), 1, 2).unwrap();
+        let renderer = TileMapRenderer::new(
             &tile_map,
             (1920, 1080),
-            (5, 5),
+            (10, 10),
             View::new((0.0, 0.0).into(), (10.0, 10.0).into()),
-            &textures,
+            &tileset,
             true,
         );
 
+        // 1920 / (10 * 16) = 12, 1080 / (10 * 8) = 13.5
+        assert_eq!(renderer.tile_scale, 12.0);
+        assert_eq!(renderer.tile_size(), (192.0, 96.0).into());
+        assert_eq!(
+            renderer.get_tile_position((200.0, 100.0)),
+            Some((1, 1).into())
+        );
+
+        // Big tile is anchored on the bottom left corner of its cell
+        let quads = renderer.layers[1][0].quads().unwrap();
+        let index = (1 + 4 * 5) * 4;
+        assert_eq!(
+            quads[index].position,
+            (192.0, 4.0 * 96.0 - 24.0 * 12.0).into()
+        );
+        assert_eq!(
+            quads[index + 2].position,
+            (192.0 + 32.0 * 12.0, 5.0 * 96.0).into()
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_layer_settings() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((5, 5), 2, 2);
+        let settings = LayerSettings {
+            opacity: 0.5,
+            ..Default::default()
+        };
+        tile_map.set_layer_settings(0, settings).unwrap();
+
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // Settings are loaded from the tile map
+        assert_eq!(*renderer.layer_settings(0).unwrap(), settings);
+        assert_eq!(
+            *renderer.layer_settings(1).unwrap(),
+            LayerSettings::default()
+        );
+        assert!(renderer.layer_settings(2).is_none());
+        assert_eq!(
+            renderer.layers[0][0].quads().unwrap()[0].color,
+            Color::rgba(255, 255, 255, 127)
+        );
+
+        // Update the settings
+        let settings = LayerSettings {
+            visible: false,
+            tint: Color::rgb(0, 255, 0),
+            blend_mode: LayerBlendMode::Multiply,
+            ..Default::default()
+        };
+        assert!(renderer.set_layer_settings(0, settings).is_ok());
+        assert_eq!(*renderer.layer_settings(0).unwrap(), settings);
+        assert_eq!(
+            renderer.layers[0][0].quads().unwrap()[0].color,
+            Color::rgb(0, 255, 0)
+        );
+        assert_eq!(
+            renderer.set_layer_settings(2, settings),
+            Err(RendererError::InvalidLayer)
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_advance() {
+        let mut tileset = Tileset::new(Texture::new(32, 16).unwrap(), (16, 16));
+        tileset.add_tile(1, IntRect::new(0, 0, 16, 16));
+        tileset.add_tile(2, IntRect::new(16, 0, 16, 16));
+        tileset.add_animation(
+            10,
+            Animation {
+                frames: vec![
+                    AnimationFrame {
+                        tile_id: 1,
+                        duration: 1.0,
+                    },
+                    AnimationFrame {
+                        tile_id: 2,
+                        duration: 1.0,
+                    },
+                ],
+                mode: AnimationMode::Loop,
+                phase: AnimationPhase::Synchronized,
+            },
+        );
+
+        let mut tile_map = TileMap::new((5, 5), 1, 1);
+        tile_map.set_tile((0, 0), 0, 10).unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        assert!(renderer.unknown_tiles().is_empty());
+        assert_eq!(renderer.animated_cells.len(), 1);
+        assert_eq!(
+            renderer.layers[0][0].quads().unwrap()[0].tex_coords,
+            (0.0, 0.0).into()
+        );
+
+        // Second frame
+        renderer.advance(1.5);
+        assert_eq!(renderer.animated_cells[&(0, 0)], 2);
+        assert_eq!(
+            renderer.layers[0][0].quads().unwrap()[0].tex_coords,
+            (16.0, 0.0).into()
+        );
+
+        // Newly placed animated tile starts on the current frame
+        assert!(renderer.set_tile((1, 0), 0, 10).is_ok());
+        assert_eq!(renderer.animated_cells[&(0, 1)], 2);
+
+        // Replacing the animated tile stops the animation
+        assert!(renderer.set_tile((0, 0), 0, 1).is_ok());
+        assert_eq!(renderer.animated_cells.len(), 1);
+        renderer.advance(1.0);
+        assert_eq!(
+            renderer.layers[0][0].quads().unwrap()[0].tex_coords,
+            (0.0, 0.0).into()
+        );
+        assert_eq!(
+            renderer.layers[0][0].quads().unwrap()[4].tex_coords,
+            (0.0, 0.0).into()
+        );
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_show_grid() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 2, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
         // By default grid is visible
         assert!(renderer.show_grid);
 
@@ -344,9 +2986,574 @@ mod tests {
         assert!(!renderer.show_grid);
     }
 
-    fn load_textures() -> BTreeMap<u32, SfBox<Texture>> {
-        let mut textures = BTreeMap::new();
-        textures.insert(2, Texture::new(16, 16).unwrap());
-        textures
+    #[test]
+    fn test_tile_map_renderer_set_grid_settings() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // 6 horizontal + 6 vertical lines (including the borders)
+        assert_eq!(*renderer.grid_settings(), GridSettings::default());
+        let grid = renderer.grid[0].as_ref().unwrap();
+        assert_eq!(grid.vertex_count(), 12 * 4);
+        assert_eq!(grid[0].position, (-0.5, -0.5).into());
+        assert_eq!(grid[2].position, (1080.5, 0.5).into());
+        assert_eq!(grid[5 * 4].position, (-0.5, 1079.5).into());
+        assert_eq!(grid[0].color, Color::BLACK);
+
+        let settings = GridSettings {
+            color: Color::WHITE,
+            thickness: 2.0,
+            major_interval: 5,
+            major_color: Color::RED,
+            major_thickness: 4.0,
+            ..Default::default()
+        };
+        renderer.set_grid_settings(settings);
+        assert_eq!(*renderer.grid_settings(), settings);
+        let grid = renderer.grid[0].as_ref().unwrap();
+        assert_eq!(grid.vertex_count(), 12 * 4);
+        assert_eq!(grid[0].position, (-2.0, -2.0).into());
+        assert_eq!(grid[0].color, Color::RED);
+        assert_eq!(grid[4].position, (-1.0, 215.0).into());
+        assert_eq!(grid[4].color, Color::WHITE);
+        assert_eq!(grid[5 * 4].color, Color::RED);
+
+        // Labels are added to the same vertex array
+        renderer.set_grid_settings(GridSettings {
+            show_labels: true,
+            ..settings
+        });
+        assert!(renderer.grid[0].as_ref().unwrap().vertex_count() > 12 * 4);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_grid_chunks() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((40, 40), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // Only the visible chunks grid is built
+        assert_eq!(renderer.grid.len(), 9);
+        assert!(renderer.grid[0].is_some());
+        assert!(renderer.grid[8].is_none());
+
+        // The chunk holds the lines starting on its cells
+        let grid = renderer.grid[0].as_ref().unwrap();
+        assert_eq!(grid.vertex_count(), 32 * 4);
+        assert_eq!(grid[0].position, (-0.5, -0.5).into());
+        assert_eq!(grid[2].position, (16.0 * 216.0, 0.5).into());
+
+        // The last chunk holds the border lines too
+        renderer.set_center((40.0 * 216.0, 40.0 * 216.0));
+        let grid = renderer.grid[8].as_ref().unwrap();
+        assert_eq!(grid.vertex_count(), 18 * 4);
+        assert_eq!(grid[0].position, (32.0 * 216.0, 32.0 * 216.0 - 0.5).into());
+        assert_eq!(
+            grid[2].position,
+            (40.0 * 216.0 + 0.5, 32.0 * 216.0 + 0.5).into()
+        );
+
+        // Hidden grid is not built
+        renderer.set_show_grid(false);
+        renderer.set_grid_settings(GridSettings::default());
+        assert!(renderer.grid.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_tile_map_renderer_debug_chunks() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((32, 40), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        renderer.set_debug_settings(DebugSettings {
+            show_layers: true,
+            show_chunks: true,
+            ..Default::default()
+        });
+
+        // Only the visible chunks debug overlays are built: one rect per cell & the chunk boundaries
+        assert_eq!(renderer.debug.len(), 6);
+        assert_eq!(renderer.debug[0].as_ref().unwrap().vertex_count(), 258 * 4);
+        assert!(renderer.debug[5].is_none());
+
+        // Only the changed chunk is rebuilt
+        renderer.set_tile((1, 1), 0, 0).unwrap();
+        assert_eq!(renderer.debug[0].as_ref().unwrap().vertex_count(), 257 * 4);
+        assert!(renderer.debug[5].is_none());
+
+        // The right border is a chunk boundary, the bottom one is not
+        renderer.set_center((32.0 * 216.0, 40.0 * 216.0));
+        assert_eq!(renderer.debug[5].as_ref().unwrap().vertex_count(), 131 * 4);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_hover() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        assert_eq!(renderer.overlay.vertex_count(), 0);
+
+        renderer.set_hover(Some((1, 2).into()));
+        assert_eq!(renderer.hover(), Some((1, 2).into()));
+        assert_eq!(renderer.overlay.vertex_count(), 4);
+        assert_eq!(renderer.overlay[0].position, (216.0, 432.0).into());
+        assert_eq!(renderer.overlay[2].position, (432.0, 648.0).into());
+        assert_eq!(
+            renderer.overlay[0].color,
+            renderer.overlay_settings().hover_color
+        );
+
+        renderer.set_hover(None);
+        assert_eq!(renderer.overlay.vertex_count(), 0);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_selection() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // A single cell is filled and outlined on its 4 sides
+        renderer.set_selection(vec![Vector2u::new(0, 0), Vector2u::new(10, 10)]);
+        assert_eq!(renderer.selection().count(), 1);
+        assert_eq!(renderer.overlay.vertex_count(), 5 * 4);
+
+        // 2x2 cells: 4 fills and 8 outer sides
+        renderer.set_selection_rect((3, 3), (2, 2));
+        assert_eq!(
+            renderer.selection().collect::<Vec<Vector2u>>(),
+            vec![
+                Vector2u::new(2, 2),
+                Vector2u::new(2, 3),
+                Vector2u::new(3, 2),
+                Vector2u::new(3, 3)
+            ]
+        );
+        assert_eq!(renderer.overlay.vertex_count(), 12 * 4);
+
+        renderer.set_selection(vec![]);
+        assert_eq!(renderer.overlay.vertex_count(), 0);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_objects() {
+        let mut tileset = Tileset::new(Texture::new(64, 32).unwrap(), (16, 16));
+        tileset.add_object(100, IntRect::new(0, 0, 48, 32), (3, 1));
+        tileset.add_object(101, IntRect::new(48, 0, 16, 32), (1, 1));
+
+        let mut tile_map = TileMap::new((5, 5), 2, 0);
+        let house = MapObject::new(100, (1, 2), 1, (3, 1));
+        tile_map.place_object(house).unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // The house overflows one cell above its footprint
+        assert_eq!(renderer.object_quads[0].vertex_count(), 0);
+        let quads = &renderer.object_quads[1];
+        assert_eq!(quads.vertex_count(), 4);
+        assert_eq!(quads[0].position, (216.0, 216.0).into());
+        assert_eq!(quads[2].position, (864.0, 648.0).into());
+        assert_eq!(quads[2].tex_coords, (48.0, 32.0).into());
+
+        // The tree in front of the house is drawn last, the one behind first
+        let front = MapObject::new(101, (2, 3), 1, (1, 1));
+        let back = MapObject::new(101, (0, 1), 1, (1, 1));
+        tile_map.place_object(front).unwrap();
+        tile_map.place_object(back).unwrap();
+        assert!(renderer.update_objects(&tile_map).is_ok());
+        assert_eq!(renderer.objects[1], vec![back, house, front]);
+        let quads = &renderer.object_quads[1];
+        assert_eq!(quads.vertex_count(), 12);
+        assert_eq!(quads[0].position, (0.0, 0.0).into());
+        assert_eq!(quads[8].position, (432.0, 432.0).into());
+
+        // Unknown object tiles are reported
+        tile_map
+            .place_object(MapObject::new(102, (0, 4), 1, (1, 1)))
+            .unwrap();
+        renderer.set_missing_tile_policy(MissingTilePolicy::Error);
+        assert_eq!(
+            renderer.update_objects(&tile_map),
+            Err(RendererError::UnknownTiles(vec![102].into_iter().collect()))
+        );
+        assert_eq!(renderer.objects[1].len(), 3);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_animated_objects() {
+        let mut tileset = Tileset::new(Texture::new(64, 32).unwrap(), (16, 16));
+        tileset.add_object(100, IntRect::new(0, 0, 32, 32), (2, 2));
+        tileset.add_object(101, IntRect::new(32, 0, 32, 32), (2, 2));
+        tileset.add_animation(
+            110,
+            Animation {
+                frames: vec![
+                    AnimationFrame {
+                        tile_id: 100,
+                        duration: 1.0,
+                    },
+                    AnimationFrame {
+                        tile_id: 101,
+                        duration: 1.0,
+                    },
+                ],
+                mode: AnimationMode::Loop,
+                phase: AnimationPhase::Synchronized,
+            },
+        );
+
+        let mut tile_map = TileMap::new((5, 5), 1, 0);
+        let mill = MapObject::new(110, (0, 0), 0, (2, 2));
+        let house = MapObject::new(100, (3, 3), 0, (2, 2));
+        tile_map.place_object(mill).unwrap();
+        tile_map.place_object(house).unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        assert_eq!(renderer.object_quads[0][0].tex_coords, (0.0, 0.0).into());
+
+        // Only the animated object moves to its next frame
+        renderer.advance(1.5);
+        let quads = &renderer.object_quads[0];
+        assert_eq!(quads.vertex_count(), 8);
+        assert_eq!(quads[0].tex_coords, (32.0, 0.0).into());
+        assert_eq!(quads[0].position, (0.0, 0.0).into());
+        assert_eq!(quads[4].tex_coords, (0.0, 0.0).into());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_sync() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((5, 5), 2, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        tile_map.set_tile((1, 1), 1, 2).unwrap();
+        tile_map
+            .set_layer_settings(
+                0,
+                LayerSettings {
+                    opacity: 0.5,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(renderer.sync(&mut tile_map).is_ok());
+        assert!(!tile_map.has_changes());
+
+        assert_eq!(renderer.tiles[1][1 + 5], 2);
+        let quads = renderer.layers[1][0].quads().unwrap();
+        assert_eq!(quads[(1 + 5) * 4].color, Color::WHITE);
+        assert_eq!(renderer.layer_settings(0).unwrap().opacity, 0.5);
+        let quads = renderer.layers[0][0].quads().unwrap();
+        assert_eq!(quads[0].color, Color::rgba(255, 255, 255, 127));
+
+        // Unknown tiles are reported, the other changes are still applied
+        renderer.set_missing_tile_policy(MissingTilePolicy::Error);
+        tile_map.set_tile((2, 2), 1, 7).unwrap();
+        tile_map.set_tile((3, 3), 1, 2).unwrap();
+        tile_map
+            .place_object(MapObject::new(2, (0, 4), 1, (1, 1)))
+            .unwrap();
+        assert_eq!(
+            renderer.sync(&mut tile_map),
+            Err(RendererError::UnknownTiles(vec![7].into_iter().collect()))
+        );
+        assert_eq!(renderer.tiles[1][2 + 2 * 5], 0);
+        assert_eq!(renderer.tiles[1][3 + 3 * 5], 2);
+        assert_eq!(renderer.objects[1].len(), 1);
+
+        // An invalid change does not prevent the following ones from being applied
+        let bigger_map = TileMap::new((5, 5), 3, 2);
+        let changes = [
+            TileMapChange::Tile {
+                position: (0, 0).into(),
+                layer: 2,
+            },
+            TileMapChange::Tile {
+                position: (4, 4).into(),
+                layer: 0,
+            },
+        ];
+        renderer.set_tile((4, 4), 0, 0).unwrap();
+        assert_eq!(
+            renderer.apply_changes(&bigger_map, &changes),
+            Err(RendererError::InvalidLayer)
+        );
+        assert_eq!(renderer.tiles[0][4 + 4 * 5], 2);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_geometry_cache() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((5, 5), 2, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        let mut backend = CountingBackend::default();
+
+        renderer.render(&mut backend);
+        let builds = backend.builds;
+        assert!(builds > 0);
+
+        // The geometry is kept between draws
+        renderer.render(&mut backend);
+        assert_eq!(backend.builds, builds);
+
+        // Only the changed chunk is built again
+        tile_map.set_tile((1, 1), 1, 2).unwrap();
+        renderer.sync(&mut tile_map).unwrap();
+        renderer.render(&mut backend);
+        assert_eq!(backend.builds, builds + 1);
+
+        // Every geometry is built again once the renderer is rebuilt
+        renderer.update(&tile_map, (1920, 1080), (5, 5)).unwrap();
+        renderer.render(&mut backend);
+        assert_eq!(backend.builds, builds * 2 + 1);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_debug_settings() {
+        let mut tileset = blank_tileset();
+        tileset.set_tile_property(2, "walkable", true);
+        let mut tile_map = TileMap::new((5, 5), 2, 0);
+        tile_map.set_tile((0, 0), 0, 2).unwrap();
+        tile_map.set_tile((1, 1), 1, 2 | FLIP_HORIZONTAL).unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        // The tile map fits in a single chunk
+        let debug = |renderer: &TileMapRenderer| renderer.debug[0].as_ref().unwrap().clone();
+        assert!(renderer.debug[0].is_none());
+
+        // Cells colored by their top most layer
+        let settings = DebugSettings {
+            show_layers: true,
+            ..Default::default()
+        };
+        renderer.set_debug_settings(settings.clone());
+        assert_eq!(renderer.debug_settings(), &settings);
+        assert_eq!(debug(&renderer).vertex_count(), 2 * 4);
+        assert_eq!(debug(&renderer)[0].color, settings.layer_colors[0]);
+        assert_eq!(debug(&renderer)[0].position, (0.0, 0.0).into());
+        assert_eq!(debug(&renderer)[4].color, settings.layer_colors[1]);
+        assert_eq!(debug(&renderer)[4].position, (216.0, 216.0).into());
+
+        // Cells colored by property, updated along the tiles
+        renderer.set_debug_settings(DebugSettings {
+            property: Some("walkable".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(debug(&renderer).vertex_count(), 2 * 4);
+        assert!(renderer.set_tile((4, 4), 0, 2).is_ok());
+        assert_eq!(debug(&renderer).vertex_count(), 3 * 4);
+
+        // Chunks boundaries
+        renderer.set_debug_settings(DebugSettings {
+            show_chunks: true,
+            ..Default::default()
+        });
+        assert_eq!(debug(&renderer).vertex_count(), 2 * 4);
+
+        renderer.set_debug_settings(DebugSettings {
+            show_tile_ids: true,
+            ..Default::default()
+        });
+        assert!(debug(&renderer).vertex_count() > 0);
+        assert_eq!(debug(&renderer)[0].color, Color::WHITE);
+
+        renderer.set_debug_settings(DebugSettings::default());
+        assert!(renderer.debug[0].is_none());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_visibility() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((2, 1), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        // The tile map fits in a single chunk
+        let fog = |renderer: &TileMapRenderer| renderer.fog[0].as_ref().unwrap().clone();
+        assert!(renderer.fog[0].is_none());
+
+        let mut visibility = VisibilityGrid::new((2, 1));
+        visibility
+            .set_visibility((0, 0), Visibility::Visible)
+            .unwrap();
+        renderer.set_visibility(Some(&visibility));
+
+        // Soft edges: the fog fades from the visible cell to the unseen one
+        assert_eq!(fog(&renderer).vertex_count(), 2 * 4);
+        let alphas: Vec<u8> = (0..8).map(|i| fog(&renderer)[i].color.a).collect();
+        assert_eq!(alphas, vec![0, 128, 128, 0, 128, 255, 255, 128]);
+        assert_eq!(fog(&renderer)[4].position, (216.0, 0.0).into());
+
+        // Hard edges: only the unseen cell is hidden
+        renderer.set_fog_settings(FogSettings {
+            soft_edges: false,
+            ..Default::default()
+        });
+        assert!(!renderer.fog_settings().soft_edges);
+        assert_eq!(fog(&renderer).vertex_count(), 4);
+        assert_eq!(fog(&renderer)[0].color, Color::BLACK);
+
+        // Explored cells are darkened
+        visibility.fade();
+        renderer.set_visibility(Some(&visibility));
+        assert_eq!(fog(&renderer).vertex_count(), 2 * 4);
+        assert_eq!(fog(&renderer)[0].color, Color::rgba(0, 0, 0, 128));
+
+        renderer.set_visibility(None);
+        assert!(renderer.fog[0].is_none());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_fog_chunks() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((40, 40), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        let mut visibility = VisibilityGrid::new((40, 40));
+        renderer.set_visibility(Some(&visibility));
+
+        // Only the visible chunks fog is built
+        assert_eq!(renderer.fog.len(), 9);
+        assert_eq!(renderer.fog[0].as_ref().unwrap().vertex_count(), 256 * 4);
+        assert!(renderer.fog[1].is_none());
+
+        // The chunks around the changed cells are outdated, the visible ones are rebuilt
+        renderer.set_center((40.0 * 216.0, 40.0 * 216.0));
+        visibility.reveal((16, 16), 0);
+        renderer.set_visibility(Some(&visibility));
+        assert_eq!(renderer.visibility.as_ref(), Some(&visibility));
+        assert!(renderer.fog[0].is_none());
+        assert!(renderer.fog[8].is_some());
+
+        renderer.set_center((0.0, 0.0));
+        let fog = renderer.fog[0].as_ref().unwrap();
+        assert_eq!(fog.vertex_count(), 256 * 4);
+        // The bottom right cell fades to the revealed one
+        assert_eq!(fog[255 * 4 + 2].color.a, 191);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_lighting() {
+        let tileset = blank_tileset();
+        let mut tile_map = TileMap::new((3, 1), 1, 0);
+        tile_map.set_tile((0, 0), 0, 2).unwrap();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        // The tile map fits in a single chunk
+        let light = |renderer: &TileMapRenderer| renderer.light[0].as_ref().unwrap().clone();
+        assert!(renderer.light[0].is_none());
+
+        let mut lighting = Lighting {
+            cycle: DayNightCycle {
+                duration: 10.0,
+                keyframes: vec![(0.0, Color::BLACK), (0.5, Color::WHITE)],
+            },
+            ..Default::default()
+        };
+        lighting.lights.insert(
+            2,
+            LightSource {
+                color: Color::rgb(100, 0, 0),
+                radius: 2.0,
+                intensity: 1.0,
+            },
+        );
+        renderer.set_lighting(Some(lighting.clone()));
+        assert_eq!(renderer.lighting(), Some(&lighting));
+
+        // The light fades away from the torch, at night
+        assert_eq!(light(&renderer).vertex_count(), 3 * 4);
+        assert_eq!(light(&renderer)[0].color, Color::rgb(100, 0, 0));
+        assert_eq!(light(&renderer)[1].color, Color::rgb(75, 0, 0));
+        assert_eq!(light(&renderer)[4].color, Color::rgb(75, 0, 0));
+        assert_eq!(light(&renderer)[5].color, Color::rgb(25, 0, 0));
+        assert_eq!(light(&renderer)[9].color, Color::BLACK);
+
+        // Moving the torch moves the light
+        assert!(renderer.set_tile((0, 0), 0, 0).is_ok());
+        assert_eq!(light(&renderer)[0].color, Color::BLACK);
+
+        // The ambient light changes over time
+        renderer.advance(5.0);
+        assert_eq!(light(&renderer)[0].color, Color::WHITE);
+
+        renderer.set_lighting(None);
+        assert!(renderer.light[0].is_none());
+    }
+
+    #[test]
+    fn test_tile_map_renderer_update_light() {
+        let mut tileset = blank_tileset();
+        let mut lighting = Lighting::default();
+        tileset.set_tile_property(2, &lighting.blocking_property, true);
+        lighting.lights.insert(
+            5,
+            LightSource {
+                color: Color::rgb(200, 100, 50),
+                radius: 4.0,
+                intensity: 1.0,
+            },
+        );
+        let mut tile_map = TileMap::new((40, 40), 2, 0);
+        tile_map.set_tile((10, 10), 1, 5).unwrap();
+        tile_map.set_tile((30, 30), 1, 5).unwrap();
+        tile_map.take_changes();
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+        renderer.set_lighting(Some(lighting.clone()));
+        let positions = |renderer: &TileMapRenderer| -> Vec<Vector2u> {
+            renderer
+                .light_sources
+                .iter()
+                .map(|light| light.position)
+                .collect()
+        };
+        assert_eq!(positions(&renderer), vec![(10, 10).into(), (30, 30).into()]);
+
+        // Only the light reaching the wall is recast
+        tile_map.set_tile((12, 10), 0, 2).unwrap();
+        renderer.sync(&mut tile_map).unwrap();
+        assert_eq!(positions(&renderer), vec![(30, 30).into(), (10, 10).into()]);
+
+        // The light sources follow the tiles & objects
+        tile_map.set_tile((30, 30), 1, 0).unwrap();
+        tile_map.set_tile((5, 5), 0, 5).unwrap();
+        tile_map
+            .place_object(MapObject::new(5, (20, 20), 1, (3, 3)))
+            .unwrap();
+        renderer.sync(&mut tile_map).unwrap();
+        assert_eq!(
+            positions(&renderer),
+            vec![(10, 10).into(), (5, 5).into(), (21, 21).into()]
+        );
+
+        // The light is the same as the one computed from scratch
+        let mut expected = full_hd_renderer(&tile_map, &tileset);
+        expected.set_lighting(Some(lighting));
+        for (light, expected) in renderer.light_map.iter().zip(&expected.light_map) {
+            for (channel, expected) in light.iter().zip(expected) {
+                assert!((channel - expected).abs() < 1e-3);
+            }
+        }
+        assert_eq!(renderer.light_blocking, expected.light_blocking);
+    }
+
+    #[test]
+    fn test_tile_map_renderer_set_ghost() {
+        let tileset = blank_tileset();
+        let tile_map = TileMap::new((5, 5), 1, 2);
+        let mut renderer = full_hd_renderer(&tile_map, &tileset);
+
+        // The tiles outside the map are not displayed
+        renderer.set_ghost(
+            Some((4, 1).into()),
+            &[((0, 0).into(), 2), ((1, 0).into(), 2), ((0, 1).into(), 2)],
+        );
+        assert_eq!(renderer.ghost.vertex_count(), 2 * 4);
+        assert_eq!(renderer.ghost[0].position, (864.0, 216.0).into());
+        assert_eq!(renderer.ghost[4].position, (864.0, 432.0).into());
+        assert_eq!(renderer.ghost[2].tex_coords, (16.0, 16.0).into());
+        assert_eq!(renderer.ghost[0].color, Color::rgba(255, 255, 255, 127));
+
+        renderer.set_overlay_settings(OverlaySettings {
+            ghost_opacity: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(renderer.ghost[0].color, Color::WHITE);
+
+        renderer.set_ghost(None, &[]);
+        assert_eq!(renderer.ghost.vertex_count(), 0);
     }
 }
@@ -0,0 +1,82 @@
+use sfml::graphics::{Color, Image, IntRect, Texture, View};
+use sfml::system::Vector2f;
+use std::collections::BTreeMap;
+
+use crate::geometry::Quads;
+use crate::tilemap::{LayerBlendMode, RenderBackend, TileMap, TileMapRenderer, Tileset};
+
+/// Build a tileset with a single blank 16x16 tile (id 2)
+pub fn blank_tileset() -> Tileset {
+    let mut textures = BTreeMap::new();
+    textures.insert(2, Texture::new(16, 16).unwrap());
+    Tileset::from_textures(&textures, (16, 16)).unwrap()
+}
+
+/// Build a tileset made of 16x16 tiles: tile 1 is red, tile 2 is blue
+pub fn red_blue_tileset() -> Tileset {
+    let mut image = Image::from_color(32, 16, Color::RED).unwrap();
+    for y in 0..16 {
+        for x in 16..32 {
+            image.set_pixel(x, y, Color::BLUE);
+        }
+    }
+
+    let mut tileset = Tileset::new(Texture::from_image(&image).unwrap(), (16, 16));
+    tileset.add_tile(1, IntRect::new(0, 0, 16, 16));
+    tileset.add_tile(2, IntRect::new(16, 0, 16, 16));
+    tileset
+}
+
+/// Build a CPU tileset made of 2x2 pixels tiles:
+/// tile 1 is red on the left column, blue on the top right pixel and white on the bottom right one
+/// tile 3 is half transparent green
+pub fn pixel_tileset() -> Tileset<Image> {
+    let mut atlas = Image::from_color(4, 2, Color::rgba(0, 255, 0, 128)).unwrap();
+    atlas.set_pixel(0, 0, Color::RED);
+    atlas.set_pixel(0, 1, Color::RED);
+    atlas.set_pixel(1, 0, Color::BLUE);
+    atlas.set_pixel(1, 1, Color::WHITE);
+
+    let mut tileset = Tileset::new(atlas, (2, 2));
+    tileset.add_tile(1, IntRect::new(0, 0, 2, 2));
+    tileset.add_tile(3, IntRect::new(2, 0, 2, 2));
+    tileset
+}
+
+/// Create a renderer showing 5x5 tiles on a 1920x1080 screen, with the grid visible
+pub fn full_hd_renderer<'s, A>(
+    tile_map: &TileMap,
+    tileset: &'s Tileset<A>,
+) -> TileMapRenderer<'s, A> {
+    TileMapRenderer::new(
+        tile_map,
+        (1920, 1080),
+        (5, 5),
+        View::new((0.0, 0.0).into(), (10.0, 10.0).into()),
+        tileset,
+        true,
+    )
+}
+
+/// A backend drawing nothing, it counts the geometry built & drawn
+/// the geometry is the number of vertices of the batch
+#[derive(Default)]
+pub struct CountingBackend {
+    pub builds: usize,
+    pub draws: usize,
+}
+
+impl RenderBackend for CountingBackend {
+    type Geometry = usize;
+
+    fn set_view(&mut self, _: Vector2f, _: Vector2f, _: f32) {}
+
+    fn build_layer_geometry(&mut self, quads: &Quads, _: bool) -> usize {
+        self.builds += 1;
+        quads.vertex_count()
+    }
+
+    fn draw(&mut self, _: &usize, _: LayerBlendMode, _: Vector2f) {
+        self.draws += 1;
+    }
+}
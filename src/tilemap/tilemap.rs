@@ -1,8 +1,22 @@
 use serde::{Deserialize, Serialize};
-use sfml::system::Vector2u;
+use sfml::graphics::Color;
+use sfml::system::{Vector2f, Vector2u};
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
+
+/// Flag set on a tile id to display the tile flipped horizontally
+pub const FLIP_HORIZONTAL: u32 = 1 << 31;
+/// Flag set on a tile id to display the tile flipped vertically
+pub const FLIP_VERTICAL: u32 = 1 << 30;
+/// The bits of a tile id that identify the tile (i.e without the flip flags)
+pub const TILE_ID_MASK: u32 = !(FLIP_HORIZONTAL | FLIP_VERTICAL);
+
+/// The bytes a tile map file starts with, followed by the format version
+const MAGIC: &[u8; 4] = b"RLTM";
+/// The version of the tile map file format written
+const FORMAT_VERSION: u32 = 1;
 
 #[derive(Debug, PartialEq)]
 pub enum TileMapError {
@@ -10,18 +24,225 @@ pub enum TileMapError {
     InvalidLayer,
     WriteError,
     ReadError,
+    /// The file was written using an unknown version of the format
+    UnsupportedVersion(u32),
+    /// The file content is inconsistent (i.e missing tiles, objects outside of the map)
+    InvalidContent,
 }
 
 // Allow serde serialization / deserialization of Vector2u
 #[derive(Serialize, Deserialize)]
 #[serde(remote = "Vector2u")]
-struct Vector2uDef {
+pub(crate) struct Vector2uDef {
     x: u32,
     y: u32,
 }
 
+// Allow serde serialization / deserialization of Vector2f
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Vector2f")]
+struct Vector2fDef {
+    x: f32,
+    y: f32,
+}
+
+// Allow serde serialization / deserialization of Color
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Color")]
+struct ColorDef {
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+}
+
+/// LayerBlendMode define how a layer is blended with the layers below it
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub enum LayerBlendMode {
+    Alpha,
+    Add,
+    Multiply,
+    None,
+}
+
+/// LayerSettings holds the display settings of a tile map layer
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub struct LayerSettings {
+    /// should the layer be displayed or not?
+    pub visible: bool,
+    /// the layer opacity, from 0.0 (transparent) to 1.0 (opaque)
+    pub opacity: f32,
+    /// the color the layer tiles are multiplied with
+    #[serde(with = "ColorDef")]
+    pub tint: Color,
+    pub blend_mode: LayerBlendMode,
+    /// how fast the layer moves compared to the camera
+    /// 1.0 moves with the camera, 0.5 at half speed (far background), 0.0 stays fixed on screen
+    #[serde(with = "Vector2fDef")]
+    pub parallax: Vector2f,
+    /// the layer automatic scrolling speed (in tiles per second)
+    #[serde(with = "Vector2fDef")]
+    pub scroll_speed: Vector2f,
+    /// should the layer be repeated horizontally?
+    pub repeat_x: bool,
+    /// should the layer be repeated vertically?
+    pub repeat_y: bool,
+}
+
+impl Default for LayerSettings {
+    fn default() -> Self {
+        LayerSettings {
+            visible: true,
+            opacity: 1.0,
+            tint: Color::WHITE,
+            blend_mode: LayerBlendMode::Alpha,
+            parallax: Vector2f::new(1.0, 1.0),
+            scroll_speed: Vector2f::new(0.0, 0.0),
+            repeat_x: false,
+            repeat_y: false,
+        }
+    }
+}
+
+impl LayerSettings {
+    /// Compute the color to apply on the layer tiles (tint & opacity)
+    pub fn color(&self) -> Color {
+        let alpha = self.tint.a as f32 * self.opacity.clamp(0.0, 1.0);
+        Color::rgba(self.tint.r, self.tint.g, self.tint.b, alpha as u8)
+    }
+}
+
+/// MapObject is a tile spanning several cells (i.e a house or a tree) placed as a unit
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub struct MapObject {
+    /// the object tile id (flip flags included)
+    pub tile_id: u32,
+    /// the layer the object is placed on
+    pub layer: u32,
+    /// the top left cell of the object footprint
+    #[serde(with = "Vector2uDef")]
+    pub position: Vector2u,
+    /// the number of cells covered by the object
+    #[serde(with = "Vector2uDef")]
+    pub footprint: Vector2u,
+}
+
+impl MapObject {
+    /// Create a new object with given tile id, placed at given position and layer
+    pub fn new<T: Into<Vector2u>, F: Into<Vector2u>>(
+        tile_id: u32,
+        position: T,
+        layer: u32,
+        footprint: F,
+    ) -> Self {
+        MapObject {
+            tile_id,
+            layer,
+            position: position.into(),
+            footprint: footprint.into(),
+        }
+    }
+
+    /// Determinate if the object footprint covers given cell
+    pub fn contains<T: Into<Vector2u>>(&self, position: T) -> bool {
+        let position = position.into();
+        let end = self.end();
+        position.x >= self.position.x
+            && position.y >= self.position.y
+            && position.x < end.x
+            && position.y < end.y
+    }
+
+    /// Determinate if the object footprint overlaps the footprint of given object
+    pub fn overlaps(&self, other: &MapObject) -> bool {
+        let (end, other_end) = (self.end(), other.end());
+        self.layer == other.layer
+            && self.position.x < other_end.x
+            && other.position.x < end.x
+            && self.position.y < other_end.y
+            && other.position.y < end.y
+    }
+
+    /// The key used to draw objects from back to front
+    /// objects whose footprint ends lower on the map are drawn last
+    fn sort_key(&self) -> (u32, u32) {
+        (self.end().y, self.position.x)
+    }
+
+    /// The cell following the bottom right corner of the footprint
+    /// (saturated, a footprint cannot extend past the end of the u32 range)
+    fn end(&self) -> Vector2u {
+        Vector2u::new(
+            self.position.x.saturating_add(self.footprint.x),
+            self.position.y.saturating_add(self.footprint.y),
+        )
+    }
+}
+
+/// TileMapChange describe a modification made on a tile map since its changes were last taken
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TileMapChange {
+    /// The tile at given position and layer has changed
+    Tile { position: Vector2u, layer: u32 },
+    /// The display settings of given layer have changed
+    LayerSettings { layer: u32 },
+    /// An object has been placed or removed
+    Objects,
+}
+
+/// PendingChanges holds the changes made on a tile map since they were last taken
+/// they are coalesced so their size is bounded by the tile map size, whatever the number of edits
+#[derive(Debug, Default)]
+struct PendingChanges {
+    /// the changed layers settings
+    layers: BTreeSet<u32>,
+    /// the changed cells, keyed by (layer, row, column)
+    tiles: BTreeSet<(u32, u32, u32)>,
+    /// whether an object has been placed or removed
+    objects: bool,
+}
+
+impl PendingChanges {
+    fn is_empty(&self) -> bool {
+        self.layers.is_empty() && self.tiles.is_empty() && !self.objects
+    }
+
+    /// Convert the pending changes to a list of changes
+    /// the layers settings first, then the tiles by layer in row major order, then the objects
+    fn into_changes(self) -> Vec<TileMapChange> {
+        let layers = self
+            .layers
+            .into_iter()
+            .map(|layer| TileMapChange::LayerSettings { layer });
+        let tiles = self
+            .tiles
+            .into_iter()
+            .map(|(layer, y, x)| TileMapChange::Tile {
+                position: Vector2u::new(x, y),
+                layer,
+            });
+        let objects = if self.objects {
+            Some(TileMapChange::Objects)
+        } else {
+            None
+        };
+
+        layers.chain(tiles).chain(objects).collect()
+    }
+}
+
+/// LegacyTileMap is the content of the tile map files written before the format was versioned
+/// those files only contain the tiles, without header
+#[derive(Deserialize)]
+struct LegacyTileMap {
+    tiles: Vec<Vec<u32>>,
+    #[serde(with = "Vector2uDef")]
+    size: Vector2u,
+    layer_count: u32,
+}
+
 /// TileMap is the raw representation of a tile map
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TileMap {
     /// the map tiles, the first vector is the layer, the second is the tiles in row major order
     tiles: Vec<Vec<u32>>,
@@ -30,6 +251,24 @@ pub struct TileMap {
     size: Vector2u,
     /// The number of layers
     layer_count: u32,
+    /// The layers display settings
+    layers: Vec<LayerSettings>,
+    /// The multi-cell objects placed on the map
+    objects: Vec<MapObject>,
+    /// The changes made since they were last taken
+    #[serde(skip)]
+    changes: PendingChanges,
+}
+
+impl PartialEq for TileMap {
+    fn eq(&self, other: &Self) -> bool {
+        // The pending changes are not part of the tile map content
+        self.tiles == other.tiles
+            && self.size == other.size
+            && self.layer_count == other.layer_count
+            && self.layers == other.layers
+            && self.objects == other.objects
+    }
 }
 
 impl TileMap {
@@ -50,6 +289,9 @@ impl TileMap {
             tiles,
             size,
             layer_count,
+            layers: vec![LayerSettings::default(); layer_count as usize],
+            objects: Vec::new(),
+            changes: PendingChanges::default(),
         }
     }
 
@@ -72,14 +314,22 @@ impl TileMap {
         layer: u32,
         tile: u32,
     ) -> Result<(), TileMapError> {
+        let position = position.into();
         let index = self
-            .compute_index(position.into())
+            .compute_index(position)
             .ok_or(TileMapError::InvalidPosition)?;
 
-        self.tiles
+        let current = self
+            .tiles
             .get_mut(layer as usize)
             .ok_or(TileMapError::InvalidLayer)
-            .map(|v| v[index] = tile)
+            .map(|v| &mut v[index])?;
+        if *current != tile {
+            *current = tile;
+            self.changes.tiles.insert((layer, position.y, position.x));
+        }
+
+        Ok(())
     }
 
     /// Retrieve the tile map size
@@ -92,14 +342,183 @@ impl TileMap {
         self.layer_count
     }
 
+    /// Retrieve the display settings of given layer
+    /// this will return None if the layer doesn't exist
+    pub fn layer_settings(&self, layer: u32) -> Option<&LayerSettings> {
+        self.layers.get(layer as usize)
+    }
+
+    /// Set the display settings of given layer
+    /// this operation will fails if the layer doesn't exist
+    pub fn set_layer_settings(
+        &mut self,
+        layer: u32,
+        settings: LayerSettings,
+    ) -> Result<(), TileMapError> {
+        let current = self
+            .layers
+            .get_mut(layer as usize)
+            .ok_or(TileMapError::InvalidLayer)?;
+        if *current != settings {
+            *current = settings;
+            self.changes.layers.insert(layer);
+        }
+
+        Ok(())
+    }
+
+    /// Place given object on the map, replacing the objects its footprint overlaps
+    /// this operation will fails if the footprint is empty / outside the map or if the layer doesn't exist
+    /// the replaced objects are returned
+    pub fn place_object(&mut self, object: MapObject) -> Result<Vec<MapObject>, TileMapError> {
+        if object.layer >= self.layer_count {
+            return Err(TileMapError::InvalidLayer);
+        }
+        if !self.fits(&object) {
+            return Err(TileMapError::InvalidPosition);
+        }
+
+        let (replaced, objects) = self
+            .objects
+            .drain(..)
+            .partition(|other| other.overlaps(&object));
+        self.objects = objects;
+        self.objects.push(object);
+        self.changes.objects = true;
+
+        Ok(replaced)
+    }
+
+    /// Retrieve the object covering given cell on given layer
+    /// this will return None if there is no object there
+    pub fn object_at<T: Into<Vector2u>>(&self, position: T, layer: u32) -> Option<&MapObject> {
+        let position = position.into();
+        self.objects
+            .iter()
+            .find(|object| object.layer == layer && object.contains(position))
+    }
+
+    /// Remove the object covering given cell on given layer
+    /// this will return the removed object, if any
+    pub fn remove_object_at<T: Into<Vector2u>>(
+        &mut self,
+        position: T,
+        layer: u32,
+    ) -> Option<MapObject> {
+        let position = position.into();
+        let index = self
+            .objects
+            .iter()
+            .position(|object| object.layer == layer && object.contains(position))?;
+        self.changes.objects = true;
+
+        Some(self.objects.remove(index))
+    }
+
+    /// Retrieve the objects placed on the map
+    pub fn objects(&self) -> &[MapObject] {
+        &self.objects
+    }
+
+    /// Retrieve the objects of given layer, in drawing order (from back to front)
+    pub fn sorted_objects(&self, layer: u32) -> Vec<MapObject> {
+        let mut objects: Vec<MapObject> = self
+            .objects
+            .iter()
+            .filter(|object| object.layer == layer)
+            .copied()
+            .collect();
+        objects.sort_by_key(MapObject::sort_key);
+        objects
+    }
+
+    /// Determinate if the tile map has changed since its changes were last taken
+    pub fn has_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Take the changes made on the tile map since they were last taken, i.e to update a renderer incrementally
+    /// each change is only reported once: the layers settings first,
+    /// then the tiles by layer in row major order, then the objects
+    pub fn take_changes(&mut self) -> Vec<TileMapChange> {
+        std::mem::take(&mut self.changes).into_changes()
+    }
+
     /// Write the tile map to given writer
+    /// the content is prefixed by the format magic & version
     pub fn write(&self, mut writer: impl Write) -> Result<(), TileMapError> {
-        let bytes: Vec<u8> = bincode::serialize(&self).map_err(|_| TileMapError::WriteError)?;
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &self).map_err(|_| TileMapError::WriteError)?;
         writer
             .write_all(&bytes)
             .map_err(|_| TileMapError::WriteError)
     }
 
+    /// Read a tile map from given reader
+    /// the files written before the format was versioned are read using default layers settings
+    pub fn read(mut reader: impl Read) -> Result<Self, TileMapError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|_| TileMapError::ReadError)?;
+
+        // A legacy file starts with the (little endian u64) number of layers,
+        // it cannot match the magic without being billions of layers long
+        let content = match bytes.strip_prefix(MAGIC) {
+            Some(content) => content,
+            None => {
+                let legacy: LegacyTileMap =
+                    bincode::deserialize(&bytes).map_err(|_| TileMapError::ReadError)?;
+                return TileMap::from(legacy).validate();
+            }
+        };
+
+        if content.len() < 4 {
+            return Err(TileMapError::ReadError);
+        }
+        let (version, content) = content.split_at(4);
+        let version = u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+        if version != FORMAT_VERSION {
+            return Err(TileMapError::UnsupportedVersion(version));
+        }
+
+        let tile_map: TileMap =
+            bincode::deserialize(content).map_err(|_| TileMapError::ReadError)?;
+        tile_map.validate()
+    }
+
+    /// Check the invariants of a read tile map: one tiles vector & settings per layer,
+    /// size.x * size.y tiles per layer and objects lying inside the map
+    fn validate(self) -> Result<Self, TileMapError> {
+        let cell_count = (self.size.x as u64 * self.size.y as u64) as usize;
+        let consistent = self.tiles.len() == self.layer_count as usize
+            && self.layers.len() == self.layer_count as usize
+            && self.tiles.iter().all(|tiles| tiles.len() == cell_count)
+            && self
+                .objects
+                .iter()
+                .all(|object| object.layer < self.layer_count && self.fits(object));
+
+        if consistent {
+            Ok(self)
+        } else {
+            Err(TileMapError::InvalidContent)
+        }
+    }
+
+    /// Determinate if the footprint of given object lies inside the tile map
+    fn fits(&self, object: &MapObject) -> bool {
+        let fits = |position: u32, footprint: u32, size: u32| {
+            footprint > 0
+                && position
+                    .checked_add(footprint)
+                    .is_some_and(|end| end <= size)
+        };
+        fits(object.position.x, object.footprint.x, self.size.x)
+            && fits(object.position.y, object.footprint.y, self.size.y)
+    }
+
     /// Compute the vector index from given position
     fn compute_index<T: Into<Vector2u>>(&self, position: T) -> Option<usize> {
         let position = position.into();
@@ -113,11 +532,24 @@ impl TileMap {
     }
 }
 
+impl From<LegacyTileMap> for TileMap {
+    fn from(legacy: LegacyTileMap) -> Self {
+        TileMap {
+            layers: vec![LayerSettings::default(); legacy.layer_count as usize],
+            tiles: legacy.tiles,
+            size: legacy.size,
+            layer_count: legacy.layer_count,
+            objects: Vec::new(),
+            changes: PendingChanges::default(),
+        }
+    }
+}
+
 impl TryFrom<File> for TileMap {
     type Error = TileMapError;
 
     fn try_from(value: File) -> Result<Self, Self::Error> {
-        bincode::deserialize_from(value).map_err(|_| TileMapError::ReadError)
+        TileMap::read(value)
     }
 }
 
@@ -133,7 +565,7 @@ mod tests {
         assert_eq!(tile_map.size.x, 20);
         assert_eq!(tile_map.size.y, 10);
         assert_eq!(tile_map.layer_count, 2);
-        assert_eq!(tile_map.tiles.get(0).unwrap().len(), 20 * 10);
+        assert_eq!(tile_map.tiles.first().unwrap().len(), 20 * 10);
         assert_eq!(tile_map.tiles.get(1).unwrap().len(), 20 * 10);
 
         // Make sure first layer is fill with 2
@@ -214,6 +646,300 @@ mod tests {
         assert_eq!(size.y, 10);
     }
 
+    #[test]
+    fn test_tile_map_layer_settings() {
+        let mut tile_map = TileMap::new((20, 10), 2, 2);
+
+        assert_eq!(tile_map.layers.len(), 2);
+        assert_eq!(
+            *tile_map.layer_settings(1).unwrap(),
+            LayerSettings::default()
+        );
+        assert!(tile_map.layer_settings(2).is_none());
+
+        let settings = LayerSettings {
+            visible: false,
+            opacity: 0.5,
+            tint: Color::RED,
+            blend_mode: LayerBlendMode::Add,
+            parallax: Vector2f::new(0.5, 0.0),
+            scroll_speed: Vector2f::new(1.0, 0.0),
+            repeat_x: true,
+            repeat_y: false,
+        };
+        assert!(tile_map.set_layer_settings(1, settings).is_ok());
+        assert_eq!(*tile_map.layer_settings(1).unwrap(), settings);
+        assert_eq!(
+            tile_map.set_layer_settings(2, settings).err().unwrap(),
+            TileMapError::InvalidLayer
+        );
+    }
+
+    #[test]
+    fn test_tile_map_write() {
+        let mut tile_map = TileMap::new((4, 3), 2, 2);
+        tile_map.set_tile((1, 1), 1, 12).unwrap();
+        tile_map
+            .place_object(MapObject::new(100, (1, 0), 1, (2, 1)))
+            .unwrap();
+        tile_map
+            .set_layer_settings(
+                0,
+                LayerSettings {
+                    opacity: 0.25,
+                    parallax: Vector2f::new(0.25, 0.5),
+                    scroll_speed: Vector2f::new(-1.0, 0.0),
+                    repeat_x: true,
+                    repeat_y: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let mut bytes = Vec::new();
+        assert!(tile_map.write(&mut bytes).is_ok());
+        assert_eq!(&bytes[..8], b"RLTM\x01\x00\x00\x00");
+
+        let read = TileMap::read(&bytes[..]).unwrap();
+        assert_eq!(read, tile_map);
+    }
+
+    #[test]
+    fn test_tile_map_read_legacy() {
+        /// The tile map layout written before the format was versioned
+        #[derive(Serialize)]
+        struct BaselineTileMap {
+            tiles: Vec<Vec<u32>>,
+            #[serde(with = "Vector2uDef")]
+            size: Vector2u,
+            layer_count: u32,
+        }
+
+        let baseline = BaselineTileMap {
+            tiles: vec![vec![2, 2, 3, 2], vec![0, 0, 0, 7]],
+            size: Vector2u::new(2, 2),
+            layer_count: 2,
+        };
+        let bytes = bincode::serialize(&baseline).unwrap();
+
+        let tile_map = TileMap::read(&bytes[..]).unwrap();
+        assert_eq!(tile_map.size(), (2, 2).into());
+        assert_eq!(tile_map.layer_count(), 2);
+        assert_eq!(tile_map.get_tile((0, 1), 0), Some(3));
+        assert_eq!(tile_map.get_tile((1, 1), 1), Some(7));
+        assert_eq!(tile_map.layer_settings(1), Some(&LayerSettings::default()));
+        assert!(tile_map.objects().is_empty());
+    }
+
+    #[test]
+    fn test_tile_map_read_errors() {
+        let mut bytes = Vec::new();
+        TileMap::new((2, 2), 1, 1).write(&mut bytes).unwrap();
+
+        bytes[4] = 2;
+        assert_eq!(
+            TileMap::read(&bytes[..]),
+            Err(TileMapError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            TileMap::read(&b"RLTM\x01"[..]),
+            Err(TileMapError::ReadError)
+        );
+        assert_eq!(TileMap::read(&[1, 2, 3][..]), Err(TileMapError::ReadError));
+    }
+
+    #[test]
+    fn test_tile_map_read_invalid_content() {
+        let read = |tile_map: &TileMap| {
+            let mut bytes = Vec::new();
+            tile_map.write(&mut bytes).unwrap();
+            TileMap::read(&bytes[..])
+        };
+
+        let mut tile_map = TileMap::new((3, 2), 2, 1);
+        tile_map.tiles[1].pop();
+        assert_eq!(read(&tile_map), Err(TileMapError::InvalidContent));
+
+        let mut tile_map = TileMap::new((3, 2), 2, 1);
+        tile_map.tiles.pop();
+        assert_eq!(read(&tile_map), Err(TileMapError::InvalidContent));
+
+        let mut tile_map = TileMap::new((3, 2), 2, 1);
+        tile_map.layers.push(LayerSettings::default());
+        assert_eq!(read(&tile_map), Err(TileMapError::InvalidContent));
+
+        let mut tile_map = TileMap::new((3, 2), 2, 1);
+        tile_map
+            .objects
+            .push(MapObject::new(100, (2, 0), 1, (2, 1)));
+        assert_eq!(read(&tile_map), Err(TileMapError::InvalidContent));
+
+        let mut tile_map = TileMap::new((3, 2), 2, 1);
+        tile_map
+            .objects
+            .push(MapObject::new(100, (0, 0), 2, (1, 1)));
+        assert_eq!(read(&tile_map), Err(TileMapError::InvalidContent));
+
+        // A truncated legacy file is rejected as well
+        let mut legacy = bincode::serialize(&vec![vec![1u32; 5]]).unwrap();
+        legacy.extend_from_slice(&bincode::serialize(&(3u32, 2u32, 1u32)).unwrap());
+        assert_eq!(
+            TileMap::read(&legacy[..]),
+            Err(TileMapError::InvalidContent)
+        );
+    }
+
+    #[test]
+    fn test_tile_map_place_object() {
+        let mut tile_map = TileMap::new((10, 10), 2, 2);
+
+        let house = MapObject::new(100, (2, 3), 1, (3, 1));
+        assert_eq!(tile_map.place_object(house), Ok(vec![]));
+        assert_eq!(tile_map.objects(), &[house]);
+
+        // Overlapping objects are replaced, only on the same layer
+        let tower = MapObject::new(101, (4, 3), 1, (2, 1));
+        let ground = MapObject::new(102, (2, 3), 0, (1, 1));
+        assert_eq!(tile_map.place_object(ground), Ok(vec![]));
+        assert_eq!(tile_map.place_object(tower), Ok(vec![house]));
+        assert_eq!(tile_map.objects(), &[ground, tower]);
+
+        assert_eq!(
+            tile_map.place_object(MapObject::new(100, (8, 3), 1, (3, 1))),
+            Err(TileMapError::InvalidPosition)
+        );
+        assert_eq!(
+            tile_map.place_object(MapObject::new(100, (0, 0), 1, (0, 1))),
+            Err(TileMapError::InvalidPosition)
+        );
+        assert_eq!(
+            tile_map.place_object(MapObject::new(100, (1, u32::MAX), 1, (1, 2))),
+            Err(TileMapError::InvalidPosition)
+        );
+        assert_eq!(
+            tile_map.place_object(MapObject::new(100, (0, 0), 2, (1, 1))),
+            Err(TileMapError::InvalidLayer)
+        );
+    }
+
+    #[test]
+    fn test_tile_map_object_at() {
+        let mut tile_map = TileMap::new((10, 10), 2, 2);
+        let house = MapObject::new(100, (2, 3), 1, (3, 2));
+        tile_map.place_object(house).unwrap();
+
+        assert_eq!(tile_map.object_at((4, 4), 1), Some(&house));
+        assert_eq!(tile_map.object_at((2, 3), 1), Some(&house));
+        assert_eq!(tile_map.object_at((5, 3), 1), None);
+        assert_eq!(tile_map.object_at((2, 3), 0), None);
+    }
+
+    #[test]
+    fn test_tile_map_remove_object_at() {
+        let mut tile_map = TileMap::new((10, 10), 2, 2);
+        let house = MapObject::new(100, (2, 3), 1, (3, 1));
+        tile_map.place_object(house).unwrap();
+
+        assert_eq!(tile_map.remove_object_at((1, 3), 1), None);
+        assert_eq!(tile_map.remove_object_at((4, 3), 1), Some(house));
+        assert!(tile_map.objects().is_empty());
+    }
+
+    #[test]
+    fn test_tile_map_sorted_objects() {
+        let mut tile_map = TileMap::new((10, 10), 2, 2);
+        let tower = MapObject::new(101, (0, 2), 1, (2, 2));
+        let house = MapObject::new(100, (5, 1), 1, (3, 1));
+        let tree = MapObject::new(102, (3, 3), 1, (1, 1));
+        let ground = MapObject::new(103, (0, 0), 0, (1, 1));
+        for object in [tower, house, tree, ground] {
+            tile_map.place_object(object).unwrap();
+        }
+
+        // Sorted by the bottom row of their footprint, then from left to right
+        assert_eq!(tile_map.sorted_objects(1), vec![house, tower, tree]);
+        assert_eq!(tile_map.sorted_objects(0), vec![ground]);
+    }
+
+    #[test]
+    fn test_tile_map_take_changes() {
+        let mut tile_map = TileMap::new((10, 10), 2, 2);
+        assert!(!tile_map.has_changes());
+
+        // Setting a tile to its current value is not a change
+        tile_map.set_tile((1, 2), 0, 2).unwrap();
+        assert!(!tile_map.has_changes());
+
+        // Nor is setting a layer to its current settings
+        tile_map
+            .set_layer_settings(0, LayerSettings::default())
+            .unwrap();
+        assert!(!tile_map.has_changes());
+
+        // Repeated edits of the same cell are only reported once
+        tile_map.set_tile((3, 1), 1, 5).unwrap();
+        tile_map.set_tile((1, 2), 1, 5).unwrap();
+        tile_map.set_tile((1, 2), 1, 6).unwrap();
+        tile_map.set_tile((1, 2), 1, 5).unwrap();
+        tile_map
+            .set_layer_settings(
+                1,
+                LayerSettings {
+                    visible: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        tile_map
+            .place_object(MapObject::new(100, (2, 3), 1, (3, 1)))
+            .unwrap();
+        tile_map
+            .place_object(MapObject::new(100, (2, 3), 1, (3, 1)))
+            .unwrap();
+        tile_map.remove_object_at((0, 0), 1);
+        assert!(tile_map.set_tile((10, 2), 1, 5).is_err());
+        assert!(tile_map.has_changes());
+
+        assert_eq!(
+            tile_map.take_changes(),
+            vec![
+                TileMapChange::LayerSettings { layer: 1 },
+                TileMapChange::Tile {
+                    position: (3, 1).into(),
+                    layer: 1
+                },
+                TileMapChange::Tile {
+                    position: (1, 2).into(),
+                    layer: 1
+                },
+                TileMapChange::Objects,
+            ]
+        );
+        assert!(!tile_map.has_changes());
+        assert!(tile_map.take_changes().is_empty());
+    }
+
+    #[test]
+    fn test_map_object_overlaps() {
+        let house = MapObject::new(100, (2, 3), 1, (3, 1));
+
+        assert!(house.overlaps(&MapObject::new(101, (4, 2), 1, (1, 2))));
+        assert!(!house.overlaps(&MapObject::new(101, (5, 3), 1, (1, 1))));
+        assert!(!house.overlaps(&MapObject::new(101, (2, 3), 0, (1, 1))));
+    }
+
+    #[test]
+    fn test_layer_settings_color() {
+        let settings = LayerSettings {
+            opacity: 0.5,
+            tint: Color::rgb(255, 128, 0),
+            ..Default::default()
+        };
+
+        assert_eq!(settings.color(), Color::rgba(255, 128, 0, 127));
+        assert_eq!(LayerSettings::default().color(), Color::WHITE);
+    }
+
     #[test]
     fn test_tile_layer_count() {
         let tile_map = TileMap::new((20, 10), 2, 2);
@@ -0,0 +1,406 @@
+use crate::tilemap::{Animation, TILE_ID_MASK};
+use sfml::graphics::{Image, IntRect, Texture};
+use sfml::system::{SfBox, Vector2i, Vector2u};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tile is the graphical definition of a tile inside the tileset atlas
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tile {
+    /// the location of the tile inside the atlas texture
+    pub rect: IntRect,
+    /// the position of the tile cell top left corner, relative to the tile rect (in pixels)
+    /// this allows tiles bigger than a cell to overflow on the neighbours cells
+    pub anchor: Vector2i,
+}
+
+/// Tileset is the graphical representation of the tiles
+/// it holds a single atlas and the location of each tile inside it
+/// the atlas is a GPU texture by default, but a tileset can also be built on a CPU image
+/// (i.e for the headless rasterizer)
+pub struct Tileset<A = SfBox<Texture>> {
+    atlas: A,
+    /// the size of a tile cell (in pixels)
+    tile_size: Vector2u,
+    tiles: BTreeMap<u32, Tile>,
+    animations: BTreeMap<u32, Animation>,
+    /// the properties (i.e walkable) set on each tile
+    properties: BTreeMap<u32, BTreeSet<String>>,
+}
+
+impl Tileset {
+    /// Create a new tileset by packing given textures into a single atlas
+    /// the textures may be bigger than the tile cell size, in this case they will
+    /// be anchored on the bottom left corner of their cell
+    /// this will return None if the atlas cannot be created
+    pub fn from_textures<T: Into<Vector2u>>(
+        textures: &BTreeMap<u32, SfBox<Texture>>,
+        tile_size: T,
+    ) -> Option<Self> {
+        let mut images = BTreeMap::new();
+        for (id, texture) in textures {
+            images.insert(*id, texture.copy_to_image()?);
+        }
+
+        Tileset::from_images(&images, tile_size).upload()
+    }
+
+    /// Retrieve the atlas texture
+    pub fn texture(&self) -> &Texture {
+        &self.atlas
+    }
+}
+
+impl Tileset<Image> {
+    /// Create a new tileset by packing given images into a single atlas image
+    /// the images may be bigger than the tile cell size, in this case they will
+    /// be anchored on the bottom left corner of their cell
+    pub fn from_images<T: Into<Vector2u>>(images: &BTreeMap<u32, Image>, tile_size: T) -> Self {
+        // Use the biggest image as cell size so that every tile fit in the atlas grid
+        let cell_size = images.values().fold((1, 1), |(w, h), image| {
+            let size = image.size();
+            (w.max(size.x), h.max(size.y))
+        });
+        let columns = (images.len() as f32).sqrt().ceil().max(1.0) as u32;
+        let rows = (images.len() as u32).div_ceil(columns);
+
+        let mut atlas = Image::new(columns * cell_size.0, rows.max(1) * cell_size.1);
+        let mut rects = BTreeMap::new();
+
+        for (i, (id, image)) in images.iter().enumerate() {
+            let size = image.size();
+            let x = (i as u32 % columns) * cell_size.0;
+            let y = (i as u32 / columns) * cell_size.1;
+
+            atlas.copy_image(
+                image,
+                x,
+                y,
+                &IntRect::new(0, 0, size.x as i32, size.y as i32),
+                false,
+            );
+            rects.insert(
+                *id,
+                IntRect::new(x as i32, y as i32, size.x as i32, size.y as i32),
+            );
+        }
+
+        let mut tileset = Tileset::new(atlas, tile_size);
+        for (id, rect) in rects {
+            tileset.add_tile(id, rect);
+        }
+
+        tileset
+    }
+
+    /// Create a copy of the tileset using a GPU texture created from the atlas image
+    /// this will return None if the texture cannot be created
+    pub fn upload(&self) -> Option<Tileset> {
+        Some(Tileset {
+            atlas: Texture::from_image(&self.atlas)?,
+            tile_size: self.tile_size,
+            tiles: self.tiles.clone(),
+            animations: self.animations.clone(),
+            properties: self.properties.clone(),
+        })
+    }
+}
+
+impl<A> Tileset<A> {
+    /// Create a new empty tileset using given atlas and tile cell size (in pixels)
+    pub fn new<T: Into<Vector2u>>(atlas: A, tile_size: T) -> Self {
+        Tileset {
+            atlas,
+            tile_size: tile_size.into(),
+            tiles: BTreeMap::new(),
+            animations: BTreeMap::new(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    /// Retrieve the atlas (texture or image)
+    pub fn atlas(&self) -> &A {
+        &self.atlas
+    }
+
+    /// Register a tile located at given rect of the atlas texture
+    /// if the tile is bigger than a cell it will be anchored on the bottom left corner of its cell
+    pub fn add_tile(&mut self, tile_id: u32, rect: IntRect) {
+        let anchor = Vector2i::new(0, (rect.height - self.tile_size.y as i32).max(0));
+        self.add_tile_with_anchor(tile_id, rect, anchor);
+    }
+
+    /// Register a tile located at given rect of the atlas texture
+    /// the anchor is the position of the tile cell top left corner, relative to the rect
+    pub fn add_tile_with_anchor<P: Into<Vector2i>>(
+        &mut self,
+        tile_id: u32,
+        rect: IntRect,
+        anchor: P,
+    ) {
+        self.tiles.insert(
+            tile_id,
+            Tile {
+                rect,
+                anchor: anchor.into(),
+            },
+        );
+    }
+
+    /// Register a multi-cell object located at given rect of the atlas texture
+    /// the footprint is the number of cells covered by the object (i.e its base),
+    /// the object is anchored on the bottom left corner of its footprint
+    /// and may overflow above it (i.e a house roof or a tree top)
+    pub fn add_object<T: Into<Vector2u>>(&mut self, tile_id: u32, rect: IntRect, footprint: T) {
+        let footprint_height = (footprint.into().y * self.tile_size.y) as i32;
+        self.tiles.insert(
+            tile_id,
+            Tile {
+                rect,
+                anchor: Vector2i::new(0, (rect.height - footprint_height).max(0)),
+            },
+        );
+    }
+
+    /// Register an animated tile
+    /// the animation frames should reference tiles registered in the tileset
+    pub fn add_animation(&mut self, tile_id: u32, animation: Animation) {
+        self.animations.insert(tile_id, animation);
+    }
+
+    /// Retrieve the animation of given tile (the flip flags are ignored)
+    /// this will return None if the tile is not animated
+    pub fn animation(&self, tile_id: u32) -> Option<&Animation> {
+        self.animations.get(&(tile_id & TILE_ID_MASK))
+    }
+
+    /// Retrieve given tile definition (the flip flags are ignored)
+    /// this will return None if the tile doesn't exist
+    pub fn tile(&self, tile_id: u32) -> Option<Tile> {
+        self.tiles.get(&(tile_id & TILE_ID_MASK)).copied()
+    }
+
+    /// Retrieve the location of given tile inside the atlas texture
+    /// this will return None if the tile doesn't exist
+    pub fn tile_rect(&self, tile_id: u32) -> Option<IntRect> {
+        self.tile(tile_id).map(|tile| tile.rect)
+    }
+
+    /// Set or unset given property (i.e walkable) on the tile of given id
+    pub fn set_tile_property(&mut self, tile_id: u32, property: &str, value: bool) {
+        let properties = self.properties.entry(tile_id & TILE_ID_MASK).or_default();
+        if value {
+            properties.insert(property.to_string());
+        } else {
+            properties.remove(property);
+        }
+    }
+
+    /// Determinate if the tile of given id has given property
+    pub fn has_property(&self, tile_id: u32, property: &str) -> bool {
+        self.properties
+            .get(&(tile_id & TILE_ID_MASK))
+            .is_some_and(|properties| properties.contains(property))
+    }
+
+    /// Retrieve the size of a tile cell (in pixels)
+    pub fn tile_size(&self) -> Vector2u {
+        self.tile_size
+    }
+
+    /// Compute how far (in pixels) the tiles may overflow from their cell
+    /// the first value is the overflow on the top / left side, the second one on the bottom / right side
+    pub fn max_overflow(&self) -> (Vector2u, Vector2u) {
+        self.tiles
+            .values()
+            .fold(Default::default(), |(before, after), tile| {
+                let after_x = tile.rect.width - tile.anchor.x - self.tile_size.x as i32;
+                let after_y = tile.rect.height - tile.anchor.y - self.tile_size.y as i32;
+                (
+                    Vector2u::new(
+                        before.x.max(tile.anchor.x.max(0) as u32),
+                        before.y.max(tile.anchor.y.max(0) as u32),
+                    ),
+                    Vector2u::new(
+                        after.x.max(after_x.max(0) as u32),
+                        after.y.max(after_y.max(0) as u32),
+                    ),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::{
+        AnimationFrame, AnimationMode, AnimationPhase, FLIP_HORIZONTAL, FLIP_VERTICAL,
+    };
+    use sfml::graphics::Color;
+
+    #[test]
+    fn test_tileset_from_textures() {
+        let mut textures = BTreeMap::new();
+        for i in 0..5 {
+            textures.insert(i + 1, Texture::new(16, 16).unwrap());
+        }
+
+        let tileset = Tileset::from_textures(&textures, (16, 16)).unwrap();
+
+        // 5 textures will be packed into a 3x2 grid
+        assert_eq!(tileset.texture().size(), (48, 32).into());
+        assert_eq!(tileset.tile_size(), (16, 16).into());
+        assert_eq!(tileset.tile_rect(1), Some(IntRect::new(0, 0, 16, 16)));
+        assert_eq!(tileset.tile_rect(3), Some(IntRect::new(32, 0, 16, 16)));
+        assert_eq!(tileset.tile_rect(4), Some(IntRect::new(0, 16, 16, 16)));
+        assert!(tileset.tile_rect(6).is_none());
+    }
+
+    #[test]
+    fn test_tileset_from_textures_mixed_sizes() {
+        let mut textures = BTreeMap::new();
+        textures.insert(1, Texture::new(16, 16).unwrap());
+        textures.insert(2, Texture::new(32, 32).unwrap());
+
+        let tileset = Tileset::from_textures(&textures, (16, 16)).unwrap();
+
+        // Atlas grid use the biggest texture as cell size
+        assert_eq!(tileset.texture().size(), (64, 32).into());
+        assert_eq!(
+            tileset.tile(1),
+            Some(Tile {
+                rect: IntRect::new(0, 0, 16, 16),
+                anchor: (0, 0).into(),
+            })
+        );
+        assert_eq!(
+            tileset.tile(2),
+            Some(Tile {
+                rect: IntRect::new(32, 0, 32, 32),
+                anchor: (0, 16).into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_tileset_from_images() {
+        let mut images = BTreeMap::new();
+        images.insert(1, Image::from_color(16, 16, Color::RED).unwrap());
+        images.insert(2, Image::from_color(16, 32, Color::BLUE).unwrap());
+
+        let tileset = Tileset::from_images(&images, (16, 16));
+
+        assert_eq!(tileset.atlas().size(), (32, 32).into());
+        assert_eq!(tileset.tile_rect(2), Some(IntRect::new(16, 0, 16, 32)));
+        assert_eq!(tileset.tile(2).unwrap().anchor, (0, 16).into());
+        assert_eq!(tileset.atlas().pixel_at(0, 0), Color::RED);
+        assert_eq!(tileset.atlas().pixel_at(16, 31), Color::BLUE);
+    }
+
+    #[test]
+    fn test_tileset_upload() {
+        let mut images = BTreeMap::new();
+        images.insert(1, Image::from_color(16, 16, Color::RED).unwrap());
+        let mut tileset = Tileset::from_images(&images, (16, 16));
+        tileset.add_animation(
+            3,
+            Animation {
+                frames: vec![],
+                mode: AnimationMode::Loop,
+                phase: AnimationPhase::Synchronized,
+            },
+        );
+
+        tileset.set_tile_property(1, "walkable", true);
+
+        let uploaded = tileset.upload().unwrap();
+
+        assert_eq!(uploaded.texture().size(), (16, 16).into());
+        assert_eq!(uploaded.tile(1), tileset.tile(1));
+        assert_eq!(uploaded.animation(3), tileset.animation(3));
+        assert!(uploaded.has_property(1, "walkable"));
+    }
+
+    #[test]
+    fn test_tileset_add_tile() {
+        let mut tileset = Tileset::new(Texture::new(32, 32).unwrap(), (16, 16));
+        assert!(tileset.tile_rect(1).is_none());
+
+        tileset.add_tile(1, IntRect::new(16, 0, 16, 16));
+
+        assert_eq!(tileset.tile_rect(1), Some(IntRect::new(16, 0, 16, 16)));
+        assert_eq!(tileset.tile(1).unwrap().anchor, (0, 0).into());
+
+        // Flip flags are ignored
+        assert_eq!(
+            tileset.tile_rect(1 | FLIP_HORIZONTAL | FLIP_VERTICAL),
+            Some(IntRect::new(16, 0, 16, 16))
+        );
+    }
+
+    #[test]
+    fn test_tileset_add_tile_with_anchor() {
+        let mut tileset = Tileset::new(Texture::new(64, 64).unwrap(), (16, 16));
+
+        tileset.add_tile_with_anchor(1, IntRect::new(0, 0, 48, 32), (16, 16));
+
+        assert_eq!(tileset.tile(1).unwrap().anchor, (16, 16).into());
+    }
+
+    #[test]
+    fn test_tileset_add_object() {
+        let mut tileset = Tileset::new(Texture::new(64, 64).unwrap(), (16, 16));
+
+        // A 3x2 cells house with a 3x1 cells base
+        tileset.add_object(1, IntRect::new(0, 0, 48, 32), (3, 1));
+
+        let tile = tileset.tile(1).unwrap();
+        assert_eq!(tile.anchor, (0, 16).into());
+        assert_eq!(tileset.max_overflow(), ((0, 16).into(), (32, 0).into()));
+    }
+
+    #[test]
+    fn test_tileset_set_tile_property() {
+        let mut tileset = Tileset::new(Texture::new(16, 16).unwrap(), (16, 16));
+        assert!(!tileset.has_property(1, "walkable"));
+
+        tileset.set_tile_property(1, "walkable", true);
+        assert!(tileset.has_property(1, "walkable"));
+        assert!(tileset.has_property(1 | FLIP_HORIZONTAL, "walkable"));
+        assert!(!tileset.has_property(1, "water"));
+        assert!(!tileset.has_property(2, "walkable"));
+
+        tileset.set_tile_property(1, "walkable", false);
+        assert!(!tileset.has_property(1, "walkable"));
+    }
+
+    #[test]
+    fn test_tileset_add_animation() {
+        let mut tileset = Tileset::new(Texture::new(32, 32).unwrap(), (16, 16));
+        assert!(tileset.animation(3).is_none());
+
+        let animation = Animation {
+            frames: vec![AnimationFrame {
+                tile_id: 1,
+                duration: 1.0,
+            }],
+            mode: AnimationMode::Loop,
+            phase: AnimationPhase::Synchronized,
+        };
+        tileset.add_animation(3, animation.clone());
+
+        assert_eq!(tileset.animation(3), Some(&animation));
+        assert!(tileset.tile(3).is_none());
+    }
+
+    #[test]
+    fn test_tileset_max_overflow() {
+        let mut tileset = Tileset::new(Texture::new(64, 64).unwrap(), (16, 16));
+        assert_eq!(tileset.max_overflow(), ((0, 0).into(), (0, 0).into()));
+
+        tileset.add_tile(1, IntRect::new(0, 0, 16, 16));
+        tileset.add_tile(2, IntRect::new(0, 0, 32, 32));
+        tileset.add_tile_with_anchor(3, IntRect::new(0, 0, 48, 16), (16, 0));
+
+        assert_eq!(tileset.max_overflow(), ((16, 16).into(), (16, 0).into()));
+    }
+}
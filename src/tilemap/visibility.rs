@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use sfml::system::Vector2u;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{Read, Write};
+
+use crate::tilemap::tilemap::Vector2uDef;
+use crate::tilemap::TileMapError;
+
+/// Visibility is the fog of war state of a cell
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub enum Visibility {
+    /// The cell has never been seen, it is hidden
+    Unseen,
+    /// The cell has been seen but is not in sight anymore, it is darkened
+    Explored,
+    /// The cell is in sight
+    Visible,
+}
+
+/// VisibilityGrid holds the visibility of each cell of a tile map
+/// it is saved separately from the tile map (i.e in the save games)
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct VisibilityGrid {
+    /// the cells visibility, in row major order
+    cells: Vec<Visibility>,
+    /// the grid size (should match the tile map size)
+    #[serde(with = "Vector2uDef")]
+    size: Vector2u,
+}
+
+impl VisibilityGrid {
+    /// Create a new grid of given size, where every cell is unseen
+    pub fn new<T: Into<Vector2u>>(size: T) -> Self {
+        let size = size.into();
+        VisibilityGrid {
+            cells: vec![Visibility::Unseen; (size.x * size.y) as usize],
+            size,
+        }
+    }
+
+    /// Retrieve the grid size
+    pub fn size(&self) -> Vector2u {
+        self.size
+    }
+
+    /// Retrieve the visibility of the cell at given position
+    /// this will return None if the position doesn't exist
+    pub fn visibility<T: Into<Vector2u>>(&self, position: T) -> Option<Visibility> {
+        let index = self.compute_index(position.into())?;
+        self.cells.get(index).copied()
+    }
+
+    /// Set the visibility of the cell at given position
+    /// this operation will fails if the position doesn't exist
+    pub fn set_visibility<T: Into<Vector2u>>(
+        &mut self,
+        position: T,
+        visibility: Visibility,
+    ) -> Result<(), TileMapError> {
+        let index = self
+            .compute_index(position.into())
+            .ok_or(TileMapError::InvalidPosition)?;
+        self.cells[index] = visibility;
+
+        Ok(())
+    }
+
+    /// Make visible the cells within given radius (in tiles) around given position
+    /// the cells outside the grid are ignored
+    pub fn reveal<T: Into<Vector2u>>(&mut self, center: T, radius: u32) {
+        let center = center.into();
+        let start_x = center.x.saturating_sub(radius);
+        let start_y = center.y.saturating_sub(radius);
+        let end_x = center
+            .x
+            .saturating_add(radius)
+            .saturating_add(1)
+            .min(self.size.x);
+        let end_y = center
+            .y
+            .saturating_add(radius)
+            .saturating_add(1)
+            .min(self.size.y);
+
+        // The distances are squared in 64 bits so large radiuses don't overflow
+        let radius = radius as u64;
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let (dx, dy) = (x.abs_diff(center.x) as u64, y.abs_diff(center.y) as u64);
+                if dx * dx + dy * dy <= radius * radius {
+                    let index = (x + y * self.size.x) as usize;
+                    self.cells[index] = Visibility::Visible;
+                }
+            }
+        }
+    }
+
+    /// Turn the visible cells into explored ones
+    /// i.e before revealing the cells currently in sight
+    pub fn fade(&mut self) {
+        for cell in &mut self.cells {
+            if *cell == Visibility::Visible {
+                *cell = Visibility::Explored;
+            }
+        }
+    }
+
+    /// Write the visibility grid to given writer
+    pub fn write(&self, mut writer: impl Write) -> Result<(), TileMapError> {
+        let bytes: Vec<u8> = bincode::serialize(&self).map_err(|_| TileMapError::WriteError)?;
+        writer
+            .write_all(&bytes)
+            .map_err(|_| TileMapError::WriteError)
+    }
+
+    /// Read a visibility grid from given reader
+    /// this will fails if the content is invalid, i.e if the cells count doesn't match the grid size
+    pub fn read(reader: impl Read) -> Result<Self, TileMapError> {
+        let grid: VisibilityGrid =
+            bincode::deserialize_from(reader).map_err(|_| TileMapError::ReadError)?;
+        let cell_count = (grid.size.x as usize).checked_mul(grid.size.y as usize);
+        if cell_count != Some(grid.cells.len()) {
+            return Err(TileMapError::ReadError);
+        }
+
+        Ok(grid)
+    }
+
+    /// Compute the vector index from given position
+    fn compute_index(&self, position: Vector2u) -> Option<usize> {
+        if position.x >= self.size.x || position.y >= self.size.y {
+            return None;
+        }
+
+        Some((position.x + position.y * self.size.x) as usize)
+    }
+}
+
+impl TryFrom<File> for VisibilityGrid {
+    type Error = TileMapError;
+
+    fn try_from(value: File) -> Result<Self, Self::Error> {
+        VisibilityGrid::read(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visibility_grid_new() {
+        let grid = VisibilityGrid::new((4, 3));
+
+        assert_eq!(grid.size(), (4, 3).into());
+        assert_eq!(grid.cells.len(), 12);
+        assert!(grid.cells.iter().all(|cell| *cell == Visibility::Unseen));
+    }
+
+    #[test]
+    fn test_visibility_grid_set_visibility() {
+        let mut grid = VisibilityGrid::new((4, 3));
+
+        assert!(grid.set_visibility((3, 2), Visibility::Explored).is_ok());
+        assert_eq!(grid.visibility((3, 2)), Some(Visibility::Explored));
+        assert_eq!(grid.visibility((2, 2)), Some(Visibility::Unseen));
+        assert_eq!(grid.visibility((4, 2)), None);
+        assert_eq!(
+            grid.set_visibility((4, 2), Visibility::Visible),
+            Err(TileMapError::InvalidPosition)
+        );
+    }
+
+    #[test]
+    fn test_visibility_grid_reveal() {
+        let mut grid = VisibilityGrid::new((5, 5));
+        grid.reveal((1, 1), 1);
+
+        for (position, visibility) in [
+            ((1, 1), Visibility::Visible),
+            ((0, 1), Visibility::Visible),
+            ((1, 2), Visibility::Visible),
+            ((2, 1), Visibility::Visible),
+            ((1, 0), Visibility::Visible),
+            ((0, 0), Visibility::Unseen),
+            ((2, 2), Visibility::Unseen),
+            ((3, 1), Visibility::Unseen),
+        ] {
+            assert_eq!(grid.visibility(position), Some(visibility));
+        }
+
+        // Cells outside the grid are ignored
+        grid.reveal((4, 4), 2);
+        assert_eq!(grid.visibility((4, 2)), Some(Visibility::Visible));
+        assert_eq!(grid.visibility((3, 3)), Some(Visibility::Visible));
+
+        // Huge radiuses don't overflow
+        let mut grid = VisibilityGrid::new((3, 3));
+        grid.reveal((2, 2), u32::MAX);
+        assert!(grid.cells.iter().all(|cell| *cell == Visibility::Visible));
+    }
+
+    #[test]
+    fn test_visibility_grid_fade() {
+        let mut grid = VisibilityGrid::new((3, 1));
+        grid.set_visibility((0, 0), Visibility::Visible).unwrap();
+        grid.set_visibility((1, 0), Visibility::Explored).unwrap();
+
+        grid.fade();
+        assert_eq!(grid.visibility((0, 0)), Some(Visibility::Explored));
+        assert_eq!(grid.visibility((1, 0)), Some(Visibility::Explored));
+        assert_eq!(grid.visibility((2, 0)), Some(Visibility::Unseen));
+    }
+
+    #[test]
+    fn test_visibility_grid_write() {
+        let mut grid = VisibilityGrid::new((4, 3));
+        grid.reveal((2, 1), 1);
+
+        let mut bytes = Vec::new();
+        assert!(grid.write(&mut bytes).is_ok());
+
+        let read = VisibilityGrid::read(&bytes[..]).unwrap();
+        assert_eq!(read, grid);
+    }
+
+    #[test]
+    fn test_visibility_grid_read_invalid() {
+        // The cells count doesn't match the grid size
+        let mut grid = VisibilityGrid::new((4, 3));
+        grid.cells.pop();
+
+        let mut bytes = Vec::new();
+        assert!(grid.write(&mut bytes).is_ok());
+        assert_eq!(
+            VisibilityGrid::read(&bytes[..]),
+            Err(TileMapError::ReadError)
+        );
+        assert_eq!(
+            VisibilityGrid::read(&b"oops"[..]),
+            Err(TileMapError::ReadError)
+        );
+    }
+}